@@ -18,6 +18,9 @@ interface Boid {
 
 const BOID_STRIDE = 5;
 
+// Same generators as the Voronoi demo; white noise is createRandomBoids
+const DISTRIBUTIONS = ['White noise', 'Poisson disk', 'Halton', 'Sobol', 'R2', 'Jittered grid'] as const
+
 function boidsToFloat32Array(boids: Boid[]): Float32Array {
   const arr = new Float32Array(boids.length * BOID_STRIDE);
   for (let i = 0; i < boids.length; i++) {
@@ -71,6 +74,46 @@ function createRandomBoids(count: number, width: number, height: number, maxSpee
   return boids;
 }
 
+// The samplers emit [x, y, vx, vy]; add the flags of a boid to each point
+function pointsToBoids(points: Float32Array | number[]): Boid[] {
+  const boids: Boid[] = [];
+  for (let i = 0; i + 3 < points.length; i += 4) {
+    boids.push({
+      x: points[i],
+      y: points[i + 1],
+      vx: points[i + 2],
+      vy: points[i + 3],
+      flags: 0,
+    });
+  }
+  return boids;
+}
+
+// Initial boids from the selected generator, with a fresh seed each time
+function createBoids(distribution: number, count: number, width: number, height: number, maxSpeed: number): Boid[] {
+  const S = silly_demos.SamplingTests
+  const seed = (Math.random() * 0x100000000) >>> 0
+  switch (DISTRIBUTIONS[distribution]) {
+    case 'Poisson disk': {
+      // Bridson's sampler places about 0.63 points per r² of area
+      const settings = new silly_demos.PoissonDisk(Math.sqrt(0.63 * width * height / count))
+      const points = S.poisson_disk(width, height, settings, [], seed, maxSpeed)
+      settings.free()
+      return pointsToBoids(points)
+    }
+    case 'Halton':
+      return pointsToBoids(S.low_discrepancy(silly_demos.Sequence.Halton, count, width, height, seed, maxSpeed))
+    case 'Sobol':
+      return pointsToBoids(S.low_discrepancy(silly_demos.Sequence.Sobol, count, width, height, seed, maxSpeed))
+    case 'R2':
+      return pointsToBoids(S.low_discrepancy(silly_demos.Sequence.R2, count, width, height, seed, maxSpeed))
+    case 'Jittered grid':
+      return pointsToBoids(S.jittered_grid(count, width, height, 0.8, seed, maxSpeed))
+    default:
+      return createRandomBoids(count, width, height, maxSpeed)
+  }
+}
+
 function BoidsDemo() {
   const canvasRef = useRef<HTMLCanvasElement>(null)
   const [wasm, setWasm] = useState<typeof silly_demos | null>(null)
@@ -80,6 +123,7 @@ function BoidsDemo() {
     visibleCount: 0
   })
   const [isRunning, setIsRunning] = useState(true)
+  const [distribution, setDistribution] = useState(0)
 
   // Visualization toggles
   const [showSeparation, setShowSeparation] = useState(false)
@@ -142,10 +186,10 @@ function BoidsDemo() {
     canvas.style.height = displayHeight + 'px'
 
     // Create boids as array of struct
-    const boids = createRandomBoids(config.boidCount, displayWidth, displayHeight, config.maxSpeed)
+    const boids = createBoids(distribution, config.boidCount, displayWidth, displayHeight, config.maxSpeed)
     boidsRef.current = boids
-    setStats(prev => ({ ...prev, boidCount: config.boidCount }))
-  }, [wasm, config.boidCount])
+    setStats(prev => ({ ...prev, boidCount: boids.length }))
+  }, [wasm, config.boidCount, distribution])
 
   // Animation loop
   useEffect(() => {
@@ -317,8 +361,8 @@ function BoidsDemo() {
   const resetSimulation = () => {
     if (!wasm || !canvasRef.current) return
     const canvas = canvasRef.current
-    boidsRef.current = createRandomBoids(config.boidCount, canvas.clientWidth, canvas.clientHeight, config.maxSpeed * 0.5)
-    setStats(prev => ({ ...prev, boidCount: config.boidCount }))
+    boidsRef.current = createBoids(distribution, config.boidCount, canvas.clientWidth, canvas.clientHeight, config.maxSpeed * 0.5)
+    setStats(prev => ({ ...prev, boidCount: boidsRef.current.length }))
   }
 
   if (!wasm) {
@@ -388,6 +432,12 @@ function BoidsDemo() {
                   onChange={(e) => handleConfigChange('boidCount', parseInt(e.target.value))}
                 />
               </label>
+              <label>
+                Distribution
+                <select value={distribution} onChange={e => setDistribution(parseInt(e.target.value, 10))}>
+                  {DISTRIBUTIONS.map((name, i) => <option key={name} value={i}>{name}</option>)}
+                </select>
+              </label>
             </div>
           </div>
 
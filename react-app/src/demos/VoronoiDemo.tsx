@@ -6,10 +6,51 @@ const STRIDE = 4
 
 type Mode = 'triangulation' | 'voronoi'
 
+// Mirrors the PointMotion and PointBoundary enums on the Rust side
+const MOTIONS = ['Drift', 'Brownian', 'Vortex', 'Curl noise', 'Repulsion'] as const
+const BOUNDARIES = ['Respawn', 'Bounce', 'Wrap'] as const
+const DISTRIBUTIONS = ['White noise', 'Poisson disk', 'Halton', 'Sobol', 'R2', 'Jittered grid'] as const
+
 function toF32(arr: Float32Array | number[]): Float32Array {
     return arr instanceof Float32Array ? arr : new Float32Array(arr)
 }
 
+// The white noise generator appends the four fixed corners; the others leave
+// them out and rely on the cells being clipped to the canvas
+function hasCorners(distribution: number): boolean {
+    return DISTRIBUTIONS[distribution] === 'White noise'
+}
+
+// Initial points from the selected generator
+function createPoints(distribution: number, count: number, width: number, height: number, seed: number, speed: number): Float32Array {
+    const S = silly_demos.SamplingTests
+    let out: Float32Array | number[]
+    switch (DISTRIBUTIONS[distribution]) {
+        case 'Poisson disk': {
+            // Bridson's sampler places about 0.63 points per r² of area
+            const settings = new silly_demos.PoissonDisk(Math.sqrt(0.63 * width * height / count))
+            out = S.poisson_disk(width, height, settings, [], seed, speed)
+            settings.free()
+            break
+        }
+        case 'Halton':
+            out = S.low_discrepancy(silly_demos.Sequence.Halton, count, width, height, seed, speed)
+            break
+        case 'Sobol':
+            out = S.low_discrepancy(silly_demos.Sequence.Sobol, count, width, height, seed, speed)
+            break
+        case 'R2':
+            out = S.low_discrepancy(silly_demos.Sequence.R2, count, width, height, seed, speed)
+            break
+        case 'Jittered grid':
+            out = S.jittered_grid(count, width, height, 0.8, seed, speed)
+            break
+        default:
+            out = silly_demos.VoronoiTests.voronoi_create_points(count, width, height, seed, speed)
+    }
+    return toF32(out)
+}
+
 function useHiDPICanvas(canvasRef: React.RefObject<HTMLCanvasElement | null>) {
   useEffect(() => {
     const initial = canvasRef.current
@@ -44,37 +85,6 @@ function useHiDPICanvas(canvasRef: React.RefObject<HTMLCanvasElement | null>) {
   }, [canvasRef])
 }
 
-// Geometry helpers
-function pointInTriangle(px: number, py: number, ax: number, ay: number, bx: number, by: number, cx: number, cy: number): boolean {
-  // Using barycentric technique with same-side test
-  const v0x = cx - ax, v0y = cy - ay
-  const v1x = bx - ax, v1y = by - ay
-  const v2x = px - ax, v2y = py - ay
-  const dot00 = v0x * v0x + v0y * v0y
-  const dot01 = v0x * v1x + v0y * v1y
-  const dot02 = v0x * v2x + v0y * v2y
-  const dot11 = v1x * v1x + v1y * v1y
-  const dot12 = v1x * v2x + v1y * v2y
-  const denom = dot00 * dot11 - dot01 * dot01
-  if (Math.abs(denom) < 1e-8) return false
-  const inv = 1 / denom
-  const u = (dot11 * dot02 - dot01 * dot12) * inv
-  const v = (dot00 * dot12 - dot01 * dot02) * inv
-  return u >= -1e-6 && v >= -1e-6 && (u + v) <= 1 + 1e-6
-}
-
-function circumcenter(ax: number, ay: number, bx: number, by: number, cx: number, cy: number): [number, number] | null {
-  // Robust-ish circumcenter; returns null for near-collinear
-  const d = 2 * (ax * (by - cy) + bx * (cy - ay) + cx * (ay - by))
-  if (Math.abs(d) < 1e-8) return null
-  const a2 = ax * ax + ay * ay
-  const b2 = bx * bx + by * by
-  const c2 = cx * cx + cy * cy
-  const ux = (a2 * (by - cy) + b2 * (cy - ay) + c2 * (ay - by)) / d
-  const uy = (a2 * (cx - bx) + b2 * (ax - cx) + c2 * (bx - ax)) / d
-  return [ux, uy]
-}
-
 export default function VoronoiDemo() {
     const canvasRef = useRef<HTMLCanvasElement | null>(null)
     const animRef = useRef<number>(0)
@@ -86,9 +96,19 @@ export default function VoronoiDemo() {
     const [seed, setSeed] = useState(1337)
     const [count, setCount] = useState(100)
     const [speed, setSpeed] = useState(5)
+    const [kinetic, setKinetic] = useState(false)
+    const [shadeCells, setShadeCells] = useState(false)
+    const [raster, setRaster] = useState(false)
+    const [motion, setMotion] = useState(0)
+    const [boundary, setBoundary] = useState(0)
+    const [distribution, setDistribution] = useState(0)
 
     // simulation buffer
     const pointsRef = useRef<Float32Array>(new Float32Array())
+    // number of steps taken, seeds the per-step randomness of the dynamics
+    const stepRef = useRef<number>(0)
+    // triangulation kept across frames in kinetic mode
+    const triRef = useRef<any>(null)
 
     // mouse tracking (CSS pixel coordinates in canvas local space)
     const mouseRef = useRef<{ x: number; y: number; inside: boolean }>({x: 0, y: 0, inside: false})
@@ -119,9 +139,11 @@ export default function VoronoiDemo() {
         const width = (canvas as any)._displayWidth || canvas.clientWidth || 800
         const height = (canvas as any)._displayHeight || canvas.clientHeight || 600
 
-        const out = silly_demos.VoronoiTests?.voronoi_create_points?.(count, width, height, seed >>> 0, speed) || []
-        pointsRef.current = toF32(out)
-    }, [wasmReady, count, seed, speed])
+        pointsRef.current = createPoints(distribution, count, width, height, seed >>> 0, speed)
+        stepRef.current = 0
+        triRef.current?.free()
+        triRef.current = null
+    }, [wasmReady, count, seed, speed, distribution])
 
     // Animation loop
     useEffect(() => {
@@ -147,6 +169,34 @@ export default function VoronoiDemo() {
         outerCanvas.addEventListener('mousemove', onMove)
         outerCanvas.addEventListener('mouseleave', onLeave)
 
+        const dynamics = new silly_demos.PointDynamics(motion, boundary, seed >>> 0)
+        dynamics.fixed_corners = hasCorners(distribution)
+
+        function delaunayIndices(pts: Float32Array): Uint32Array | undefined {
+            if (kinetic) {
+                const idxs: Uint32Array | number[] | undefined = triRef.current?.delaunay_indices()
+                if (!idxs) return undefined
+                return idxs instanceof Uint32Array ? idxs : new Uint32Array(idxs)
+            }
+            let result: any
+            try {
+                result = silly_demos.VoronoiTests?.delaunay_indices?.(pts, 0)
+            } catch {
+                // Empty or non-finite input has no triangulation.
+                return undefined
+            }
+            if (!result) return undefined
+            try {
+                // Triangles index the merged vertices; draw each at the first
+                // input point that became it.
+                const first = new Uint32Array(result.vertices.length / 2)
+                for (let i = result.vertex_of.length - 1; i >= 0; i--) first[result.vertex_of[i]] = i
+                return (result.triangles as Uint32Array).map(v => first[v])
+            } finally {
+                result.free()
+            }
+        }
+
         function drawPoints(pts: Float32Array) {
             ctx.fillStyle = '#ffffff'
             const r = 2.2
@@ -159,10 +209,9 @@ export default function VoronoiDemo() {
             }
         }
 
-        function drawTriangulation(pts: Float32Array, indices?: Uint32Array) {
-            const idxs = indices ?? (silly_demos.VoronoiTests?.delaunay_indices?.(pts) as (Uint32Array | number[] | undefined))
-            if (!idxs) return
-            const I = idxs instanceof Uint32Array ? idxs : new Uint32Array(idxs)
+        function drawTriangulation(pts: Float32Array) {
+            const I = delaunayIndices(pts)
+            if (!I) return
             ctx.strokeStyle = '#66ccff'
             ctx.lineWidth = 1
             ctx.beginPath()
@@ -184,23 +233,67 @@ export default function VoronoiDemo() {
             drawPoints(pts)
         }
 
-        function drawVoronoi(pts: Float32Array) {
-            const segs = silly_demos.VoronoiTests?.voronoi_edges?.(pts) as (Float32Array | number[] | undefined)
-            if (!segs) return
-            const S = segs instanceof Float32Array ? segs : new Float32Array(segs)
+        function getCells(pts: Float32Array, width: number, height: number): { coords: Float32Array, offsets: Uint32Array } | undefined {
+            const cells = silly_demos.VoronoiTests?.voronoi_cells?.(pts, 0, 0, width, height)
+            if (!cells) return undefined
+            const coords = cells.coords as Float32Array
+            const offsets = cells.offsets as Uint32Array
+            cells.free?.()
+            return {coords, offsets}
+        }
+
+        function traceCell(coords: Float32Array, offsets: Uint32Array, site: number) {
+            const start = offsets[site], end = offsets[site + 1]
+            if (end - start < 3) return false
+            ctx.moveTo(coords[start * 2], coords[start * 2 + 1])
+            for (let k = start + 1; k < end; k++) ctx.lineTo(coords[k * 2], coords[k * 2 + 1])
+            ctx.closePath()
+            return true
+        }
+
+        // Fill every cell by its area relative to the mean: small cells blue, large ones red
+        function shadeByArea(pts: Float32Array, cells: { coords: Float32Array, offsets: Uint32Array }, width: number, height: number) {
+            const stats = silly_demos.VoronoiTests?.voronoi_cell_stats?.(pts, 0, 0, width, height)
+            if (!stats) return
+            const areas = stats.areas as Float32Array
+            stats.free?.()
+            const mean = (width * height) / Math.max(1, areas.length)
+            for (let site = 0; site < areas.length; site++) {
+                const t = Math.max(-1, Math.min(1, Math.log2(areas[site] / mean) / 2))
+                if (!Number.isFinite(t)) continue
+                ctx.beginPath()
+                if (!traceCell(cells.coords, cells.offsets, site)) continue
+                ctx.fillStyle = `hsla(${220 - 220 * (t + 1) / 2}, 70%, 50%, 0.35)`
+                ctx.fill()
+            }
+        }
+
+        function drawVoronoi(pts: Float32Array, width: number, height: number, cells?: { coords: Float32Array, offsets: Uint32Array }) {
+            if (!cells) return
+            if (shadeCells) shadeByArea(pts, cells, width, height)
             ctx.strokeStyle = '#ffaa66'
             ctx.lineWidth = 1
             ctx.beginPath()
-            for (let i = 0; i + 3 < S.length; i += 4) {
-                const x1 = S[i], y1 = S[i + 1]
-                const x2 = S[i + 2], y2 = S[i + 3]
-                ctx.moveTo(x1, y1)
-                ctx.lineTo(x2, y2)
+            for (let site = 0; site + 1 < cells.offsets.length; site++) {
+                traceCell(cells.coords, cells.offsets, site)
             }
             ctx.stroke()
             drawPoints(pts)
         }
 
+        // putImageData ignores the canvas transform, so render at device pixels
+        function drawRaster(pts: Float32Array, width: number, height: number) {
+            const c = canvasRef.current!
+            const style = new silly_demos.RenderStyle()
+            style.scale = c.width / width
+            if (mode === 'triangulation') style.edge_color = 0x66CCFFFF
+            const rgba = mode === 'triangulation'
+                ? silly_demos.VoronoiTests.render_delaunay(pts, c.width, c.height, style)
+                : silly_demos.VoronoiTests.render_voronoi(pts, c.width, c.height, [], style)
+            style.free()
+            ctx.putImageData(new ImageData(new Uint8ClampedArray(rgba.buffer, rgba.byteOffset, rgba.byteLength), c.width, c.height), 0, 0)
+        }
+
         function frame(ts: number) {
             const c = canvasRef.current
             if (!c) {
@@ -214,116 +307,92 @@ export default function VoronoiDemo() {
 
             // Step
             if (!paused && pointsRef.current.length > 0) {
-                const next = silly_demos.VoronoiTests?.voronoi_step_points?.(pointsRef.current, width, height, dt) || pointsRef.current
+                const next = silly_demos.VoronoiTests?.voronoi_step_points_with?.(pointsRef.current, width, height, dt, dynamics, stepRef.current) || pointsRef.current
                 pointsRef.current = toF32(next)
+                stepRef.current = (stepRef.current + 1) >>> 0
+            }
+
+            // Kinetic mode: carry the triangulation over and only repair it
+            if (kinetic && pointsRef.current.length > 0) {
+                const pts = pointsRef.current
+                if (triRef.current && triRef.current.id_count() === pts.length / STRIDE) {
+                    triRef.current.update_positions(pts)
+                } else {
+                    triRef.current?.free()
+                    triRef.current = new silly_demos.DelaunayTriangulation(pts)
+                }
             }
 
             // Clear
             ctx.clearRect(0, 0, width, height)
 
             const pts = pointsRef.current
-            // Precompute delaunay indices if needed for highlighting
-            let I: Uint32Array | undefined
-            if (mouseRef.current.inside) {
-                const idxs = silly_demos.VoronoiTests?.delaunay_indices?.(pts) as (Uint32Array | number[] | undefined)
-                if (idxs) I = idxs instanceof Uint32Array ? idxs : new Uint32Array(idxs)
-            }
 
-            // Render base
-            if (mode === 'triangulation') drawTriangulation(pts, I)
-            else drawVoronoi(pts)
+            // Closed cells clipped to the canvas, shared by drawing and highlighting;
+            // the rasteriser computes its own
+            const cells = mode === 'voronoi' && !raster ? getCells(pts, width, height) : undefined
+
+            // Render base, either with canvas paths or with the Rust rasteriser
+            if (raster && pts.length > 0) drawRaster(pts, width, height)
+            else if (mode === 'triangulation') drawTriangulation(pts)
+            else drawVoronoi(pts, width, height, cells)
 
             // Overlay highlight if mouse is inside
             if (mouseRef.current.inside && pts.length >= STRIDE * 3) {
                 const mx = mouseRef.current.x
                 const my = mouseRef.current.y
+                // Point location needs a triangulation; outside kinetic mode build one for the query
+                const tri = kinetic ? triRef.current : new silly_demos.DelaunayTriangulation(pts)
                 if (mode === 'triangulation') {
-                    if (!I) {
-                        const idxs = silly_demos.VoronoiTests?.delaunay_indices?.(pts) as (Uint32Array | number[] | undefined)
-                        if (idxs) I = idxs instanceof Uint32Array ? idxs : new Uint32Array(idxs)
-                    }
-                    if (I) {
-                        // find the first triangle containing mouse
-                        for (let i = 0; i + 2 < I.length; i += 3) {
-                            const a = I[i] * STRIDE
-                            const b = I[i + 1] * STRIDE
-                            const cidx = I[i + 2] * STRIDE
-                            const ax = pts[a], ay = pts[a + 1]
-                            const bx = pts[b], by = pts[b + 1]
-                            const cx = pts[cidx], cy = pts[cidx + 1]
-                            if (pointInTriangle(mx, my, ax, ay, bx, by, cx, cy)) {
-                                ctx.save()
-                                ctx.beginPath()
-                                ctx.moveTo(ax, ay)
-                                ctx.lineTo(bx, by)
-                                ctx.lineTo(cx, cy)
-                                ctx.closePath()
-                                ctx.fillStyle = 'rgba(255, 215, 0, 0.18)'
-                                ctx.strokeStyle = '#ffd700'
-                                ctx.lineWidth = 2
-                                ctx.fill()
-                                ctx.stroke()
-                                ctx.restore()
-                                break
-                            }
-                        }
+                    const loc = tri?.locate(mx, my)
+                    if (loc) {
+                        const a = loc.a * STRIDE
+                        const b = loc.b * STRIDE
+                        const cidx = loc.c * STRIDE
+                        loc.free?.()
+                        ctx.save()
+                        ctx.beginPath()
+                        ctx.moveTo(pts[a], pts[a + 1])
+                        ctx.lineTo(pts[b], pts[b + 1])
+                        ctx.lineTo(pts[cidx], pts[cidx + 1])
+                        ctx.closePath()
+                        ctx.fillStyle = 'rgba(255, 215, 0, 0.18)'
+                        ctx.strokeStyle = '#ffd700'
+                        ctx.lineWidth = 2
+                        ctx.fill()
+                        ctx.stroke()
+                        ctx.restore()
                     }
                 } else {
                     // Voronoi mode: highlight cell for nearest site
-                    let bestIdx = -1
-                    let bestD2 = Infinity
-                    for (let i = 0; i < pts.length; i += STRIDE) {
-                        const dx = pts[i] - mx
-                        const dy = pts[i + 1] - my
-                        const d2 = dx * dx + dy * dy
-                        if (d2 < bestD2) {
-                            bestD2 = d2;
-                            bestIdx = i / STRIDE | 0
-                        }
-                    }
+                    const bestIdx: number = tri?.nearest(mx, my) ?? -1
                     if (bestIdx >= 0) {
-                        if (!I) {
-                            const idxs = silly_demos.VoronoiTests?.delaunay_indices?.(pts) as (Uint32Array | number[] | undefined)
-                            if (idxs) I = idxs instanceof Uint32Array ? idxs : new Uint32Array(idxs)
-                        }
-                        if (I) {
-                            const cxys: [number, number][] = []
-                            for (let i = 0; i + 2 < I.length; i += 3) {
-                                const ia = I[i], ib = I[i + 1], ic = I[i + 2]
-                                if (ia === bestIdx || ib === bestIdx || ic === bestIdx) {
-                                    const a = ia * STRIDE, b = ib * STRIDE, c3 = ic * STRIDE
-                                    const cc = circumcenter(pts[a], pts[a + 1], pts[b], pts[b + 1], pts[c3], pts[c3 + 1])
-                                    if (cc) cxys.push(cc)
-                                }
-                            }
-                            // sort circumcenters around the site
-                            const sx = pts[bestIdx * STRIDE]
-                            const sy = pts[bestIdx * STRIDE + 1]
-                            cxys.sort((p, q) => Math.atan2(p[1] - sy, p[0] - sx) - Math.atan2(q[1] - sy, q[0] - sx))
-                            if (cxys.length >= 2) {
-                                ctx.save()
-                                ctx.beginPath()
-                                ctx.moveTo(cxys[0][0], cxys[0][1])
-                                for (let k = 1; k < cxys.length; k++) ctx.lineTo(cxys[k][0], cxys[k][1])
-                                ctx.closePath()
+                        ctx.save()
+                        // In raster mode there are no cell polygons, only the site is marked
+                        if (cells) {
+                            ctx.beginPath()
+                            if (traceCell(cells.coords, cells.offsets, bestIdx)) {
                                 ctx.fillStyle = 'rgba(102, 187, 255, 0.15)'
                                 ctx.strokeStyle = '#4af'
                                 ctx.lineWidth = 2
                                 ctx.fill()
                                 ctx.stroke()
-                                // also emphasize the site point
-                                ctx.beginPath()
-                                ctx.fillStyle = '#fff'
-                                ctx.strokeStyle = '#4af'
-                                ctx.lineWidth = 2
-                                ctx.arc(sx, sy, 3, 0, Math.PI * 2)
-                                ctx.fill()
-                                ctx.stroke()
-                                ctx.restore()
                             }
                         }
+                        // also emphasize the site point
+                        const sx = pts[bestIdx * STRIDE]
+                        const sy = pts[bestIdx * STRIDE + 1]
+                        ctx.beginPath()
+                        ctx.fillStyle = '#fff'
+                        ctx.strokeStyle = '#4af'
+                        ctx.lineWidth = 2
+                        ctx.arc(sx, sy, 3, 0, Math.PI * 2)
+                        ctx.fill()
+                        ctx.stroke()
+                        ctx.restore()
                     }
                 }
+                if (!kinetic) tri?.free()
             }
 
             animRef.current = requestAnimationFrame(frame)
@@ -334,8 +403,11 @@ export default function VoronoiDemo() {
             cancelAnimationFrame(animRef.current)
             outerCanvas.removeEventListener('mousemove', onMove)
             outerCanvas.removeEventListener('mouseleave', onLeave)
+            dynamics.free()
+            triRef.current?.free()
+            triRef.current = null
         }
-    }, [wasmReady, mode, paused])
+    }, [wasmReady, mode, paused, kinetic, shadeCells, raster, motion, boundary, seed, distribution])
 
     // UI
     return (
@@ -346,6 +418,18 @@ export default function VoronoiDemo() {
                             onClick={() => setMode(m => m === 'triangulation' ? 'voronoi' : 'triangulation')}>
                         Mode: {mode === 'triangulation' ? 'Delaunay (triangulation)' : 'Voronoi'}
                     </button>
+                    <button className="control-button" onClick={() => setKinetic(k => !k)}
+                            title="Reuse the previous frame's triangulation and repair it with edge flips">
+                        Kinetic: {kinetic ? 'On' : 'Off'}
+                    </button>
+                    <button className="control-button" onClick={() => setRaster(r => !r)}
+                            title="Draw with the Rust software rasteriser instead of canvas paths">
+                        Renderer: {raster ? 'WASM raster' : 'Canvas'}
+                    </button>
+                    <button className="control-button" onClick={() => setShadeCells(v => !v)}
+                            title="Colour Voronoi cells by their area relative to the mean">
+                        Shade by area: {shadeCells ? 'On' : 'Off'}
+                    </button>
                     <button className="control-button" onClick={() => setPaused(p => !p)}>
                         {paused ? 'Resume' : 'Pause'}
                     </button>
@@ -363,14 +447,34 @@ export default function VoronoiDemo() {
                                onChange={e => setSpeed(Math.max(1, Math.min(200, parseInt(e.target.value || '0', 10))))}
                                style={{marginLeft: 8, width: 100}}/>
                     </label>
+                    <label className="pill">Start
+                        <select value={distribution} onChange={e => setDistribution(parseInt(e.target.value, 10))}
+                                style={{marginLeft: 8}}>
+                            {DISTRIBUTIONS.map((name, i) => <option key={name} value={i}>{name}</option>)}
+                        </select>
+                    </label>
+                    <label className="pill">Motion
+                        <select value={motion} onChange={e => setMotion(parseInt(e.target.value, 10))}
+                                style={{marginLeft: 8}}>
+                            {MOTIONS.map((name, i) => <option key={name} value={i}>{name}</option>)}
+                        </select>
+                    </label>
+                    <label className="pill">Edges
+                        <select value={boundary} onChange={e => setBoundary(parseInt(e.target.value, 10))}
+                                style={{marginLeft: 8}}>
+                            {BOUNDARIES.map((name, i) => <option key={name} value={i}>{name}</option>)}
+                        </select>
+                    </label>
                     <button className="control-button" onClick={() => {
                         // Recreate points explicitly
                         const canvas = canvasRef.current
                         if (!canvas) return
                         const width = (canvas as any)._displayWidth || canvas.clientWidth || 800
                         const height = (canvas as any)._displayHeight || canvas.clientHeight || 600
-                        const out = silly_demos.VoronoiTests?.voronoi_create_points?.(count, width, height, seed >>> 0, speed) || []
-                        pointsRef.current = toF32(out)
+                        pointsRef.current = createPoints(distribution, count, width, height, seed >>> 0, speed)
+                        stepRef.current = 0
+                        triRef.current?.free()
+                        triRef.current = null
                     }}>Recreate
                     </button>
                 </div>
@@ -14,11 +14,291 @@ declare module '../pkg/silly_demos.js' {
     dx: number
     dy: number
   }
+  export interface VoronoiCells {
+    readonly coords: Float32Array
+    readonly offsets: Uint32Array
+    free(): void
+  }
+  export interface MedialAxis {
+    readonly nodes: Float32Array
+    readonly radii: Float32Array
+    readonly edges: Uint32Array
+    free(): void
+  }
+  export interface RoadmapPath {
+    found: boolean
+    readonly points: Float32Array
+    readonly clearance: Float32Array
+    length: number
+    min_clearance: number
+    free(): void
+  }
+  export interface CellStats {
+    readonly areas: Float32Array
+    readonly perimeters: Float32Array
+    readonly centroids: Float32Array
+    readonly bounding_circles: Float32Array
+    readonly neighbor_counts: Uint32Array
+    readonly neighbors: Uint32Array
+    readonly neighbor_offsets: Uint32Array
+    free(): void
+  }
+  export interface LloydResult {
+    readonly points: Float32Array
+    readonly residuals: Float32Array
+    free(): void
+  }
+  export interface AlphaShape {
+    readonly triangles: Uint32Array
+    readonly loops: Uint32Array
+    readonly loop_offsets: Uint32Array
+    free(): void
+  }
+  export interface ConstrainedTriangulation {
+    readonly points: Float32Array
+    readonly triangles: Uint32Array
+    readonly constrained_edges: Uint32Array
+    free(): void
+  }
+  export interface QualityMesh {
+    readonly vertices: Float32Array
+    readonly indices: Uint32Array
+    readonly budget_exhausted: boolean
+    free(): void
+  }
+  export enum DistanceMetric {
+    Euclidean = 0,
+    Manhattan = 1,
+    Chebyshev = 2,
+    Minkowski = 3,
+  }
+  export class RasterGrid {
+    constructor(width: number, height: number, minX: number, minY: number, maxX: number, maxY: number)
+    width: number
+    height: number
+    min_x: number
+    min_y: number
+    max_x: number
+    max_y: number
+    free(): void
+  }
+  export interface RasterVoronoi {
+    width: number
+    height: number
+    readonly labels: Uint32Array
+    readonly distances: Float32Array
+    free(): void
+  }
+  export enum Interpolation {
+    Linear = 0,
+    NaturalNeighbor = 1,
+  }
+  export class SvgStyle {
+    constructor(stroke: string, strokeWidth: number, fill: string)
+    stroke: string
+    stroke_width: number
+    fill: string
+    free(): void
+  }
+  export interface ValidationReport {
+    valid: boolean
+    vertices: number
+    edges: number
+    triangles: number
+    euler_characteristic: number
+    readonly out_of_range_triangles: Uint32Array
+    readonly inverted_triangles: Uint32Array
+    readonly non_manifold_edges: Uint32Array
+    readonly non_delaunay_edges: Uint32Array
+    readonly overlapping_triangles: Uint32Array
+    readonly duplicate_points: Uint32Array
+    readonly unused_points: Uint32Array
+    free(): void
+  }
+  export enum TriangulationErrorKind {
+    NoPoints = 0,
+    NonFinite = 1,
+    InvalidBoundary = 4,
+    InvalidGrid = 5,
+    InvalidConstraint = 6,
+  }
+  export interface TriangulationError {
+    kind: TriangulationErrorKind
+    message: string
+    free(): void
+  }
+  export interface DelaunayResult {
+    readonly vertices: Float32Array
+    readonly vertex_of: Uint32Array
+    readonly triangles: Uint32Array
+    readonly voronoi_edges: Float32Array
+    collinear: boolean
+    readonly line: Uint32Array
+    free(): void
+  }
+  export enum PointMotion {
+    Drift = 0,
+    Brownian = 1,
+    Vortex = 2,
+    CurlNoise = 3,
+    Repulsion = 4,
+  }
+  export enum PointBoundary {
+    Respawn = 0,
+    Bounce = 1,
+    Wrap = 2,
+  }
+  export class PointDynamics {
+    constructor(motion: PointMotion, boundary: PointBoundary, seed: number)
+    motion: PointMotion
+    boundary: PointBoundary
+    seed: number
+    min_speed: number
+    max_speed: number
+    strength: number
+    scale: number
+    fixed_corners: boolean
+    free(): void
+  }
+  export enum PathObjective {
+    Shortest = 0,
+    Widest = 1,
+  }
+  export class VoronoiRoadmap {
+    constructor(points: Float32Array | number[])
+    static from_outlines(coords: Float32Array | number[], offsets: Uint32Array | number[], spacing: number): VoronoiRoadmap
+    sites(): Float32Array
+    edges(minClearance: number): Float32Array
+    find_path(startX: number, startY: number, goalX: number, goalY: number, minClearance: number, objective: PathObjective): RoadmapPath
+    free(): void
+  }
+  export enum Sequence {
+    Halton = 0,
+    Sobol = 1,
+    R2 = 2,
+  }
+  export class RenderStyle {
+    constructor()
+    /** Colours are 0xRRGGBBAA. */
+    background: number
+    edge_color: number
+    edge_width: number
+    site_color: number
+    site_radius: number
+    antialias: boolean
+    scale: number
+    free(): void
+  }
+  export class PoissonDisk {
+    constructor(minDistance: number)
+    min_distance: number
+    max_distance: number
+    attempts: number
+    density_width: number
+    free(): void
+  }
+  export interface PeriodicTriangulation {
+    readonly triangles: Uint32Array
+    readonly shifts: Int32Array
+    free(): void
+  }
+  export interface SphericalVoronoi {
+    readonly triangles: Uint32Array
+    readonly centers: Float32Array
+    readonly centers_lon_lat: Float32Array
+    readonly cells: Uint32Array
+    readonly cell_offsets: Uint32Array
+    free(): void
+  }
+  export interface PointLocation {
+    readonly a: number
+    readonly b: number
+    readonly c: number
+    readonly u: number
+    readonly v: number
+    readonly w: number
+    free(): void
+  }
+  export class DelaunayTriangulation {
+    constructor(points: Float32Array | number[])
+    insert(x: number, y: number): number
+    remove(id: number): boolean
+    update_positions(points: Float32Array | number[]): number
+    id_count(): number
+    contains(id: number): boolean
+    positions(): Float32Array
+    delaunay_indices(): Uint32Array
+    voronoi_edges(): Float32Array
+    neighbors(id: number): Uint32Array
+    nearest(x: number, y: number): number | undefined
+    k_nearest(x: number, y: number, k: number): Uint32Array
+    locate(x: number, y: number): PointLocation | undefined
+    hull(): Uint32Array
+    to_svg(style: SvgStyle): string
+    to_geojson(): string
+    to_obj(): string
+    validate(): ValidationReport
+    free(): void
+  }
   export namespace VoronoiTests {
     function voronoi_create_points(count: number, width: number, height: number, seed: number, speed: number): Float32Array | number[]
     function voronoi_step_points(points: Float32Array | number[], width: number, height: number, dt: number): Float32Array | number[]
-    function delaunay_indices(points: Float32Array | number[]): Uint32Array | number[]
-    function voronoi_edges(points: Float32Array | number[]): Float32Array | number[]
+    function voronoi_step_points_with(points: Float32Array | number[], width: number, height: number, dt: number, dynamics: PointDynamics, step: number): Float32Array | number[]
+    /** Like delaunay() without the Voronoi edges; triangles index result.vertices. */
+    function delaunay_indices(points: Float32Array | number[], epsilon: number): DelaunayResult
+    /** Like delaunay() without the triangles. */
+    function voronoi_edges(points: Float32Array | number[], epsilon: number): DelaunayResult
+    /** Merges points closer than epsilon and returns the vertex of every input point. Throws a TriangulationError on empty or non-finite input. */
+    function delaunay(points: Float32Array | number[], epsilon: number): DelaunayResult
+    function voronoi_cells(points: Float32Array | number[], minX: number, minY: number, maxX: number, maxY: number): VoronoiCells
+    function voronoi_cell_stats(points: Float32Array | number[], minX: number, minY: number, maxX: number, maxY: number): CellStats
+    /** Throws a TriangulationError if the boundary is not convex. */
+    function voronoi_cell_stats_clipped(points: Float32Array | number[], boundary: Float32Array | number[]): CellStats
+    function medial_axis(coords: Float32Array | number[], offsets: Uint32Array | number[], spacing: number, minSignificance: number): MedialAxis
+    function render_voronoi(points: Float32Array | number[], width: number, height: number, colors: Uint32Array | number[], style: RenderStyle): Uint8Array
+    function render_delaunay(points: Float32Array | number[], width: number, height: number, style: RenderStyle): Uint8Array
+    function periodic_delaunay(points: Float32Array | number[], width: number, height: number): PeriodicTriangulation
+    function periodic_cells(points: Float32Array | number[], width: number, height: number): VoronoiCells
+    function spherical_voronoi(points: Float32Array | number[]): SphericalVoronoi
+    function spherical_voronoi_lon_lat(lonLat: Float32Array | number[]): SphericalVoronoi
+    /** Throws a TriangulationError if the boundary is not convex. */
+    function voronoi_cells_clipped(points: Float32Array | number[], boundary: Float32Array | number[]): VoronoiCells
+    function regular_indices(points: Float32Array | number[], weights: Float32Array | number[]): Uint32Array
+    function power_cells(points: Float32Array | number[], weights: Float32Array | number[], minX: number, minY: number, maxX: number, maxY: number): VoronoiCells
+    /** Throws a TriangulationError if the boundary is not convex. */
+    function power_cells_clipped(points: Float32Array | number[], weights: Float32Array | number[], boundary: Float32Array | number[]): VoronoiCells
+    function additively_weighted_cells(points: Float32Array | number[], weights: Float32Array | number[], minX: number, minY: number, maxX: number, maxY: number, samples: number): VoronoiCells
+    function emst_edges(points: Float32Array | number[]): Uint32Array
+    function gabriel_edges(points: Float32Array | number[]): Uint32Array
+    function relative_neighborhood_edges(points: Float32Array | number[]): Uint32Array
+    function knn_graph_edges(points: Float32Array | number[], k: number): Uint32Array
+    function convex_hull(points: Float32Array | number[]): Uint32Array
+    function delaunay_hull(points: Float32Array | number[]): Uint32Array
+    function alpha_shape(points: Float32Array | number[], alpha: number): AlphaShape
+    function concave_hull(points: Float32Array | number[], maxEdge: number): Uint32Array
+    /** Throws a TriangulationError on non-finite coordinates or a forced edge that cannot be inserted. */
+    function constrained_delaunay(points: Float32Array | number[], segments: Uint32Array | number[], rings: Uint32Array | number[], ringOffsets: Uint32Array | number[]): ConstrainedTriangulation
+    /** Throws like constrained_delaunay. */
+    function refine_mesh(points: Float32Array | number[], segments: Uint32Array | number[], rings: Uint32Array | number[], ringOffsets: Uint32Array | number[], minAngle: number, maxArea: number, maxSteiner: number): QualityMesh
+    function raster_voronoi(points: Float32Array | number[], grid: RasterGrid, metric: DistanceMetric, p: number): RasterVoronoi
+    function raster_voronoi_jfa(points: Float32Array | number[], grid: RasterGrid, metric: DistanceMetric, p: number): RasterVoronoi
+    function interpolate(points: Float32Array | number[], values: Float32Array | number[], queries: Float32Array | number[], method: Interpolation): Float32Array
+    function interpolate_grid(points: Float32Array | number[], values: Float32Array | number[], grid: RasterGrid, method: Interpolation): Float32Array
+    function triangles_svg(points: Float32Array | number[], stride: number, indices: Uint32Array | number[], style: SvgStyle): string
+    function edges_svg(segments: Float32Array | number[], style: SvgStyle): string
+    function cells_svg(cells: VoronoiCells, style: SvgStyle): string
+    function triangles_geojson(points: Float32Array | number[], stride: number, indices: Uint32Array | number[]): string
+    function cells_geojson(cells: VoronoiCells): string
+    function triangles_obj(points: Float32Array | number[], stride: number, indices: Uint32Array | number[]): string
+    function validate_triangulation(points: Float32Array | number[], indices: Uint32Array | number[]): ValidationReport
+    function lloyd_relax(points: Float32Array | number[], iterations: number, minX: number, minY: number, maxX: number, maxY: number): LloydResult
+    /** Throws a TriangulationError if the density grid is too small. */
+    function lloyd_relax_weighted(points: Float32Array | number[], iterations: number, minX: number, minY: number, maxX: number, maxY: number, density: Float32Array | number[], gridWidth: number, gridHeight: number): LloydResult
+  }
+  export namespace SamplingTests {
+    function poisson_disk(width: number, height: number, settings: PoissonDisk, density: Float32Array | number[], seed: number, speed: number): Float32Array | number[]
+    function low_discrepancy(sequence: Sequence, count: number, width: number, height: number, seed: number, speed: number): Float32Array | number[]
+    function jittered_grid(count: number, width: number, height: number, jitter: number, seed: number, speed: number): Float32Array | number[]
   }
 }
 
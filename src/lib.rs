@@ -2,10 +2,13 @@ mod boids;
 mod collision;
 mod geometry;
 mod rand;
+mod sampling;
 mod utils;
 mod vec2;
 mod voronoi;
 
+pub use voronoi::export;
+
 use crate::utils::set_panic_hook;
 use wasm_bindgen::prelude::*;
 
@@ -36,6 +39,12 @@ pub struct GeometryTests;
 #[wasm_bindgen]
 pub struct BoidsTests;
 
+/// Namespace for point generators
+/// This empty struct serves as a namespace for the seeded samplers shared
+/// by the Voronoi and boids demos when exported to WASM
+#[wasm_bindgen]
+pub struct SamplingTests;
+
 #[wasm_bindgen]
 pub struct CircleCollisionResult {
     #[wasm_bindgen(readonly)]
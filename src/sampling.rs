@@ -0,0 +1,465 @@
+//! Seeded point generators.
+//!
+//! All generators emit the `[x,y,vx,vy,...]` layout of
+//! `VoronoiTests::voronoi_create_points`, without the fixed corner points;
+//! the Voronoi demo draws their cells clipped to the viewport with
+//! `VoronoiTests::voronoi_cells` and steps them with
+//! `PointDynamics::fixed_corners` off. The boids demo appends the flags
+//! of its `[x,y,vx,vy,flags]` layout to each point.
+//! The velocities point in a random direction at 20–100% of `speed` and
+//! are drawn from their own stream, so the positions do not depend on
+//! `speed`.
+
+use crate::SamplingTests;
+use crate::rand::{frand01, hash_u32};
+use wasm_bindgen::prelude::*;
+
+/// Upper bound on the background grid of [`poisson_disk`]; smaller minimum
+/// distances are raised to fit.
+const MAX_CELLS: f32 = (1 << 20) as f32;
+
+/// Low-discrepancy sequence for [`SamplingTests::low_discrepancy`].
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sequence {
+    /// Radical inverses in bases 2 and 3.
+    Halton = 0,
+    /// The first two Sobol dimensions.
+    Sobol = 1,
+    /// Roberts' additive recurrence with the plastic number.
+    R2 = 2,
+}
+
+/// Settings for [`SamplingTests::poisson_disk`].
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct PoissonDisk {
+    /// Distance between samples where the density is 1, or everywhere
+    /// without a density map.
+    pub min_distance: f32,
+    /// Distance between samples where the density is 0.
+    pub max_distance: f32,
+    /// Candidates tried around a sample before it is retired.
+    pub attempts: u32,
+    /// Columns of the density map; its rows follow from its length.
+    pub density_width: u32,
+}
+
+#[wasm_bindgen]
+impl PoissonDisk {
+    #[wasm_bindgen(constructor)]
+    pub fn new(min_distance: f32) -> PoissonDisk {
+        PoissonDisk {
+            min_distance,
+            max_distance: min_distance,
+            attempts: 30,
+            density_width: 0,
+        }
+    }
+}
+
+impl PoissonDisk {
+    /// Required distance around `(x, y)`.
+    fn radius_at(&self, density: &[f32], width: f32, height: f32, x: f32, y: f32) -> f32 {
+        let cols = self.density_width as usize;
+        if cols == 0 || density.len() < cols {
+            return self.min_distance;
+        }
+        let rows = density.len() / cols;
+        let col = ((x / width * cols as f32) as usize).min(cols - 1);
+        let row = ((y / height * rows as f32) as usize).min(rows - 1);
+        let d = density[row * cols + col];
+        let d = if d.is_nan() { 0.0 } else { d.clamp(0.0, 1.0) };
+        self.max_distance + (self.min_distance - self.max_distance) * d
+    }
+}
+
+/// Background grid of [`poisson_disk`] for the samples with radii in
+/// `[cell / 2, cell)`. Those are at least `cell / 2` apart, so every cell
+/// holds only a few of them.
+struct RadiusGrid {
+    cell: f32,
+    cols: usize,
+    rows: usize,
+    samples: Vec<Vec<u32>>,
+}
+
+impl RadiusGrid {
+    fn new(cell: f32, width: f32, height: f32) -> RadiusGrid {
+        let cols = ((width / cell).ceil() as usize).max(1);
+        let rows = ((height / cell).ceil() as usize).max(1);
+        RadiusGrid {
+            cell,
+            cols,
+            rows,
+            samples: vec![Vec::new(); cols * rows],
+        }
+    }
+
+    fn cell_of(&self, x: f32, y: f32) -> (usize, usize) {
+        (
+            ((x / self.cell) as usize).min(self.cols - 1),
+            ((y / self.cell) as usize).min(self.rows - 1),
+        )
+    }
+
+    fn insert(&mut self, x: f32, y: f32, id: u32) {
+        let (cx, cy) = self.cell_of(x, y);
+        self.samples[cy * self.cols + cx].push(id);
+    }
+
+    /// Samples in the cells that could be within `dist` of `(x, y)`.
+    fn near(&self, x: f32, y: f32, dist: f32) -> impl Iterator<Item = u32> + '_ {
+        let (cx, cy) = self.cell_of(x, y);
+        let reach = (dist / self.cell).ceil() as usize;
+        let (x0, x1) = (cx.saturating_sub(reach), (cx + reach).min(self.cols - 1));
+        let (y0, y1) = (cy.saturating_sub(reach), (cy + reach).min(self.rows - 1));
+        (y0..=y1)
+            .flat_map(move |j| (x0..=x1).map(move |i| j * self.cols + i))
+            .flat_map(|k| self.samples[k].iter().copied())
+    }
+}
+
+/// Bridson's Poisson-disk sampling of `[0, width) x [0, height)`. Two
+/// samples are at least the larger of their radii apart; candidates are
+/// drawn from the annulus between one and two radii around an active
+/// sample.
+///
+/// The samples are filed by radius into grids with cells of 2, 4, 8, ...
+/// times the minimum distance, so a candidate only searches as far as the
+/// radii in each grid reach, however wide the range of radii.
+pub(crate) fn poisson_disk(
+    width: f32,
+    height: f32,
+    settings: &PoissonDisk,
+    density: &[f32],
+    seed: u32,
+) -> Vec<(f32, f32)> {
+    // `!(x > 0)` also rejects NaN.
+    let area = width * height;
+    if !(width > 0.0 && height > 0.0 && settings.min_distance > 0.0 && area.is_finite()) {
+        return Vec::new();
+    }
+    let mut settings = *settings;
+    settings.min_distance = settings
+        .min_distance
+        .max((2.0 * area / MAX_CELLS).sqrt())
+        .max((width + height) / MAX_CELLS);
+    // A radius beyond the diagonal keeps out no more than the diagonal.
+    let diagonal = width.hypot(height).max(settings.min_distance);
+    settings.max_distance = settings
+        .max_distance
+        .max(settings.min_distance)
+        .min(diagonal);
+    if !settings.max_distance.is_finite() {
+        return Vec::new();
+    }
+
+    let min = settings.min_distance;
+    let levels = (settings.max_distance / min).log2().floor() as usize + 1;
+    let mut grids: Vec<RadiusGrid> = (0..levels)
+        .map(|k| RadiusGrid::new(min * 2f32.powi(k as i32 + 1), width, height))
+        .collect();
+    let level_of = |r: f32| ((r / min).log2().floor().max(0.0) as usize).min(levels - 1);
+    let mut samples: Vec<(f32, f32, f32)> = Vec::new();
+    let mut active: Vec<usize> = Vec::new();
+    let mut s = hash_u32(seed ^ 0x2545_F491) | 1;
+
+    let (x, y) = (frand01(&mut s) * width, frand01(&mut s) * height);
+    let r = settings.radius_at(density, width, height, x, y);
+    grids[level_of(r)].insert(x, y, 0);
+    active.push(0);
+    samples.push((x, y, r));
+
+    while !active.is_empty() {
+        let k = ((frand01(&mut s) * active.len() as f32) as usize).min(active.len() - 1);
+        let (px, py, pr) = samples[active[k]];
+        let mut placed = false;
+        for _ in 0..settings.attempts.max(1) {
+            let ang = frand01(&mut s) * std::f32::consts::TAU;
+            let dist = pr * (1.0 + frand01(&mut s));
+            let (x, y) = (px + ang.cos() * dist, py + ang.sin() * dist);
+            if !(0.0..width).contains(&x) || !(0.0..height).contains(&y) {
+                continue;
+            }
+            let r = settings.radius_at(density, width, height, x, y);
+            // The radii in a grid are below its cell size.
+            let clear = grids.iter().all(|grid| {
+                grid.near(x, y, r.max(grid.cell)).all(|q| {
+                    let (qx, qy, qr) = samples[q as usize];
+                    let min = r.max(qr);
+                    (x - qx) * (x - qx) + (y - qy) * (y - qy) >= min * min
+                })
+            });
+            if clear {
+                grids[level_of(r)].insert(x, y, samples.len() as u32);
+                active.push(samples.len());
+                samples.push((x, y, r));
+                placed = true;
+                break;
+            }
+        }
+        if !placed {
+            active.swap_remove(k);
+        }
+    }
+    samples.into_iter().map(|(x, y, _)| (x, y)).collect()
+}
+
+/// Radical inverse of `i` in base `b`.
+fn radical_inverse(mut i: u32, b: u32) -> f64 {
+    let (mut inv, mut f) = (0.0, 1.0 / b as f64);
+    while i > 0 {
+        inv += (i % b) as f64 * f;
+        i /= b;
+        f /= b as f64;
+    }
+    inv
+}
+
+/// Point `i` of the first two Sobol dimensions as 32-bit fractions. The
+/// first dimension is the van der Corput sequence, the second one uses the
+/// primitive polynomial `x + 1`.
+fn sobol(i: u32) -> (u32, u32) {
+    let (mut x, mut y) = (0, 0);
+    let mut v = 1u32 << 31;
+    for bit in 0..32 {
+        if i >> bit & 1 == 1 {
+            x ^= 1 << (31 - bit);
+            y ^= v;
+        }
+        v ^= v >> 1;
+    }
+    (x, y)
+}
+
+/// Point `i` of `sequence` in the unit square. Seed 0 gives the plain
+/// sequence; other seeds shift it, by a random digital (XOR) shift for
+/// Sobol and a random toroidal shift otherwise, which keeps the
+/// low-discrepancy property.
+pub(crate) fn sequence_point(sequence: Sequence, i: u32, seed: u32) -> (f64, f64) {
+    let (sx, sy) = if seed == 0 {
+        (0, 0)
+    } else {
+        let mut s = hash_u32(seed) | 1;
+        let sx = hash_u32(s);
+        s = hash_u32(sx) | 1;
+        (sx, hash_u32(s))
+    };
+    let frac = |v: u32| v as f64 / 4_294_967_296.0;
+    match sequence {
+        Sequence::Halton => (
+            (radical_inverse(i, 2) + frac(sx)).fract(),
+            (radical_inverse(i, 3) + frac(sy)).fract(),
+        ),
+        Sequence::Sobol => {
+            let (x, y) = sobol(i);
+            (frac(x ^ sx), frac(y ^ sy))
+        }
+        Sequence::R2 => {
+            // The plastic number, the real root of x^3 = x + 1.
+            const G: f64 = 1.324_717_957_244_746;
+            let (ax, ay) = (1.0 / G, 1.0 / (G * G));
+            let (ox, oy) = if seed == 0 {
+                (0.5, 0.5)
+            } else {
+                (frac(sx), frac(sy))
+            };
+            ((ox + ax * i as f64).fract(), (oy + ay * i as f64).fract())
+        }
+    }
+}
+
+/// A grid of `count` cells with about the aspect ratio of the area and one
+/// point per cell, moved off the cell centre by up to `jitter` (0–1) times
+/// half the cell size. The last row takes the remainder, in wider cells.
+pub(crate) fn jittered_grid(
+    count: usize,
+    width: f32,
+    height: f32,
+    jitter: f32,
+    seed: u32,
+) -> Vec<(f32, f32)> {
+    if count == 0 || !(width > 0.0 && height > 0.0) {
+        return Vec::new();
+    }
+    let cols = ((count as f32 * width / height).sqrt().round() as usize).clamp(1, count);
+    let rows = count.div_ceil(cols);
+    let ch = height / rows as f32;
+    let jitter = if jitter.is_nan() {
+        0.0
+    } else {
+        jitter.clamp(0.0, 1.0)
+    };
+    let mut s = hash_u32(seed ^ 0x2545_F491) | 1;
+    let mut out = Vec::with_capacity(count);
+    for j in 0..rows {
+        let in_row = if j + 1 == rows {
+            count - cols * (rows - 1)
+        } else {
+            cols
+        };
+        let cw = width / in_row as f32;
+        for i in 0..in_row {
+            let dx = (frand01(&mut s) - 0.5) * jitter;
+            let dy = (frand01(&mut s) - 0.5) * jitter;
+            out.push(((i as f32 + 0.5 + dx) * cw, (j as f32 + 0.5 + dy) * ch));
+        }
+    }
+    out
+}
+
+/// Give the points velocities and flatten them to [x,y,vx,vy,...].
+fn with_velocities(
+    points: impl IntoIterator<Item = (f32, f32)>,
+    seed: u32,
+    speed: f32,
+) -> Vec<f32> {
+    let mut s = hash_u32(seed ^ 0x9E37_79B9) | 1;
+    let mut out = Vec::new();
+    for (x, y) in points {
+        let ang = frand01(&mut s) * std::f32::consts::TAU;
+        let spd = (0.2 + 0.8 * frand01(&mut s)) * speed;
+        out.extend([x, y, ang.cos() * spd, ang.sin() * spd]);
+    }
+    out
+}
+
+#[wasm_bindgen]
+impl SamplingTests {
+    /// Poisson-disk (blue noise) points in `[0, width) x [0, height)`.
+    /// With `settings.density_width > 0`, `density` is a row-major map
+    /// over the area with values in [0,1] that blends the spacing from
+    /// `max_distance` (0) to `min_distance` (1); otherwise the spacing is
+    /// `min_distance` everywhere. Layout: [x,y,vx,vy,...]
+    pub fn poisson_disk(
+        width: f32,
+        height: f32,
+        settings: &PoissonDisk,
+        density: &[f32],
+        seed: u32,
+        speed: f32,
+    ) -> Vec<f32> {
+        let points = poisson_disk(width, height, settings, density, seed);
+        with_velocities(points, seed, speed)
+    }
+
+    /// `count` points of a low-discrepancy sequence scaled to the area.
+    /// The sequence starts at index 1, as index 0 is the origin for Halton
+    /// and Sobol. Layout: [x,y,vx,vy,...]
+    pub fn low_discrepancy(
+        sequence: Sequence,
+        count: usize,
+        width: f32,
+        height: f32,
+        seed: u32,
+        speed: f32,
+    ) -> Vec<f32> {
+        let points = (1..=count as u32).map(|i| {
+            let (u, v) = sequence_point(sequence, i, seed);
+            (u as f32 * width, v as f32 * height)
+        });
+        with_velocities(points, seed, speed)
+    }
+
+    /// `count` points, one per cell of a grid with about the aspect ratio
+    /// of the area, each moved off its cell centre by up to `jitter` (0–1)
+    /// times half the cell size. Layout: [x,y,vx,vy,...]
+    pub fn jittered_grid(
+        count: usize,
+        width: f32,
+        height: f32,
+        jitter: f32,
+        seed: u32,
+        speed: f32,
+    ) -> Vec<f32> {
+        let points = jittered_grid(count, width, height, jitter, seed);
+        with_velocities(points, seed, speed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poisson_disk_keeps_its_distance() {
+        let settings = PoissonDisk::new(12.0);
+        let points = poisson_disk(400.0, 300.0, &settings, &[], 7);
+        // A maximal packing covers the area at roughly 0.5-0.7 points per r².
+        assert!(points.len() > 400, "{}", points.len());
+        for (i, &(ax, ay)) in points.iter().enumerate() {
+            assert!((0.0..400.0).contains(&ax) && (0.0..300.0).contains(&ay));
+            for &(bx, by) in &points[i + 1..] {
+                assert!((ax - bx).hypot(ay - by) >= 12.0);
+            }
+        }
+        assert_eq!(points, poisson_disk(400.0, 300.0, &settings, &[], 7));
+    }
+
+    #[test]
+    fn variable_radius_keeps_its_distance() {
+        let mut settings = PoissonDisk::new(3.0);
+        settings.max_distance = 24.0;
+        settings.density_width = 4;
+        let density = [1.0, 0.6, 0.3, 0.0];
+        let points = poisson_disk(400.0, 300.0, &settings, &density, 3);
+        let radius = |(x, y): (f32, f32)| settings.radius_at(&density, 400.0, 300.0, x, y);
+        for (i, &a) in points.iter().enumerate() {
+            assert!((0.0..400.0).contains(&a.0) && (0.0..300.0).contains(&a.1));
+            for &b in &points[i + 1..] {
+                let min = radius(a).max(radius(b));
+                assert!((a.0 - b.0).hypot(a.1 - b.1) >= min, "{a:?} {b:?}");
+            }
+        }
+        // The densest quarter holds (24 / 3)² times as many as the sparsest.
+        let quarter = |q: f32| points.iter().filter(|p| (p.0 / 100.0).floor() == q).count();
+        assert!(
+            quarter(0.0) > 30 * quarter(3.0),
+            "{} {}",
+            quarter(0.0),
+            quarter(3.0)
+        );
+
+        // A wide range of radii stays quick; a single grid with cells of the
+        // minimum distance would search thousands of cells per candidate.
+        settings.min_distance = 2.0;
+        settings.max_distance = 200.0;
+        let ramp: Vec<f32> = (0..64).map(|i| i as f32 / 63.0).collect();
+        settings.density_width = 64;
+        let points = poisson_disk(800.0, 600.0, &settings, &ramp, 1);
+        assert!(points.len() > 1000, "{}", points.len());
+    }
+
+    #[test]
+    fn jittered_grid_has_count_points() {
+        for (width, height) in [(800.0, 600.0), (100.0, 900.0), (1.0, 1.0)] {
+            for count in (0..200).chain([999, 1000, 1001]) {
+                let points = jittered_grid(count, width, height, 1.0, 5);
+                assert_eq!(points.len(), count, "{width}x{height}");
+                assert!(
+                    points
+                        .iter()
+                        .all(|&(x, y)| (0.0..=width).contains(&x) && (0.0..=height).contains(&y))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn sobol_points_are_stratified() {
+        assert_eq!(sequence_point(Sequence::Sobol, 1, 0), (0.5, 0.5));
+        assert_eq!(sequence_point(Sequence::Sobol, 2, 0), (0.25, 0.75));
+        assert_eq!(sequence_point(Sequence::Halton, 1, 0), (0.5, 1.0 / 3.0));
+        // Any 2^k consecutive aligned points, shifted or not, put one point
+        // in every cell of a 2^a x 2^b grid with a + b = k.
+        for seed in [0, 1, 99] {
+            let mut cells = [false; 64];
+            for i in 64..128 {
+                let (x, y) = sequence_point(Sequence::Sobol, i, seed);
+                cells[(x * 8.0) as usize * 8 + (y * 8.0) as usize] = true;
+            }
+            assert!(cells.iter().all(|&c| c), "seed {seed}");
+        }
+    }
+}
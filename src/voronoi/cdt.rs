@@ -0,0 +1,624 @@
+//! Constrained Delaunay triangulation.
+//!
+//! Forced edges are inserted into an ordinary Delaunay triangulation one at a
+//! time: the edges crossing the new segment are collected by walking along
+//! it and then flipped out of the way (Sloan's method), after which the
+//! triangles around the new diagonals are made Delaunay again. Forced edges
+//! are remembered in [`Triangulation::constraints`] so that later flips and
+//! point insertions leave them alone.
+//!
+//! Segments that cross an existing forced edge, or run through a vertex, are
+//! split there; crossings add a Steiner point at the intersection. Polygon
+//! rings are forced edges of kind [`Constraint::Boundary`], and the triangles
+//! inside the shape are those reached from outside by crossing an odd number
+//! of boundary edges, so holes and nested islands follow the even-odd rule.
+
+use super::degenerate::{TriangulationError, TriangulationErrorKind, check_points};
+use super::predicates::orient2d;
+use super::triangulation::{GHOST, Insertion, NONE, Triangulation, next, prev};
+use super::{Constraint, Edge, Pt, VoronoiTests, read_xy};
+use std::collections::VecDeque;
+use wasm_bindgen::prelude::*;
+
+/// A triangulated shape. Steiner points created where forced edges cross are
+/// appended to the input points, so indices into the input stay valid.
+#[wasm_bindgen(getter_with_clone)]
+pub struct ConstrainedTriangulation {
+    /// All points as [x,y,...], input points first.
+    #[wasm_bindgen(readonly)]
+    pub points: Vec<f32>,
+    /// Triangles inside the shape as index triplets, counter-clockwise.
+    #[wasm_bindgen(readonly)]
+    pub triangles: Vec<u32>,
+    /// Forced edges as index pairs [a0,b0,a1,b1,...], split at every vertex
+    /// that lies on them.
+    #[wasm_bindgen(readonly)]
+    pub constrained_edges: Vec<u32>,
+}
+
+/// Where the walk along a new segment stopped.
+enum Walk {
+    /// The segment reaches its end vertex through the collected edges.
+    End,
+    /// The segment runs through vertex `x` first.
+    Vertex(usize),
+    /// The segment crosses the forced edge `u - v`.
+    Crossing(usize, usize),
+}
+
+/// Squared distance, relative to the squared segment length, within which
+/// an intersection snaps to a segment endpoint.
+const SNAP: f64 = 1e-18;
+
+/// Error for a forced edge `a - b` that could not be inserted.
+fn constraint_error(a: usize, b: usize, what: &str) -> TriangulationError {
+    TriangulationError::new(
+        TriangulationErrorKind::InvalidConstraint,
+        format!("cannot force the edge {a} - {b}: {what}"),
+    )
+}
+
+/// Intersection of the lines through `a - b` and `c - d`.
+fn intersection(a: Pt, b: Pt, c: Pt, d: Pt) -> Pt {
+    let r = b.sub(a);
+    let s = d.sub(c);
+    let t = (c.sub(a).x * s.y - c.sub(a).y * s.x) / (r.x * s.y - r.y * s.x);
+    a.add(r.mul(t))
+}
+
+/// Read forced edges from index pairs and rings from the flat
+/// `rings`/`ring_offsets` layout of the wasm API.
+pub(crate) fn read_constraints(
+    segments: &[u32],
+    rings: &[u32],
+    ring_offsets: &[u32],
+) -> (Vec<(usize, usize)>, Vec<Vec<usize>>) {
+    let segments = segments
+        .chunks_exact(2)
+        .map(|s| (s[0] as usize, s[1] as usize))
+        .collect();
+    let rings = ring_offsets
+        .windows(2)
+        .filter_map(|w| rings.get(w[0] as usize..w[1] as usize))
+        .filter(|ring| ring.len() >= 2)
+        .map(|ring| ring.iter().map(|&i| i as usize).collect())
+        .collect();
+    (segments, rings)
+}
+
+impl Triangulation {
+    /// Triangulate `points` with the given forced `segments` and closed
+    /// polygon `rings`, all as point indices. Indices of duplicate points are
+    /// mapped to the vertex they coincide with; out-of-range indices are
+    /// ignored. If the points do not span a triangle, there are no edges to
+    /// force and the constraints are dropped. Fails if a forced edge cannot
+    /// be inserted, which only rounding trouble on degenerate input causes.
+    pub(crate) fn constrained(
+        points: Vec<Pt>,
+        segments: &[(usize, usize)],
+        rings: &[Vec<usize>],
+    ) -> Result<Self, TriangulationError> {
+        let n = points.len();
+        let mut tri = Self::with_filter(points, |_| true);
+        if !tri.has_triangles() {
+            return Ok(tri);
+        }
+        let vertex: Vec<usize> = (0..n)
+            .map(|i| {
+                if tri.is_vertex(i) {
+                    i
+                } else {
+                    tri.nearest_vertex(tri.points[i]).unwrap_or(NONE)
+                }
+            })
+            .collect();
+        let edge = |a: usize, b: usize| (a < n && b < n).then(|| (vertex[a], vertex[b]));
+
+        for &(a, b) in segments {
+            if let Some((a, b)) = edge(a, b) {
+                tri.insert_segment(a, b, Constraint::Segment)?;
+            }
+        }
+        for ring in rings {
+            for (k, &a) in ring.iter().enumerate() {
+                let b = ring[(k + 1) % ring.len()];
+                if let Some((a, b)) = edge(a, b) {
+                    tri.insert_segment(a, b, Constraint::Boundary)?;
+                }
+            }
+        }
+        Ok(tri)
+    }
+
+    /// Record `a - b` as forced. An edge forced twice as a boundary, e.g. by
+    /// two rings sharing it, separates nothing but stays forced.
+    fn add_constraint(&mut self, a: usize, b: usize, kind: Constraint) {
+        self.constraints
+            .entry(Edge::new(a, b))
+            .and_modify(|k| *k = k.merge(kind))
+            .or_insert(kind);
+    }
+
+    /// Half-edge `u -> v`, if the two vertices are connected.
+    pub(crate) fn find_edge(&self, u: usize, v: usize) -> Option<usize> {
+        self.star(u).find(|&e| self.verts[next(e)] == v)
+    }
+
+    /// Force the edge between vertices `a` and `b` into the triangulation.
+    pub(crate) fn insert_segment(
+        &mut self,
+        a: usize,
+        b: usize,
+        kind: Constraint,
+    ) -> Result<(), TriangulationError> {
+        let vertex = |i: usize| i < self.points.len() && self.is_vertex(i);
+        if !vertex(a) || !vertex(b) {
+            return Err(constraint_error(a, b, "an endpoint is not a vertex"));
+        }
+        let mut work = vec![(a, b)];
+        let mut crossing = Vec::new();
+        while let Some((a, b)) = work.pop() {
+            if a == b {
+                continue;
+            }
+            if self.find_edge(a, b).is_some() {
+                self.add_constraint(a, b, kind);
+                continue;
+            }
+            crossing.clear();
+            match self.walk_segment(a, b, &mut crossing)? {
+                Walk::End => self.flip_crossing(a, b, &crossing, kind)?,
+                Walk::Vertex(x) => {
+                    work.push((x, b));
+                    if crossing.is_empty() {
+                        // `x` is a neighbour of `a` along the segment.
+                        self.add_constraint(a, x, kind);
+                    } else {
+                        self.flip_crossing(a, x, &crossing, kind)?;
+                    }
+                }
+                Walk::Crossing(u, v) => {
+                    let w = self.split_crossing(a, b, u, v)?;
+                    work.push((w, b));
+                    work.push((a, w));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Walk from vertex `a` towards `b`, collecting the edges the segment
+    /// crosses as vertex pairs `(u, v)` with `u` right and `v` left of it.
+    fn walk_segment(
+        &self,
+        a: usize,
+        b: usize,
+        crossing: &mut Vec<(usize, usize)>,
+    ) -> Result<Walk, TriangulationError> {
+        let (pa, pb) = (self.points[a], self.points[b]);
+        // Find the edge along the segment or the triangle around `a` that
+        // the segment leaves through.
+        let mut h = NONE;
+        for e in self.star(a) {
+            let (c, d) = (self.verts[next(e)], self.verts[prev(e)]);
+            if c == GHOST {
+                continue;
+            }
+            let pc = self.points[c];
+            if orient2d(pa, pb, pc) == 0.0 && pc.sub(pa).dot(pb.sub(pa)) > 0.0 {
+                return Ok(Walk::Vertex(c));
+            }
+            if d != GHOST && orient2d(pa, pc, pb) > 0.0 && orient2d(pa, self.points[d], pb) < 0.0 {
+                h = next(e);
+                break;
+            }
+        }
+        if h == NONE {
+            return Err(constraint_error(a, b, "it leaves the triangulation"));
+        }
+
+        loop {
+            let (u, v) = (self.verts[h], self.verts[next(h)]);
+            if self.is_constrained(h) {
+                return Ok(Walk::Crossing(u, v));
+            }
+            crossing.push((u, v));
+            let f = self.twins[h];
+            let x = self.verts[prev(f)];
+            if x == b {
+                return Ok(Walk::End);
+            }
+            let s = orient2d(pa, pb, self.points[x]);
+            if s == 0.0 {
+                return Ok(Walk::Vertex(x));
+            }
+            h = if s > 0.0 { next(f) } else { prev(f) };
+        }
+    }
+
+    /// Flip the edges crossing `a - b` until it is an edge, then force it and
+    /// restore the Delaunay property around the new diagonals.
+    fn flip_crossing(
+        &mut self,
+        a: usize,
+        b: usize,
+        crossing: &[(usize, usize)],
+        kind: Constraint,
+    ) -> Result<(), TriangulationError> {
+        let (pa, pb) = (self.points[a], self.points[b]);
+        let mut queue: VecDeque<(usize, usize)> = crossing.iter().copied().collect();
+        let mut fresh = Vec::new();
+        while let Some((u, v)) = queue.pop_front() {
+            let e = self
+                .find_edge(u, v)
+                .ok_or_else(|| constraint_error(a, b, "a crossing edge vanished"))?;
+            let c = self.verts[prev(e)];
+            let d = self.verts[prev(self.twins[e])];
+            let (pc, pd) = (self.points[c], self.points[d]);
+            // Only a strictly convex quadrilateral can be flipped; another
+            // flip will make this one convex later.
+            if orient2d(pc, pd, self.points[u]) * orient2d(pc, pd, self.points[v]) >= 0.0 {
+                queue.push_back((u, v));
+                continue;
+            }
+            self.flip(e);
+            let (sc, sd) = (orient2d(pa, pb, pc), orient2d(pa, pb, pd));
+            if sc * sd < 0.0 {
+                // Keep "right vertex first" for the next round.
+                queue.push_back(if sc < 0.0 { (c, d) } else { (d, c) });
+            } else {
+                fresh.push((c, d));
+            }
+        }
+        self.add_constraint(a, b, kind);
+
+        // Every triangle in the re-triangulated region has a new edge, so
+        // checking the triangles on both sides of them covers the old
+        // boundary edges of the region too.
+        fresh.push((a, b));
+        let mut stack = Vec::new();
+        for (c, d) in fresh {
+            if let Some(e) = self.find_edge(c, d) {
+                let f = self.twins[e];
+                stack.extend([e, next(e), prev(e), next(f), prev(f)]);
+            }
+        }
+        self.legalize(&mut stack);
+        Ok(())
+    }
+
+    /// Resolve the crossing of the new segment `a - b` with the forced edge
+    /// `u - v` and return the vertex both pass through. The intersection is
+    /// rounded, so one that is within rounding error of an endpoint snaps to
+    /// it; otherwise the next split could land on the wrong side of the
+    /// previous one, over and over.
+    fn split_crossing(
+        &mut self,
+        a: usize,
+        b: usize,
+        u: usize,
+        v: usize,
+    ) -> Result<usize, TriangulationError> {
+        let [pa, pb, pu, pv] = [a, b, u, v].map(|i| self.points[i]);
+        let q = intersection(pa, pb, pu, pv);
+        let near = |x: Pt, len2: f64| x.sub(q).len2() <= SNAP * len2;
+        let (ab, uv) = (pa.sub(pb).len2(), pu.sub(pv).len2());
+        if near(pa, ab) || near(pb, ab) {
+            let w = if near(pa, ab) { a } else { b };
+            self.reroute_constraint(u, v, w)?;
+            Ok(w)
+        } else if near(pu, uv) {
+            Ok(u)
+        } else if near(pv, uv) {
+            Ok(v)
+        } else {
+            self.split_constraint(u, v, q)
+        }
+    }
+
+    /// Split the forced edge `u - v` by inserting `q` (or reusing the vertex
+    /// already at `q`) and force both halves. Returns the splitting vertex.
+    pub(crate) fn split_constraint(
+        &mut self,
+        u: usize,
+        v: usize,
+        q: Pt,
+    ) -> Result<usize, TriangulationError> {
+        let i = self.push_point(q);
+        let w = match self.insert(i) {
+            Insertion::Inserted => i,
+            Insertion::Duplicate(w) => {
+                self.pop_point();
+                w
+            }
+            Insertion::Deferred | Insertion::Hidden => {
+                self.pop_point();
+                return Err(constraint_error(u, v, "the split point was not inserted"));
+            }
+        };
+        if w != u && w != v {
+            self.reroute_constraint(u, v, w)?;
+        }
+        Ok(w)
+    }
+
+    /// Replace the forced edge `u - v` by `u - w` and `w - v`. Nothing to do
+    /// if inserting `w` has split it already.
+    fn reroute_constraint(
+        &mut self,
+        u: usize,
+        v: usize,
+        w: usize,
+    ) -> Result<(), TriangulationError> {
+        let Some(kind) = self.constraints.remove(&Edge::new(u, v)) else {
+            return Ok(());
+        };
+        // The edge is no longer forced and may have to go: `w` was rounded
+        // off it and sits next to it in a sliver.
+        if let Some(e) = self.find_edge(u, v) {
+            self.legalize(&mut vec![e]);
+        }
+        self.insert_segment(u, w, kind)?;
+        self.insert_segment(w, v, kind)
+    }
+
+    /// Solid triangles inside the shape: reached from outside the convex hull
+    /// by crossing an odd number of boundary edges. Without boundaries every
+    /// solid triangle counts.
+    pub(crate) fn interior_triangles(&self) -> Vec<bool> {
+        let solid: Vec<bool> = (0..self.tri_count()).map(|t| self.is_solid(t)).collect();
+        if !self
+            .constraints
+            .values()
+            .any(|&k| k == Constraint::Boundary)
+        {
+            return solid;
+        }
+        let is_boundary = |e: usize| {
+            let edge = Edge::new(self.verts[e], self.verts[next(e)]);
+            self.constraints.get(&edge) == Some(&Constraint::Boundary)
+        };
+
+        let mut inside: Vec<Option<bool>> = vec![None; self.tri_count()];
+        let mut queue = VecDeque::new();
+        for e in 0..self.verts.len() {
+            let t = e / 3;
+            if solid[t] && inside[t].is_none() && self.is_ghost(self.twins[e] / 3) {
+                inside[t] = Some(is_boundary(e));
+                queue.push_back(t);
+            }
+        }
+        while let Some(t) = queue.pop_front() {
+            let here = inside[t] == Some(true);
+            for e in 3 * t..3 * t + 3 {
+                let n = self.twins[e] / 3;
+                if solid[n] && inside[n].is_none() {
+                    inside[n] = Some(here != is_boundary(e));
+                    queue.push_back(n);
+                }
+            }
+        }
+        inside.into_iter().map(|i| i == Some(true)).collect()
+    }
+}
+
+#[wasm_bindgen]
+impl VoronoiTests {
+    /// Constrained Delaunay triangulation of a shape. Points use the layout
+    /// [x,y,...]; `segments` are forced edges as index pairs. `rings` holds
+    /// closed polygon boundaries as point indices, ring `i` being
+    /// `rings[ring_offsets[i]..ring_offsets[i + 1]]`. Only triangles inside
+    /// an odd number of rings are returned, so inner rings cut holes; without
+    /// rings the whole convex hull is triangulated. Fails on non-finite
+    /// coordinates and on forced edges that cannot be inserted.
+    pub fn constrained_delaunay(
+        points_flat: &[f32],
+        segments: &[u32],
+        rings: &[u32],
+        ring_offsets: &[u32],
+    ) -> Result<ConstrainedTriangulation, TriangulationError> {
+        let points = read_xy(points_flat);
+        if !points.is_empty() {
+            check_points(&points)?;
+        }
+        let (segments, rings) = read_constraints(segments, rings, ring_offsets);
+        let tri = Triangulation::constrained(points, &segments, &rings)?;
+
+        let kept = tri.interior_triangles();
+        let mut triangles = Vec::new();
+        for t in (0..kept.len()).filter(|&t| kept[t]) {
+            triangles.extend(tri.tri_verts(t).map(|v| v as u32));
+        }
+        let mut edges: Vec<&Edge> = tri.constraints.keys().collect();
+        edges.sort_by_key(|e| (e.a, e.b));
+        let constrained_edges = edges
+            .into_iter()
+            .flat_map(|e| [e.a as u32, e.b as u32])
+            .collect();
+        Ok(ConstrainedTriangulation {
+            points: tri
+                .points
+                .iter()
+                .flat_map(|p| [p.x as f32, p.y as f32])
+                .collect(),
+            triangles,
+            constrained_edges,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rand::hash_u32;
+    use crate::voronoi::predicates::incircle;
+    use crate::voronoi::test_util::{pt, random_points};
+
+    /// Triangles are counter-clockwise, forced edges are edges, and every
+    /// other edge is locally Delaunay.
+    fn assert_constrained_delaunay(tri: &Triangulation) {
+        for t in tri.triangles() {
+            let [a, b, c] = [t.a, t.b, t.c].map(|v| tri.points[v]);
+            assert!(orient2d(a, b, c) > 0.0, "inverted triangle {t:?}");
+        }
+        for e in tri.constraints.keys() {
+            assert!(tri.find_edge(e.a, e.b).is_some(), "{e:?} is missing");
+        }
+        for e in 0..tri.verts.len() {
+            let f = tri.twins[e];
+            if f == NONE || !tri.is_solid(e / 3) || !tri.is_solid(f / 3) || tri.is_constrained(e) {
+                continue;
+            }
+            let [a, b, c] = tri.tri_verts(e / 3).map(|v| tri.points[v]);
+            let d = tri.points[tri.verts[prev(f)]];
+            assert!(incircle(a, b, c, d) <= 0.0, "edge {e} is not Delaunay");
+        }
+    }
+
+    fn inside_area(tri: &Triangulation) -> f64 {
+        let kept = tri.interior_triangles();
+        (0..kept.len())
+            .filter(|&t| kept[t])
+            .map(|t| {
+                let [a, b, c] = tri.tri_verts(t).map(|v| tri.points[v]);
+                orient2d(a, b, c) / 2.0
+            })
+            .sum()
+    }
+
+    /// The forced edges on the segment `a - b` cover all of it.
+    fn assert_covered(tri: &Triangulation, a: usize, b: usize) {
+        let (pa, pb) = (tri.points[a], tri.points[b]);
+        let len = pa.sub(pb).len2().sqrt();
+        let on_segment = |q: Pt| {
+            let d = orient2d(pa, pb, q).abs() / len;
+            let t = q.sub(pa).dot(pb.sub(pa)) / (len * len);
+            d < 1e-6 && (-1e-9..=1.0 + 1e-9).contains(&t)
+        };
+        let covered: f64 = tri
+            .constraints
+            .keys()
+            .filter(|e| on_segment(tri.points[e.a]) && on_segment(tri.points[e.b]))
+            .map(|e| tri.points[e.a].sub(tri.points[e.b]).len2().sqrt())
+            .sum();
+        assert!(covered >= len - 1e-6, "{a} - {b}: {covered} of {len}");
+    }
+
+    #[test]
+    fn forced_edges_through_collinear_vertices() {
+        // Segments between grid points run through other grid points.
+        let grid: Vec<Pt> = (0..144)
+            .map(|i| pt((i % 12) as f64, (i / 12) as f64))
+            .collect();
+        for seed in 0..60u32 {
+            let segments: Vec<(usize, usize)> = (0..15)
+                .map(|k| {
+                    let a = hash_u32(seed * 13 + k) % 144;
+                    let b = hash_u32(seed * 17 + k + 99) % 144;
+                    (a as usize, b as usize)
+                })
+                .collect();
+            let rings = [vec![0, 11, 143, 132], vec![13, 26, 37]];
+            let tri = Triangulation::constrained(grid.clone(), &segments, &rings).unwrap();
+            assert_constrained_delaunay(&tri);
+            // Forced edges are split at every grid point they pass.
+            for e in tri.constraints.keys() {
+                let (pa, pb) = (tri.points[e.a], tri.points[e.b]);
+                let inner = grid
+                    .iter()
+                    .any(|&q| orient2d(pa, pb, q) == 0.0 && q.sub(pa).dot(q.sub(pb)) < 0.0);
+                assert!(!inner, "seed {seed}: {e:?} runs through a grid point");
+            }
+            for &(a, b) in &segments {
+                assert_covered(&tri, a, b);
+            }
+            // The 11 x 11 square without the half-unit triangle twice.
+            assert!((inside_area(&tri) - 120.0).abs() < 1e-9, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn crossing_segments_get_steiner_points() {
+        for seed in 1..80u32 {
+            let mut points = vec![
+                pt(0.0, 0.0),
+                pt(10.0, 0.0),
+                pt(10.0, 10.0),
+                pt(0.0, 10.0),
+                pt(3.0, 3.0),
+                pt(3.0, 7.0),
+                pt(7.0, 7.0),
+                pt(7.0, 3.0),
+            ];
+            points.extend(random_points(60, seed, 10.0));
+            let segments: Vec<(usize, usize)> = (0..30)
+                .map(|k| {
+                    let a = hash_u32(seed * 77 + k) % 60;
+                    let b = hash_u32(seed * 91 + k * 3 + 1) % 60;
+                    (8 + a as usize, 8 + b as usize)
+                })
+                .collect();
+            let rings = [vec![0, 1, 2, 3], vec![4, 5, 6, 7]];
+            let tri = Triangulation::constrained(points, &segments, &rings).unwrap();
+            assert_constrained_delaunay(&tri);
+            assert!(tri.points.len() > 68, "seed {seed}: no crossings");
+            for &(a, b) in &segments {
+                assert_covered(&tri, a, b);
+            }
+            let area = inside_area(&tri);
+            assert!((area - 84.0).abs() < 1e-6, "seed {seed}: area {area}");
+        }
+    }
+
+    #[test]
+    fn nested_rings_alternate() {
+        // An island in a hole in a square, plus a ring that shares an edge
+        // with the square and only adds area.
+        let square =
+            |x: f64, y: f64, s: f64| [pt(x, y), pt(x + s, y), pt(x + s, y + s), pt(x, y + s)];
+        let mut points: Vec<Pt> = [
+            square(0.0, 0.0, 10.0),
+            square(2.0, 2.0, 6.0),
+            square(4.0, 4.0, 2.0),
+        ]
+        .concat();
+        points.extend([pt(20.0, 0.0), pt(20.0, 10.0)]);
+        let rings = [
+            vec![0, 1, 2, 3],
+            vec![4, 7, 6, 5],
+            vec![8, 9, 10, 11],
+            vec![1, 12, 13, 2],
+        ];
+        let tri = Triangulation::constrained(points.clone(), &[], &rings).unwrap();
+        assert_constrained_delaunay(&tri);
+        let area = inside_area(&tri);
+        assert!((area - (100.0 - 36.0 + 4.0 + 100.0)).abs() < 1e-9, "{area}");
+        // The shared edge separates nothing but stays forced.
+        assert_eq!(tri.constraints[&Edge::new(1, 2)], Constraint::Segment);
+
+        // Without rings the whole hull is kept.
+        let tri = Triangulation::constrained(points, &[(0, 6)], &[]).unwrap();
+        assert!((inside_area(&tri) - 200.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn bad_constraints() {
+        let points = random_points(20, 3, 10.0);
+        // Out-of-range indices are skipped.
+        let tri = Triangulation::constrained(points.clone(), &[(0, 20), (1, 2)], &[]).unwrap();
+        assert_eq!(tri.constraints.len(), 1);
+
+        let mut tri = Triangulation::new(&points);
+        tri.push_point(pt(50.0, 50.0));
+        for (a, b) in [(0, 20), (0, 99)] {
+            let err = tri.insert_segment(a, b, Constraint::Segment).unwrap_err();
+            assert_eq!(err.kind, TriangulationErrorKind::InvalidConstraint);
+        }
+
+        let flat = [0.0, 0.0, 1.0, 0.0, f32::NAN, 1.0];
+        let err = VoronoiTests::constrained_delaunay(&flat, &[0, 1], &[], &[]).err();
+        assert_eq!(err.map(|e| e.kind), Some(TriangulationErrorKind::NonFinite));
+        let empty = VoronoiTests::constrained_delaunay(&[], &[], &[], &[]).ok();
+        assert!(empty.is_some_and(|t| t.triangles.is_empty()));
+    }
+}
@@ -0,0 +1,440 @@
+//! Closed Voronoi cells, one polygon per site, clipped to a convex boundary.
+//!
+//! A Voronoi cell is the intersection of the half-planes "closer to the site
+//! than to a neighbour" over all Delaunay neighbours of the site. Starting
+//! from the clip polygon and cutting it with those bisectors gives bounded,
+//! closed cells for hull sites too, without special handling of the
+//! unbounded Voronoi edges.
+
+use super::degenerate::{TriangulationError, TriangulationErrorKind};
+use super::triangulation::{GHOST, NONE, Triangulation};
+use super::{Pt, VoronoiTests, orient2d, read_points, read_xy};
+use wasm_bindgen::prelude::*;
+
+/// Voronoi cells as a flat buffer.
+///
+/// The polygon of site `i` is made of the points `offsets[i]..offsets[i + 1]`
+/// of `coords`, where point `k` is `(coords[2k], coords[2k + 1])`. Polygons are
+/// counter-clockwise and not closed explicitly; empty for duplicate sites.
+#[wasm_bindgen(getter_with_clone)]
+pub struct VoronoiCells {
+    #[wasm_bindgen(readonly)]
+    pub coords: Vec<f32>,
+    #[wasm_bindgen(readonly)]
+    pub offsets: Vec<u32>,
+}
+
+impl VoronoiCells {
+    pub(crate) fn from_polygons(cells: &[Vec<Pt>]) -> Self {
+        let total: usize = cells.iter().map(Vec::len).sum();
+        let mut coords = Vec::with_capacity(total * 2);
+        let mut offsets = Vec::with_capacity(cells.len() + 1);
+        offsets.push(0);
+        for cell in cells {
+            for p in cell {
+                coords.push(p.x as f32);
+                coords.push(p.y as f32);
+            }
+            offsets.push((coords.len() / 2) as u32);
+        }
+        Self { coords, offsets }
+    }
+}
+
+/// Axis-aligned rectangle as a counter-clockwise clip polygon.
+pub(crate) fn rect_polygon(min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> Vec<Pt> {
+    vec![
+        Pt { x: min_x, y: min_y },
+        Pt { x: max_x, y: min_y },
+        Pt { x: max_x, y: max_y },
+        Pt { x: min_x, y: max_y },
+    ]
+}
+
+/// Read a convex polygon `[x0,y0,x1,y1,...]` and orient it counter-clockwise.
+/// Repeated points (and a closing copy of the first one) are dropped;
+/// anything that is not a convex polygon with area is an error.
+pub(crate) fn read_convex_polygon(flat: &[f32]) -> Result<Vec<Pt>, TriangulationError> {
+    let mut poly = read_xy(flat);
+    poly.dedup();
+    if poly.len() > 1 && poly.first() == poly.last() {
+        poly.pop();
+    }
+    if signed_area(&poly) < 0.0 {
+        poly.reverse();
+    }
+    if is_convex(&poly) {
+        Ok(poly)
+    } else {
+        Err(TriangulationError::new(
+            TriangulationErrorKind::InvalidBoundary,
+            "the clip boundary is not a convex polygon",
+        ))
+    }
+}
+
+/// `true` for a counter-clockwise convex polygon with positive area.
+/// Every corner has to turn left (or go straight), and the turns have to
+/// add up to one full turn; a star polygon turns left only but goes round
+/// more than once.
+fn is_convex(poly: &[Pt]) -> bool {
+    let n = poly.len();
+    let finite = poly.iter().all(|p| p.x.is_finite() && p.y.is_finite());
+    if n < 3 || !finite || signed_area(poly) <= 0.0 {
+        return false;
+    }
+    let mut turning = 0.0;
+    for k in 0..n {
+        let (a, b, c) = (poly[k], poly[(k + 1) % n], poly[(k + 2) % n]);
+        if orient2d(a, b, c) < 0.0 {
+            return false;
+        }
+        let (u, v) = (b.sub(a), c.sub(b));
+        turning += (u.x * v.y - u.y * v.x).atan2(u.dot(v));
+    }
+    turning < 3.0 * std::f64::consts::PI
+}
+
+/// Signed area of a polygon, positive for counter-clockwise winding.
+pub(crate) fn signed_area(poly: &[Pt]) -> f64 {
+    let n = poly.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (poly[i], poly[(i + 1) % n]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum::<f64>()
+        * 0.5
+}
+
+/// Area-weighted centroid of a polygon, or `None` if it has no area.
+pub(crate) fn polygon_centroid(poly: &[Pt]) -> Option<Pt> {
+    let n = poly.len();
+    let origin = *poly.first()?;
+    let mut area2 = 0.0;
+    let mut cx = 0.0;
+    let mut cy = 0.0;
+    for i in 0..n {
+        // Relative to the first vertex to keep the sums well conditioned.
+        let a = poly[i].sub(origin);
+        let b = poly[(i + 1) % n].sub(origin);
+        let cross = a.x * b.y - b.x * a.y;
+        area2 += cross;
+        cx += (a.x + b.x) * cross;
+        cy += (a.y + b.y) * cross;
+    }
+    if area2 == 0.0 {
+        return None;
+    }
+    Some(origin.add(Pt {
+        x: cx / (3.0 * area2),
+        y: cy / (3.0 * area2),
+    }))
+}
+
+/// A convex cell whose edge `k`, from `polygon[k]` to `polygon[k + 1]`,
+/// lies on the bisector with site `across[k]`, or on the clip polygon if
+/// that is [`NONE`].
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LabelledCell {
+    pub(crate) polygon: Vec<Pt>,
+    pub(crate) across: Vec<usize>,
+}
+
+impl LabelledCell {
+    pub(crate) fn new(clip: &[Pt]) -> Self {
+        Self {
+            polygon: clip.to_vec(),
+            across: vec![NONE; clip.len()],
+        }
+    }
+
+    /// Keep the part where `(p - m) · n <= 0` (Sutherland–Hodgman); the
+    /// new edge along the line is labelled `label`.
+    pub(crate) fn clip_half_plane(&self, m: Pt, n: Pt, label: usize) -> Self {
+        let mut out = Self {
+            polygon: Vec::with_capacity(self.polygon.len() + 1),
+            across: Vec::with_capacity(self.polygon.len() + 1),
+        };
+        let (Some(&last), Some(&last_across)) = (self.polygon.last(), self.across.last()) else {
+            return out;
+        };
+        let (mut prev, mut prev_across) = (last, last_across);
+        let mut prev_d = prev.sub(m).dot(n);
+        for (&cur, &cur_across) in self.polygon.iter().zip(&self.across) {
+            let cur_d = cur.sub(m).dot(n);
+            let cut = || prev.add(cur.sub(prev).mul(prev_d / (prev_d - cur_d)));
+            if cur_d <= 0.0 {
+                if prev_d > 0.0 {
+                    // Entering: the rest of the old edge keeps its label.
+                    out.polygon.push(cut());
+                    out.across.push(prev_across);
+                }
+                out.polygon.push(cur);
+                out.across.push(cur_across);
+            } else if prev_d <= 0.0 {
+                // Leaving: the edge to the entry point runs along the line.
+                out.polygon.push(cut());
+                out.across.push(label);
+            }
+            (prev, prev_across, prev_d) = (cur, cur_across, cur_d);
+        }
+        out
+    }
+
+    /// Cut the cell down to the points closer to `site` than to `other`.
+    pub(crate) fn clip_bisector(&self, site: Pt, other: Pt, other_id: usize) -> Self {
+        self.clip_half_plane(site.add(other).mul(0.5), other.sub(site), other_id)
+    }
+}
+
+/// Keep the part of `poly` where `(p - m) · n <= 0`.
+pub(crate) fn clip_half_plane(poly: &[Pt], m: Pt, n: Pt) -> Vec<Pt> {
+    LabelledCell::new(poly).clip_half_plane(m, n, NONE).polygon
+}
+
+/// Cut `poly` down to the points closer to `site` than to `other`.
+pub(crate) fn clip_bisector(poly: &[Pt], site: Pt, other: Pt) -> Vec<Pt> {
+    LabelledCell::new(poly)
+        .clip_bisector(site, other, NONE)
+        .polygon
+}
+
+/// Voronoi cell of every point of the triangulation, clipped to the convex,
+/// counter-clockwise polygon `clip`. Points that are not vertices
+/// (duplicates) get an empty cell.
+pub(crate) fn clipped_cells(tri: &Triangulation, clip: &[Pt]) -> Vec<Vec<Pt>> {
+    labelled_cells(tri, clip)
+        .into_iter()
+        .map(|cell| cell.polygon)
+        .collect()
+}
+
+/// [`clipped_cells`] with every edge labelled by the site across it.
+pub(crate) fn labelled_cells(tri: &Triangulation, clip: &[Pt]) -> Vec<LabelledCell> {
+    let points = &tri.points;
+    if !tri.has_triangles() {
+        return collinear_cells(points, clip);
+    }
+    (0..points.len())
+        .map(|i| {
+            if !tri.is_vertex(i) {
+                return LabelledCell::default();
+            }
+            let mut cell = LabelledCell::new(clip);
+            for j in tri.neighbors(i) {
+                if j == GHOST {
+                    continue;
+                }
+                cell = cell.clip_bisector(points[i], points[j], j);
+                if cell.polygon.is_empty() {
+                    break;
+                }
+            }
+            cell
+        })
+        .collect()
+}
+
+/// Cells of sites without a triangulation (fewer than three points, or all of
+/// them on one line): every site only borders its neighbours along the line.
+fn collinear_cells(points: &[Pt], clip: &[Pt]) -> Vec<LabelledCell> {
+    let mut cells = vec![LabelledCell::default(); points.len()];
+    let order = collinear_order(points);
+    for (k, &i) in order.iter().enumerate() {
+        let mut cell = LabelledCell::new(clip);
+        if k > 0 {
+            let j = order[k - 1];
+            cell = cell.clip_bisector(points[i], points[j], j);
+        }
+        if k + 1 < order.len() {
+            let j = order[k + 1];
+            cell = cell.clip_bisector(points[i], points[j], j);
+        }
+        cells[i] = cell;
+    }
+    cells
+}
+
+/// Indices of points that all lie on one line, sorted along it, with
+/// duplicates removed (the first index of each position is kept) and
+/// non-finite points left out.
+pub(crate) fn collinear_order(points: &[Pt]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..points.len())
+        .filter(|&i| points[i].x.is_finite() && points[i].y.is_finite())
+        .collect();
+    let Some(&first) = order.first() else {
+        return order;
+    };
+    let origin = points[first];
+    let dir = order
+        .iter()
+        .map(|&i| points[i].sub(origin))
+        .max_by(|a, b| a.len2().total_cmp(&b.len2()))
+        .unwrap_or(Pt { x: 1.0, y: 0.0 });
+
+    order.sort_by(|&a, &b| {
+        let ta = points[a].sub(origin).dot(dir);
+        let tb = points[b].sub(origin).dot(dir);
+        ta.total_cmp(&tb).then(a.cmp(&b))
+    });
+    order.dedup_by(|b, a| points[*a] == points[*b]);
+    order
+}
+
+#[wasm_bindgen]
+impl VoronoiTests {
+    /// Voronoi cell of every site as a closed polygon, clipped to the
+    /// rectangle `[min_x, max_x] x [min_y, max_y]`.
+    /// Input layout: [x,y,vx,vy,...]
+    pub fn voronoi_cells(
+        points_flat: &[f32],
+        min_x: f32,
+        min_y: f32,
+        max_x: f32,
+        max_y: f32,
+    ) -> VoronoiCells {
+        let clip = rect_polygon(min_x as f64, min_y as f64, max_x as f64, max_y as f64);
+        cells_for(points_flat, &clip)
+    }
+
+    /// Voronoi cell of every site, clipped to a convex polygon given as
+    /// [x0,y0,x1,y1,...] in either winding order. Fails if the boundary is
+    /// not convex.
+    pub fn voronoi_cells_clipped(
+        points_flat: &[f32],
+        boundary: &[f32],
+    ) -> Result<VoronoiCells, TriangulationError> {
+        let clip = read_convex_polygon(boundary)?;
+        Ok(cells_for(points_flat, &clip))
+    }
+}
+
+fn cells_for(points_flat: &[f32], clip: &[Pt]) -> VoronoiCells {
+    let pts = read_points(points_flat);
+    let tri = Triangulation::new(&pts);
+    VoronoiCells::from_polygons(&clipped_cells(&tri, clip))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voronoi::test_util::{pt, random_points};
+
+    fn nearest(points: &[Pt], p: Pt) -> usize {
+        (0..points.len())
+            .min_by(|&a, &b| {
+                let da = points[a].sub(p).len2();
+                let db = points[b].sub(p).len2();
+                da.total_cmp(&db)
+            })
+            .unwrap()
+    }
+
+    /// Cells tile the clip polygon and every cell corner is at least as
+    /// close to its own site as to any other.
+    fn assert_cells(points: &[Pt], cells: &[Vec<Pt>], clip: &[Pt]) {
+        let total: f64 = cells.iter().map(|c| signed_area(c)).sum();
+        assert!((total - signed_area(clip)).abs() < 1e-6 * signed_area(clip));
+        for (i, cell) in cells.iter().enumerate() {
+            assert!(signed_area(cell) >= 0.0);
+            for &q in cell {
+                let own = points[i].sub(q).len2();
+                let best = points[nearest(points, q)].sub(q).len2();
+                assert!(own <= best * (1.0 + 1e-9) + 1e-9, "cell {i} corner {q:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn cells_tile_the_clip_polygon() {
+        // Some sites lie outside the clip polygon.
+        let mut points: Vec<Pt> = random_points(300, 3, 120.0)
+            .into_iter()
+            .map(|p| pt(p.x - 10.0, p.y * (80.0 / 120.0) - 10.0))
+            .collect();
+        points.push(points[5]);
+        let tri = Triangulation::new(&points);
+
+        let rect = rect_polygon(0.0, 0.0, 100.0, 60.0);
+        let cells = clipped_cells(&tri, &rect);
+        assert_cells(&points, &cells, &rect);
+        assert!(cells[300].is_empty(), "duplicate site has a cell");
+
+        let hexagon: Vec<Pt> = (0..6)
+            .map(|k| {
+                let a = k as f64 * std::f64::consts::FRAC_PI_3;
+                pt(50.0 + 30.0 * a.cos(), 30.0 + 30.0 * a.sin())
+            })
+            .collect();
+        let cells = clipped_cells(&tri, &hexagon);
+        assert_cells(&points, &cells, &hexagon);
+
+        // Non-finite sites get no cell, also among sites on one line.
+        let nan = pt(f64::NAN, 5.0);
+        let line = [pt(10.0, 30.0), pt(50.0, 30.0), pt(90.0, 30.0)];
+        for points in [
+            [line[0], line[1], line[2], nan],
+            [nan, line[0], line[1], line[2]],
+        ] {
+            let cells = clipped_cells(&Triangulation::new(&points), &rect);
+            assert_cells(&points, &cells, &rect);
+            let areas: Vec<f64> = cells.iter().map(|c| signed_area(c)).collect();
+            let nan_at = points.iter().position(|p| p.x.is_nan()).unwrap();
+            assert_eq!(areas[nan_at], 0.0);
+            let mut rest = areas;
+            rest.remove(nan_at);
+            assert_eq!(rest, [1800.0, 2400.0, 1800.0]);
+        }
+        let mut points = random_points(50, 8, 100.0);
+        points.insert(0, pt(3.0, f64::INFINITY));
+        let cells = clipped_cells(&Triangulation::new(&points), &rect);
+        assert!(cells[0].is_empty());
+        assert_cells(&points, &cells, &rect);
+    }
+
+    #[test]
+    fn collinear_sites_get_strips() {
+        let points = [
+            pt(70.0, 30.0),
+            pt(10.0, 30.0),
+            pt(40.0, 30.0),
+            pt(10.0, 30.0),
+        ];
+        let tri = Triangulation::new(&points);
+        assert!(!tri.has_triangles());
+        let rect = rect_polygon(0.0, 0.0, 100.0, 60.0);
+        let cells = clipped_cells(&tri, &rect);
+        assert_cells(&points, &cells, &rect);
+        // Bisectors at x = 25 and x = 55; the duplicate has no cell.
+        let areas: Vec<f64> = cells.iter().map(|c| signed_area(c)).collect();
+        assert_eq!(areas, [45.0 * 60.0, 25.0 * 60.0, 30.0 * 60.0, 0.0]);
+
+        let single = Triangulation::new(&[pt(5.0, 5.0)]);
+        let cells = clipped_cells(&single, &rect);
+        assert_eq!(signed_area(&cells[0]), 6000.0);
+    }
+
+    #[test]
+    fn clip_boundary_has_to_be_convex() {
+        let square = [0.0, 0.0, 0.0, 10.0, 10.0, 10.0, 10.0, 0.0, 0.0, 0.0];
+        let poly = read_convex_polygon(&square).unwrap();
+        assert_eq!(poly.len(), 4);
+        assert!(signed_area(&poly) > 0.0);
+
+        let l_shape = [
+            0.0, 0.0, 10.0, 0.0, 10.0, 5.0, 5.0, 5.0, 5.0, 10.0, 0.0, 10.0,
+        ];
+        let star: Vec<f32> = (0..5)
+            .flat_map(|k| {
+                let a = k as f32 * 4.0 * std::f32::consts::PI / 5.0;
+                [a.cos(), a.sin()]
+            })
+            .collect();
+        let line = [0.0, 0.0, 1.0, 1.0, 2.0, 2.0];
+        for boundary in [&l_shape[..], &star, &line, &[]] {
+            let err = read_convex_polygon(boundary).unwrap_err();
+            assert_eq!(err.kind, TriangulationErrorKind::InvalidBoundary);
+        }
+    }
+}
@@ -0,0 +1,353 @@
+//! What happens to inputs that have no proper triangulation.
+//!
+//! - Coordinates that are NaN or infinite are rejected.
+//! - Points closer than an epsilon become one vertex, and the result
+//!   reports for every input point the vertex it ended up as. An epsilon
+//!   that is not positive merges exact duplicates only.
+//! - Fewer than three distinct points, or points all on one line, have no
+//!   triangles and are returned as a line instead.
+//!
+//! [`VoronoiTests::delaunay`], [`VoronoiTests::delaunay_indices`] and
+//! [`VoronoiTests::voronoi_edges`] all return a [`DelaunayResult`] and throw
+//! a [`TriangulationError`] rather than return an empty buffer.
+
+use super::cells::collinear_order;
+use super::triangulation::Triangulation;
+use super::{Pt, VoronoiTests, read_points, triangle_indices, voronoi_edges_flat};
+use std::collections::HashMap;
+use std::fmt;
+use wasm_bindgen::prelude::*;
+
+/// Why the points could not be triangulated.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriangulationErrorKind {
+    /// The input is empty.
+    NoPoints = 0,
+    /// A coordinate is NaN or infinite.
+    NonFinite = 1,
+    /// A clip boundary is not a convex polygon.
+    InvalidBoundary = 4,
+    /// A grid of values does not match its dimensions.
+    InvalidGrid = 5,
+    /// A forced edge could not be inserted.
+    InvalidConstraint = 6,
+}
+
+/// Error thrown to JS by the triangulation and cell functions.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TriangulationError {
+    pub kind: TriangulationErrorKind,
+    pub message: String,
+}
+
+impl TriangulationError {
+    pub(crate) fn new(kind: TriangulationErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for TriangulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for TriangulationError {}
+
+/// Reject empty input and non-finite coordinates.
+pub(crate) fn check_points(points: &[Pt]) -> Result<(), TriangulationError> {
+    if points.is_empty() {
+        return Err(TriangulationError::new(
+            TriangulationErrorKind::NoPoints,
+            "no points to triangulate",
+        ));
+    }
+    match points
+        .iter()
+        .position(|p| !p.x.is_finite() || !p.y.is_finite())
+    {
+        Some(i) => Err(TriangulationError::new(
+            TriangulationErrorKind::NonFinite,
+            format!("point {i} has a non-finite coordinate"),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Merge points closer than `epsilon` (exact duplicates only if it is not
+/// positive). Each vertex sits at the first input point of its cluster; a
+/// point joins the first vertex within `epsilon` of it, in input order.
+/// Returns the vertices and the vertex of every input point.
+pub(crate) fn merge_points(points: &[Pt], epsilon: f64) -> (Vec<Pt>, Vec<usize>) {
+    let exact = !(epsilon > 0.0 && epsilon.is_finite());
+    let size = if exact { 1.0 } else { epsilon };
+    let cell = |p: Pt| ((p.x / size).floor() as i64, (p.y / size).floor() as i64);
+    let key = |p: Pt| ((p.x + 0.0).to_bits(), (p.y + 0.0).to_bits());
+
+    let mut vertices: Vec<Pt> = Vec::new();
+    let mut vertex_of = Vec::with_capacity(points.len());
+    let mut by_key: HashMap<(u64, u64), usize> = HashMap::new();
+    let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for &p in points {
+        let found = if exact {
+            by_key.get(&key(p)).copied()
+        } else {
+            let (cx, cy) = cell(p);
+            (cy - 1..=cy + 1)
+                .flat_map(|y| (cx - 1..=cx + 1).map(move |x| (x, y)))
+                .filter_map(|c| grid.get(&c))
+                .flatten()
+                .copied()
+                .filter(|&v| vertices[v].sub(p).len2() <= epsilon * epsilon)
+                .min()
+        };
+        let v = found.unwrap_or_else(|| {
+            let v = vertices.len();
+            vertices.push(p);
+            if exact {
+                by_key.insert(key(p), v);
+            } else {
+                grid.entry(cell(p)).or_default().push(v);
+            }
+            v
+        });
+        vertex_of.push(v);
+    }
+    (vertices, vertex_of)
+}
+
+/// Delaunay triangulation with duplicate merging. Either `triangles` or,
+/// for collinear input, `line` is filled; indices refer to `vertices`.
+/// [`VoronoiTests::delaunay_indices`] leaves `voronoi_edges` empty and
+/// [`VoronoiTests::voronoi_edges`] leaves `triangles` empty.
+#[wasm_bindgen(getter_with_clone)]
+pub struct DelaunayResult {
+    /// Merged vertex positions [x0,y0,x1,y1,...].
+    #[wasm_bindgen(readonly)]
+    pub vertices: Vec<f32>,
+    /// The vertex of every input point.
+    #[wasm_bindgen(readonly)]
+    pub vertex_of: Vec<u32>,
+    /// Counter-clockwise triangles as vertex triplets.
+    #[wasm_bindgen(readonly)]
+    pub triangles: Vec<u32>,
+    /// Voronoi edges between circumcenters [x1,y1,x2,y2,...]. Empty for a
+    /// line, whose Voronoi edges are all unbounded.
+    #[wasm_bindgen(readonly)]
+    pub voronoi_edges: Vec<f32>,
+    /// `true` if all vertices lie on one line (or there are fewer than three).
+    pub collinear: bool,
+    /// For collinear input, the vertices in order along the line.
+    #[wasm_bindgen(readonly)]
+    pub line: Vec<u32>,
+}
+
+/// Check `points_flat` ([x,y,vx,vy,...]), merge points closer than
+/// `epsilon` and triangulate the vertices. The result is filled in except
+/// for the triangles and Voronoi edges.
+pub(crate) fn triangulate_merged(
+    points_flat: &[f32],
+    epsilon: f32,
+) -> Result<(Triangulation, DelaunayResult), TriangulationError> {
+    let points = read_points(points_flat);
+    check_points(&points)?;
+    let (vertices, vertex_of) = merge_points(&points, epsilon as f64);
+    let tri = Triangulation::new(&vertices);
+    let collinear = !tri.has_triangles();
+    let result = DelaunayResult {
+        vertices: vertices
+            .iter()
+            .flat_map(|p| [p.x as f32, p.y as f32])
+            .collect(),
+        vertex_of: vertex_of.into_iter().map(|v| v as u32).collect(),
+        triangles: Vec::new(),
+        voronoi_edges: Vec::new(),
+        collinear,
+        line: if collinear {
+            collinear_order(&vertices)
+                .into_iter()
+                .map(|v| v as u32)
+                .collect()
+        } else {
+            Vec::new()
+        },
+    };
+    Ok((tri, result))
+}
+
+#[wasm_bindgen]
+impl VoronoiTests {
+    /// Delaunay triangulation after merging points closer than `epsilon`.
+    /// Collinear input gives a line instead of triangles; empty input and
+    /// non-finite coordinates are errors. Input layout: [x,y,vx,vy,...]
+    pub fn delaunay(
+        points_flat: &[f32],
+        epsilon: f32,
+    ) -> Result<DelaunayResult, TriangulationError> {
+        let (tri, mut result) = triangulate_merged(points_flat, epsilon)?;
+        result.triangles = triangle_indices(&tri);
+        result.voronoi_edges = voronoi_edges_flat(&tri);
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voronoi::Constraint;
+    use crate::voronoi::test_util::pt;
+
+    /// [x,y,vx,vy,...] for the given positions.
+    fn flat(xy: &[(f32, f32)]) -> Vec<f32> {
+        xy.iter().flat_map(|&(x, y)| [x, y, 0.0, 0.0]).collect()
+    }
+
+    #[test]
+    fn every_error_kind() {
+        use TriangulationErrorKind::*;
+        let kind = |r: Result<DelaunayResult, TriangulationError>| r.err().map(|e| e.kind);
+        assert_eq!(
+            kind(VoronoiTests::delaunay_indices(&[], 0.0)),
+            Some(NoPoints)
+        );
+        let nan = flat(&[(0.0, 0.0), (1.0, 0.0), (0.0, f32::NAN)]);
+        assert_eq!(
+            kind(VoronoiTests::delaunay_indices(&nan, 0.0)),
+            Some(NonFinite)
+        );
+        let infinite = flat(&[(0.0, 0.0), (f32::INFINITY, 0.0), (0.0, 1.0)]);
+        let err = VoronoiTests::voronoi_edges(&infinite, 0.0).err().unwrap();
+        assert_eq!(err.kind, NonFinite);
+        assert_eq!(err.message, "point 1 has a non-finite coordinate");
+
+        let sites = flat(&[(1.0, 1.0), (2.0, 1.0), (1.0, 2.0)]);
+        let bowtie = [0.0, 0.0, 4.0, 4.0, 4.0, 0.0, 0.0, 4.0];
+        let err = VoronoiTests::voronoi_cells_clipped(&sites, &bowtie).err();
+        assert_eq!(err.map(|e| e.kind), Some(InvalidBoundary));
+        let err =
+            VoronoiTests::lloyd_relax_weighted(&sites, 1, 0.0, 0.0, 4.0, 4.0, &[1.0; 3], 2, 2)
+                .err();
+        assert_eq!(err.map(|e| e.kind), Some(InvalidGrid));
+        let mut tri = Triangulation::new(&read_points(&sites));
+        let err = tri.insert_segment(0, 7, Constraint::Segment).unwrap_err();
+        assert_eq!(err.kind, InvalidConstraint);
+
+        // Valid input still triangulates.
+        let result = VoronoiTests::delaunay_indices(&sites, 0.0).unwrap();
+        assert_eq!(result.triangles.len(), 3);
+    }
+
+    #[test]
+    fn merge_points_clusters() {
+        // Exact merging only joins identical positions, signed zeros too.
+        let points = [pt(0.0, 0.0), pt(1.0, 0.0), pt(-0.0, 0.0), pt(1.0, 1e-12)];
+        for epsilon in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let (vertices, vertex_of) = merge_points(&points, epsilon);
+            assert_eq!(vertices.len(), 3);
+            assert_eq!(vertex_of, [0, 1, 0, 2]);
+        }
+
+        // A point joins the first vertex within epsilon; vertices stay at
+        // the first point of their cluster, so a chain does not collapse.
+        let points = [
+            pt(0.0, 0.0),
+            pt(0.4, 0.0),
+            pt(0.8, 0.0),
+            pt(1.1, 0.0),
+            pt(0.3, 0.3),
+            pt(10.0, 10.0),
+            pt(10.0, 10.49),
+        ];
+        let (vertices, vertex_of) = merge_points(&points, 0.5);
+        assert_eq!(vertex_of, [0, 0, 1, 1, 0, 2, 2]);
+        assert_eq!(vertices, [points[0], points[2], points[5]]);
+
+        // Against brute force: every point is within epsilon of its vertex,
+        // and its vertex is the first one within epsilon.
+        let mut s = 5u32;
+        let points: Vec<Pt> = (0..2000)
+            .map(|_| {
+                let x = (crate::rand::frand01(&mut s) * 40.0).round() as f64 / 4.0;
+                pt(
+                    x,
+                    (crate::rand::frand01(&mut s) * 40.0).round() as f64 / 4.0,
+                )
+            })
+            .collect();
+        let epsilon = 0.6;
+        let (vertices, vertex_of) = merge_points(&points, epsilon);
+        for (p, &v) in points.iter().zip(&vertex_of) {
+            let first = vertices
+                .iter()
+                .position(|q| q.sub(*p).len2() <= epsilon * epsilon);
+            assert_eq!(first, Some(v), "{p:?}");
+        }
+        for (a, b) in vertices.iter().zip(vertices.iter().skip(1)) {
+            assert_ne!(a, b);
+        }
+    }
+
+    #[test]
+    fn delaunay_reports_the_merge() {
+        let points = flat(&[
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (0.1, 0.0),
+            (0.0, 10.0),
+            (10.0, 0.05),
+        ]);
+        let result = VoronoiTests::delaunay(&points, 0.2).unwrap();
+        assert_eq!(result.vertex_of, [0, 1, 0, 2, 1]);
+        assert_eq!(result.vertices, [0.0, 0.0, 10.0, 0.0, 0.0, 10.0]);
+        assert!(!result.collinear && result.triangles.len() == 3);
+
+        // Merging can leave a line.
+        let points = flat(&[(0.0, 0.0), (5.0, 0.0), (10.0, 0.0), (5.0, 0.1)]);
+        let result = VoronoiTests::delaunay(&points, 0.5).unwrap();
+        assert!(result.collinear && result.triangles.is_empty());
+        assert_eq!(result.line, [0, 1, 2]);
+    }
+
+    #[test]
+    fn indices_and_edges_follow_delaunay() {
+        let points = flat(&[
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (0.0, 0.0),
+            (0.0, 10.0),
+            (10.0, 10.0),
+            (9.9, 10.0),
+        ]);
+        for epsilon in [0.0, 0.5] {
+            let full = VoronoiTests::delaunay(&points, epsilon).unwrap();
+            let indices = VoronoiTests::delaunay_indices(&points, epsilon).unwrap();
+            let edges = VoronoiTests::voronoi_edges(&points, epsilon).unwrap();
+            for part in [&indices, &edges] {
+                assert_eq!(part.vertices, full.vertices);
+                assert_eq!(part.vertex_of, full.vertex_of);
+            }
+            assert_eq!(indices.triangles, full.triangles);
+            assert!(indices.voronoi_edges.is_empty());
+            assert_eq!(edges.voronoi_edges, full.voronoi_edges);
+            assert!(edges.triangles.is_empty());
+        }
+        let merged = VoronoiTests::delaunay_indices(&points, 0.5).unwrap();
+        assert_eq!(merged.vertex_of, [0, 1, 0, 2, 3, 3]);
+
+        // Collinear points and too few distinct ones give a line.
+        let line = flat(&[(0.0, 0.0), (2.0, 1.0), (4.0, 2.0), (-2.0, -1.0)]);
+        let result = VoronoiTests::voronoi_edges(&line, 0.0).unwrap();
+        assert!(result.collinear && result.voronoi_edges.is_empty());
+        assert_eq!(result.line, [3, 0, 1, 2]);
+        let two = flat(&[(0.0, 0.0), (1.0, 0.0), (0.0, 0.0), (1.0, 0.0)]);
+        let result = VoronoiTests::delaunay_indices(&two, 0.0).unwrap();
+        assert!(result.collinear && result.triangles.is_empty());
+        assert_eq!(result.vertex_of, [0, 1, 0, 1]);
+        assert_eq!(result.line.len(), 2);
+    }
+}
@@ -0,0 +1,400 @@
+//! A Delaunay triangulation that lives across calls.
+//!
+//! The stateless functions on [`VoronoiTests`] triangulate their input from
+//! scratch every time. [`DelaunayTriangulation`] keeps the topology instead,
+//! so inserting or removing a single point only repairs the triangles around
+//! it, and indices, Voronoi edges and neighbours are all read from the same
+//! structure. Moving all points a little, as the animated demos do every
+//! frame, is handled by edge flips around each moved point.
+//!
+//! Point ids are stable: a removed id is never reused, and inserting a point
+//! that coincides with an existing one returns the existing id.
+
+use super::triangulation::{GHOST, Insertion, NONE, Triangulation, hilbert_order};
+use super::{Pt, read_points, triangle_indices, voronoi_edges_flat};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct DelaunayTriangulation {
+    pub(crate) tri: Triangulation,
+    pub(crate) removed: Vec<bool>,
+    /// Live points that coincide with a vertex and are therefore not
+    /// vertices themselves. They take over when that vertex is removed.
+    aliases: Vec<usize>,
+}
+
+impl DelaunayTriangulation {
+    pub(crate) fn from_points(points: Vec<Pt>) -> Self {
+        let mut this = Self {
+            removed: vec![false; points.len()],
+            tri: Triangulation::with_filter(points, |_| true),
+            aliases: Vec::new(),
+        };
+        this.collect_aliases();
+        this
+    }
+
+    pub(crate) fn triangulation(&self) -> &Triangulation {
+        &self.tri
+    }
+
+    /// Triangulate all live points from scratch. Only needed while the points
+    /// do not span a triangle, where there is no topology to repair.
+    fn rebuild(&mut self) {
+        let points = std::mem::take(&mut self.tri.points);
+        let removed = &self.removed;
+        self.tri = Triangulation::with_filter(points, |i| !removed[i]);
+        self.collect_aliases();
+    }
+
+    /// Live points that are not vertices. Points at a non-finite position
+    /// are not vertices either, but never take over from one.
+    fn collect_aliases(&mut self) {
+        self.aliases.clear();
+        if !self.tri.has_triangles() {
+            return;
+        }
+        let tri = &self.tri;
+        let finite = |i: usize| tri.points[i].x.is_finite() && tri.points[i].y.is_finite();
+        self.aliases.extend(
+            (0..tri.points.len()).filter(|&i| !self.removed[i] && !tri.is_vertex(i) && finite(i)),
+        );
+    }
+
+    fn is_live(&self, id: usize) -> bool {
+        id < self.removed.len() && !self.removed[id]
+    }
+
+    /// Insert `p` and return its id, or [`NONE`] without inserting anything
+    /// if `p` is not finite.
+    pub(crate) fn insert_point(&mut self, p: Pt) -> usize {
+        if !p.x.is_finite() || !p.y.is_finite() {
+            return NONE;
+        }
+        let id = self.tri.push_point(p);
+        self.removed.push(false);
+        match self.tri.insert(id) {
+            Insertion::Inserted => id,
+            Insertion::Duplicate(v) => {
+                self.tri.pop_point();
+                self.removed.pop();
+                v
+            }
+            Insertion::Deferred => {
+                let existing = (0..id).find(|&i| !self.removed[i] && self.tri.points[i] == p);
+                if let Some(v) = existing {
+                    self.tri.pop_point();
+                    self.removed.pop();
+                    return v;
+                }
+                self.rebuild();
+                id
+            }
+            Insertion::Hidden => unreachable!("hidden point without weights"),
+        }
+    }
+
+    pub(crate) fn remove_point(&mut self, id: usize) -> bool {
+        if !self.is_live(id) {
+            return false;
+        }
+        self.removed[id] = true;
+        if !self.tri.is_vertex(id) {
+            self.aliases.retain(|&a| a != id);
+            return true;
+        }
+        self.tri.remove(id);
+        if !self.tri.has_triangles() {
+            self.aliases.clear();
+            return true;
+        }
+        let p = self.tri.points[id];
+        if let Some(k) = self.aliases.iter().position(|&a| self.tri.points[a] == p) {
+            let alias = self.aliases.swap_remove(k);
+            self.tri.insert(alias);
+        }
+        true
+    }
+}
+
+#[wasm_bindgen]
+impl DelaunayTriangulation {
+    /// Triangulate the initial points. Input layout: [x,y,vx,vy,...]; the
+    /// point ids are their positions in the input.
+    #[wasm_bindgen(constructor)]
+    pub fn new(points_flat: &[f32]) -> DelaunayTriangulation {
+        Self::from_points(read_points(points_flat))
+    }
+
+    /// Insert a point and return its id. If a live point already sits at
+    /// `(x, y)`, nothing changes and that point's id is returned. A
+    /// non-finite point is rejected with `u32::MAX`.
+    pub fn insert(&mut self, x: f32, y: f32) -> u32 {
+        let p = Pt {
+            x: x as f64,
+            y: y as f64,
+        };
+        match self.insert_point(p) {
+            NONE => u32::MAX,
+            id => id as u32,
+        }
+    }
+
+    /// Remove the point with the given id. Returns `false` if there is no
+    /// such live point.
+    pub fn remove(&mut self, id: u32) -> bool {
+        self.remove_point(id as usize)
+    }
+
+    /// Move the points to new positions, keeping the triangulation from the
+    /// previous call. Point `i` of the input (layout [x,y,vx,vy,...]) moves
+    /// id `i`; removed ids and ids beyond the input are left alone.
+    ///
+    /// Points that moved a little are fixed up with edge flips around them,
+    /// points whose move would tangle their triangles (large jumps, respawns)
+    /// are removed and inserted again. Points moved to a non-finite position
+    /// leave the triangulation but keep their id, and are inserted again
+    /// once their position is finite. Returns the number of points that
+    /// could not be moved in place.
+    pub fn update_positions(&mut self, points_flat: &[f32]) -> u32 {
+        let points = read_points(points_flat);
+        let mut reinserted = 0;
+        for id in hilbert_order(&points) {
+            if self.is_live(id) && !self.tri.move_vertex(id, points[id]) {
+                reinserted += 1;
+            }
+        }
+        if !self.tri.has_triangles() {
+            self.rebuild();
+            return reinserted;
+        }
+        // A duplicate becomes a vertex again once the point it coincided
+        // with has moved away, possibly later in this same pass.
+        if reinserted > 0 || !self.aliases.is_empty() {
+            self.collect_aliases();
+            for &a in &self.aliases {
+                self.tri.insert(a);
+            }
+            let tri = &self.tri;
+            self.aliases.retain(|&a| !tri.is_vertex(a));
+        }
+        reinserted
+    }
+
+    /// Number of ids handed out so far, including removed ones.
+    pub fn id_count(&self) -> u32 {
+        self.removed.len() as u32
+    }
+
+    /// Whether `id` refers to a point that has not been removed.
+    pub fn contains(&self, id: u32) -> bool {
+        self.is_live(id as usize)
+    }
+
+    /// Positions of all ids as [x,y,...]; removed ids yield NaN.
+    pub fn positions(&self) -> Vec<f32> {
+        let mut out = Vec::with_capacity(self.removed.len() * 2);
+        for (p, &removed) in self.tri.points.iter().zip(&self.removed) {
+            if removed {
+                out.push(f32::NAN);
+                out.push(f32::NAN);
+            } else {
+                out.push(p.x as f32);
+                out.push(p.y as f32);
+            }
+        }
+        out
+    }
+
+    /// Delaunay triangles as id triplets, counter-clockwise.
+    pub fn delaunay_indices(&self) -> Vec<u32> {
+        triangle_indices(&self.tri)
+    }
+
+    /// Voronoi edges as line segments [x1,y1,x2,y2,...]
+    pub fn voronoi_edges(&self) -> Vec<f32> {
+        voronoi_edges_flat(&self.tri)
+    }
+
+    /// Ids of the Delaunay neighbours of `id` in counter-clockwise order.
+    /// Empty for removed ids and while the points do not span a triangle.
+    pub fn neighbors(&self, id: u32) -> Vec<u32> {
+        let id = id as usize;
+        if !self.is_live(id) {
+            return Vec::new();
+        }
+        self.tri
+            .neighbors(id)
+            .filter(|&j| j != GHOST)
+            .map(|j| j as u32)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn random_point(s: &mut u32) -> Pt {
+        Pt {
+            x: crate::rand::frand01(s) as f64 * 100.0,
+            y: crate::rand::frand01(s) as f64 * 100.0,
+        }
+    }
+
+    /// Triangles as id triplets rotated to start at the smallest id.
+    fn triangle_set(indices: &[u32]) -> BTreeSet<[u32; 3]> {
+        indices
+            .chunks(3)
+            .map(|t| {
+                let k = (0..3).min_by_key(|&k| t[k]).unwrap();
+                [t[k], t[(k + 1) % 3], t[(k + 2) % 3]]
+            })
+            .collect()
+    }
+
+    /// The triangles match a fresh triangulation of the live points.
+    fn assert_matches_rebuild(dt: &DelaunayTriangulation) {
+        let removed = &dt.removed;
+        let fresh = Triangulation::with_filter(dt.tri.points.clone(), |i| !removed[i]);
+        assert_eq!(
+            triangle_set(&dt.delaunay_indices()),
+            triangle_set(&triangle_indices(&fresh))
+        );
+    }
+
+    #[test]
+    fn insert_and_remove_match_a_rebuild() {
+        let mut s = 21u32;
+        let initial: Vec<Pt> = (0..50).map(|_| random_point(&mut s)).collect();
+        let mut dt = DelaunayTriangulation::from_points(initial);
+        assert_matches_rebuild(&dt);
+
+        for round in 0..400 {
+            let r = crate::rand::frand01(&mut s);
+            if r < 0.5 {
+                let id = dt.insert_point(random_point(&mut s));
+                assert_eq!(id, dt.removed.len() - 1);
+            } else {
+                let live: Vec<usize> = (0..dt.removed.len()).filter(|&i| dt.is_live(i)).collect();
+                let id = live[(r * 1000.0) as usize % live.len()];
+                assert!(dt.remove_point(id));
+                assert!(!dt.remove_point(id), "removed twice");
+            }
+            if round % 10 == 0 {
+                assert_matches_rebuild(&dt);
+            }
+        }
+        assert_matches_rebuild(&dt);
+    }
+
+    #[test]
+    fn coinciding_points_share_an_id() {
+        let mut s = 4u32;
+        let initial: Vec<Pt> = (0..20).map(|_| random_point(&mut s)).collect();
+        let p = initial[7];
+        let mut dt = DelaunayTriangulation::from_points(initial);
+        assert_eq!(dt.insert_point(p), 7);
+        assert_eq!(dt.id_count(), 20);
+
+        // Removing down to two points leaves no triangles; inserting again
+        // brings them back.
+        for id in 2..20 {
+            assert!(dt.remove_point(id));
+        }
+        assert!(dt.delaunay_indices().is_empty());
+        let id = dt.insert_point(Pt { x: 50.0, y: 150.0 });
+        assert_eq!(id, 20);
+        assert_eq!(dt.delaunay_indices().len(), 3);
+        assert_matches_rebuild(&dt);
+    }
+
+    #[test]
+    fn non_finite_points_are_rejected() {
+        let mut s = 9u32;
+        let initial: Vec<Pt> = (0..50).map(|_| random_point(&mut s)).collect();
+        let mut dt = DelaunayTriangulation::from_points(initial);
+        let before = dt.delaunay_indices();
+        for (x, y) in [
+            (f32::NAN, 5.0),
+            (5.0, f32::INFINITY),
+            (f32::NEG_INFINITY, 0.0),
+        ] {
+            assert_eq!(dt.insert(x, y), u32::MAX);
+        }
+        assert_eq!(dt.id_count(), 50);
+        assert!(!dt.contains(u32::MAX));
+        assert_eq!(dt.delaunay_indices(), before);
+        assert_eq!(dt.insert(50.0, 50.0), 50);
+
+        // Also while the points do not span a triangle yet.
+        let mut line = DelaunayTriangulation::from_points(vec![Pt { x: 0.0, y: 0.0 }]);
+        assert_eq!(line.insert(f32::NAN, f32::NAN), u32::MAX);
+        assert_eq!(line.id_count(), 1);
+    }
+
+    #[test]
+    fn kinetic_updates_stay_delaunay() {
+        use crate::voronoi::VoronoiTests;
+        for seed in 1..6u32 {
+            // Fast points leave the 400 x 300 box often and respawn.
+            let mut flat = VoronoiTests::voronoi_create_points(200, 400.0, 300.0, seed, 150.0);
+            let snap = seed % 2 == 0;
+            let mut dt = DelaunayTriangulation::new(&flat);
+            let mut reinserted = 0;
+            for frame in 0..60 {
+                flat = VoronoiTests::voronoi_step_points(&flat, 400.0, 300.0, 0.05);
+                if snap {
+                    // A coarse grid makes cocircular points and duplicates.
+                    for v in flat.chunks_mut(4) {
+                        v[0] = (v[0] / 10.0).round() * 10.0;
+                        v[1] = (v[1] / 10.0).round() * 10.0;
+                    }
+                }
+                reinserted += dt.update_positions(&flat);
+                let report = dt.validate();
+                assert!(report.valid, "seed {seed} frame {frame}: {report:?}");
+                let fresh = Triangulation::new(&read_points(&flat));
+                assert_eq!(
+                    dt.tri.triangles().len(),
+                    fresh.triangles().len(),
+                    "seed {seed} frame {frame}"
+                );
+            }
+            assert!(reinserted > 0, "no respawns for seed {seed}");
+        }
+    }
+
+    #[test]
+    fn points_moved_to_nan_leave_and_come_back() {
+        let mut s = 13u32;
+        let mut flat: Vec<f32> = (0..60)
+            .flat_map(|_| {
+                let p = random_point(&mut s);
+                [p.x as f32, p.y as f32, 0.0, 0.0]
+            })
+            .collect();
+        let mut dt = DelaunayTriangulation::new(&flat);
+        let home = [flat[4 * 7], flat[4 * 7 + 1]];
+        let uses = |dt: &DelaunayTriangulation, id: u32| dt.delaunay_indices().contains(&id);
+
+        flat[4 * 7] = f32::NAN;
+        flat[4 * 9 + 1] = f32::INFINITY;
+        assert_eq!(dt.update_positions(&flat), 2);
+        assert!(dt.contains(7) && dt.contains(9));
+        assert!(!uses(&dt, 7) && !uses(&dt, 9));
+        assert!(dt.neighbors(7).is_empty());
+        assert!(dt.validate().valid);
+        assert_matches_rebuild(&dt);
+
+        // Staying non-finite changes nothing; coming back re-inserts.
+        assert_eq!(dt.update_positions(&flat), 0);
+        flat[4 * 7] = home[0];
+        assert_eq!(dt.update_positions(&flat), 1);
+        assert!(uses(&dt, 7) && !uses(&dt, 9));
+        assert_eq!(dt.positions()[14..16], home);
+        assert!(dt.validate().valid);
+        assert_matches_rebuild(&dt);
+    }
+}
@@ -0,0 +1,409 @@
+//! Point motion for the animated demos.
+//!
+//! Every step first updates the velocities according to a [`PointMotion`],
+//! then moves the points and finally applies a [`PointBoundary`] to those
+//! that left the viewport. All randomness is hashed from the seed, the step
+//! number and the point index (for respawns also the point's state), so a
+//! run can be replayed exactly.
+//!
+//! As in [`VoronoiTests::voronoi_create_points`], the last four points of a
+//! buffer are the viewport corners and never move, unless
+//! [`PointDynamics::fixed_corners`] is off for buffers from the samplers,
+//! which have no corners.
+
+use super::VoronoiTests;
+use crate::rand::{frand01, hash_u32};
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+/// How the points move inside the viewport.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointMotion {
+    /// Constant velocity.
+    Drift = 0,
+    /// Drift plus a random walk with diffusion `strength`.
+    Brownian = 1,
+    /// Drift plus a whirl around the viewport centre that turns at up to
+    /// `strength` px/s, strongest at distance `scale`.
+    Vortex = 2,
+    /// Drift plus advection by a divergence-free noise field with features
+    /// of size `scale` and speeds around `strength` px/s.
+    CurlNoise = 3,
+    /// Sites push each other apart within distance `scale`, accelerating
+    /// by up to `strength` px/s²; speeds are capped at `max_speed`.
+    Repulsion = 4,
+}
+
+/// What happens to points that leave the viewport.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointBoundary {
+    /// Reappear at a random position with a random velocity between
+    /// `min_speed` and `max_speed`.
+    Respawn = 0,
+    /// Reflect off the viewport edges.
+    Bounce = 1,
+    /// Come back in on the opposite side.
+    Wrap = 2,
+}
+
+/// Settings for [`VoronoiTests::voronoi_step_points_with`].
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct PointDynamics {
+    pub motion: PointMotion,
+    pub boundary: PointBoundary,
+    pub seed: u32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+    pub strength: f32,
+    pub scale: f32,
+    /// Whether the last four points are the fixed viewport corners.
+    pub fixed_corners: bool,
+}
+
+impl Default for PointDynamics {
+    /// Drift and respawn at 10–50 px/s, the behaviour of
+    /// [`VoronoiTests::voronoi_step_points`].
+    fn default() -> Self {
+        Self {
+            motion: PointMotion::Drift,
+            boundary: PointBoundary::Respawn,
+            seed: 0,
+            min_speed: 10.0,
+            max_speed: 50.0,
+            strength: 40.0,
+            scale: 120.0,
+            fixed_corners: true,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl PointDynamics {
+    #[wasm_bindgen(constructor)]
+    pub fn new(motion: PointMotion, boundary: PointBoundary, seed: u32) -> PointDynamics {
+        PointDynamics {
+            motion,
+            boundary,
+            seed,
+            ..Default::default()
+        }
+    }
+}
+
+/// Random state for point `i` in step `step`; never zero, which would be a
+/// fixed point of the xorshift.
+fn point_state(seed: u32, step: u32, i: usize) -> u32 {
+    hash_u32(seed ^ step.wrapping_mul(0x9E37_79B9) ^ (i as u32).wrapping_mul(0x85EB_CA6B)) | 1
+}
+
+/// Standard normal pair by the Box–Muller transform.
+fn gaussian2(state: &mut u32) -> (f32, f32) {
+    let u = frand01(state).max(f32::MIN_POSITIVE);
+    let v = frand01(state);
+    let r = (-2.0 * u.ln()).sqrt();
+    let a = v * std::f32::consts::TAU;
+    (r * a.cos(), r * a.sin())
+}
+
+/// Smooth value noise in [-1, 1] with unit feature size.
+fn value_noise(seed: u32, x: f32, y: f32) -> f32 {
+    let lattice = |ix: i32, iy: i32| {
+        let mut s = hash_u32(
+            seed ^ (ix as u32).wrapping_mul(0x9E37_79B9) ^ (iy as u32).wrapping_mul(0x85EB_CA6B),
+        ) | 1;
+        frand01(&mut s) * 2.0 - 1.0
+    };
+    // Quintic fade, so the gradient (and with it the curl) is continuous.
+    let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    let (fx, fy) = (x.floor(), y.floor());
+    let (ix, iy) = (fx as i32, fy as i32);
+    let (u, v) = (fade(x - fx), fade(y - fy));
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    lerp(
+        lerp(lattice(ix, iy), lattice(ix + 1, iy), u),
+        lerp(lattice(ix, iy + 1), lattice(ix + 1, iy + 1), u),
+        v,
+    )
+}
+
+/// Velocity of the curl of a noise stream function at `(x, y)`.
+fn curl_noise(d: &PointDynamics, x: f32, y: f32) -> (f32, f32) {
+    let scale = d.scale.max(1e-3);
+    let (x, y) = (x / scale, y / scale);
+    let h = 1e-2;
+    let psi = |x: f32, y: f32| value_noise(d.seed, x, y);
+    let dx = (psi(x + h, y) - psi(x - h, y)) / (2.0 * h);
+    let dy = (psi(x, y + h) - psi(x, y - h)) / (2.0 * h);
+    (d.strength * dy, -d.strength * dx)
+}
+
+/// Velocity change of every point from the mutual repulsion, found with a
+/// uniform grid of cell size `scale`.
+fn repulsion(d: &PointDynamics, points: &[f32], moving: usize, dt: f32) -> Vec<(f32, f32)> {
+    let radius = d.scale.max(1e-3);
+    let n = points.len() / 4;
+    let cell = |x: f32, y: f32| ((x / radius).floor() as i32, (y / radius).floor() as i32);
+    let mut grid: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+    for j in 0..n {
+        grid.entry(cell(points[4 * j], points[4 * j + 1]))
+            .or_default()
+            .push(j);
+    }
+    (0..moving)
+        .map(|i| {
+            let (x, y) = (points[4 * i], points[4 * i + 1]);
+            let (cx, cy) = cell(x, y);
+            let (mut ax, mut ay) = (0.0, 0.0);
+            for gy in cy - 1..=cy + 1 {
+                for gx in cx - 1..=cx + 1 {
+                    for &j in grid.get(&(gx, gy)).into_iter().flatten() {
+                        let (dx, dy) = (x - points[4 * j], y - points[4 * j + 1]);
+                        let dist = (dx * dx + dy * dy).sqrt();
+                        if j == i || dist <= 0.0 || dist >= radius {
+                            continue;
+                        }
+                        let push = d.strength * (1.0 - dist / radius) / dist;
+                        ax += dx * push;
+                        ay += dy * push;
+                    }
+                }
+            }
+            (ax * dt, ay * dt)
+        })
+        .collect()
+}
+
+/// Advance the points of a `[x,y,vx,vy,...]` buffer by `dt`.
+pub(crate) fn step_points(
+    points: &[f32],
+    width: f32,
+    height: f32,
+    dt: f32,
+    d: &PointDynamics,
+    step: u32,
+) -> Vec<f32> {
+    let mut out = points.to_vec();
+    let n = points.len() / 4;
+    // Treat the last 4 points (if present) as fixed corners.
+    let fixed_corners = d.fixed_corners && n >= 4;
+    let moving_n = if fixed_corners { n - 4 } else { n };
+
+    let pushes = if d.motion == PointMotion::Repulsion {
+        repulsion(d, points, moving_n, dt)
+    } else {
+        Vec::new()
+    };
+    let (cx, cy) = (width * 0.5, height * 0.5);
+    for i in 0..moving_n {
+        let ix = i * 4;
+        let [mut x, mut y, mut vx, mut vy] =
+            [points[ix], points[ix + 1], points[ix + 2], points[ix + 3]];
+
+        // Velocity of the surrounding flow, on top of the point's own.
+        let (mut fx, mut fy) = (0.0, 0.0);
+        match d.motion {
+            PointMotion::Drift => {}
+            PointMotion::Brownian => {
+                let mut s = point_state(d.seed, step, i);
+                let (gx, gy) = gaussian2(&mut s);
+                // A displacement of sqrt(2 D dt) per step, as a velocity.
+                let sigma = (2.0 * d.strength.max(0.0) / dt.max(1e-6)).sqrt();
+                (fx, fy) = (gx * sigma, gy * sigma);
+            }
+            PointMotion::Vortex => {
+                let scale = d.scale.max(1e-3);
+                let (rx, ry) = ((x - cx) / scale, (y - cy) / scale);
+                let swirl = 2.0 * d.strength / (1.0 + rx * rx + ry * ry);
+                (fx, fy) = (-ry * swirl, rx * swirl);
+            }
+            PointMotion::CurlNoise => (fx, fy) = curl_noise(d, x, y),
+            PointMotion::Repulsion => {
+                let (ax, ay) = pushes.get(i).copied().unwrap_or_default();
+                vx += ax;
+                vy += ay;
+                let speed = (vx * vx + vy * vy).sqrt();
+                if speed > d.max_speed && speed > 0.0 {
+                    vx *= d.max_speed / speed;
+                    vy *= d.max_speed / speed;
+                }
+            }
+        }
+        x += (vx + fx) * dt;
+        y += (vy + fy) * dt;
+
+        let out_of_bounds = x < 0.0 || x > width || y < 0.0 || y > height;
+        if out_of_bounds {
+            match d.boundary {
+                PointBoundary::Respawn => {
+                    // Deterministic respawn based on previous state
+                    let state = x.to_bits() ^ y.to_bits() ^ vx.to_bits() ^ vy.to_bits();
+                    let mut s = point_state(d.seed ^ state, step, i);
+                    x = frand01(&mut s) * width;
+                    y = frand01(&mut s) * height;
+                    let ang = frand01(&mut s) * std::f32::consts::TAU;
+                    let spd = d.min_speed + (d.max_speed - d.min_speed) * frand01(&mut s);
+                    vx = ang.cos() * spd;
+                    vy = ang.sin() * spd;
+                }
+                PointBoundary::Bounce => {
+                    if x < 0.0 {
+                        x = -x;
+                        vx = vx.abs();
+                    } else if x > width {
+                        x = 2.0 * width - x;
+                        vx = -vx.abs();
+                    }
+                    if y < 0.0 {
+                        y = -y;
+                        vy = vy.abs();
+                    } else if y > height {
+                        y = 2.0 * height - y;
+                        vy = -vy.abs();
+                    }
+                    // A step longer than the viewport still ends inside.
+                    x = x.clamp(0.0, width.max(0.0));
+                    y = y.clamp(0.0, height.max(0.0));
+                }
+                PointBoundary::Wrap => {
+                    if width > 0.0 {
+                        x = x.rem_euclid(width);
+                    }
+                    if height > 0.0 {
+                        y = y.rem_euclid(height);
+                    }
+                }
+            }
+        }
+        out[ix] = x;
+        out[ix + 1] = y;
+        out[ix + 2] = vx;
+        out[ix + 3] = vy;
+    }
+
+    // Pin the last four as fixed corners with zero velocity, and update
+    // their positions to match the current canvas size (handles resizes).
+    if fixed_corners {
+        let base = moving_n * 4;
+        let corners = [
+            (0.0f32, 0.0f32),
+            (width, 0.0f32),
+            (0.0f32, height),
+            (width, height),
+        ];
+        for (k, &(x, y)) in corners.iter().enumerate() {
+            let ix = base + k * 4;
+            if ix + 3 < out.len() {
+                out[ix] = x;
+                out[ix + 1] = y;
+                out[ix + 2] = 0.0;
+                out[ix + 3] = 0.0;
+            }
+        }
+    }
+    out
+}
+
+#[wasm_bindgen]
+impl VoronoiTests {
+    /// Like [`VoronoiTests::voronoi_step_points`], with the motion and
+    /// boundary behaviour taken from `dynamics`. `step` numbers the calls
+    /// and seeds the per-step randomness; pass a counter to replay a run.
+    pub fn voronoi_step_points_with(
+        points: &[f32],
+        width: f32,
+        height: f32,
+        dt: f32,
+        dynamics: &PointDynamics,
+        step: u32,
+    ) -> Vec<f32> {
+        step_points(points, width, height, dt, dynamics, step)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOTIONS: [PointMotion; 5] = [
+        PointMotion::Drift,
+        PointMotion::Brownian,
+        PointMotion::Vortex,
+        PointMotion::CurlNoise,
+        PointMotion::Repulsion,
+    ];
+    const BOUNDARIES: [PointBoundary; 3] = [
+        PointBoundary::Respawn,
+        PointBoundary::Bounce,
+        PointBoundary::Wrap,
+    ];
+
+    fn run(d: &PointDynamics, steps: u32) -> Vec<f32> {
+        // Fast points, so that many of them leave the viewport.
+        let mut points = VoronoiTests::voronoi_create_points(200, 300.0, 200.0, 7, 150.0);
+        for step in 0..steps {
+            points = step_points(&points, 300.0, 200.0, 0.05, d, step);
+        }
+        points
+    }
+
+    #[test]
+    fn runs_replay_exactly() {
+        for motion in MOTIONS {
+            for boundary in BOUNDARIES {
+                let d = PointDynamics::new(motion, boundary, 5);
+                let a = run(&d, 100);
+                assert_eq!(a, run(&d, 100), "{motion:?} {boundary:?}");
+                let other = PointDynamics::new(motion, boundary, 6);
+                let depends_on_seed =
+                    matches!(motion, PointMotion::Brownian | PointMotion::CurlNoise)
+                        || boundary == PointBoundary::Respawn;
+                if depends_on_seed {
+                    assert_ne!(a, run(&other, 100), "{motion:?} {boundary:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn points_stay_in_bounds() {
+        for motion in MOTIONS {
+            for boundary in BOUNDARIES {
+                let d = PointDynamics::new(motion, boundary, 3);
+                let mut points = VoronoiTests::voronoi_create_points(200, 300.0, 200.0, 7, 150.0);
+                for step in 0..200 {
+                    points = step_points(&points, 300.0, 200.0, 0.05, &d, step);
+                    for (i, p) in points.chunks(4).enumerate() {
+                        let inside = (0.0..=300.0).contains(&p[0]) && (0.0..=200.0).contains(&p[1]);
+                        assert!(
+                            inside && p.iter().all(|v| v.is_finite()),
+                            "{motion:?} {boundary:?} step {step} point {i}: {p:?}"
+                        );
+                    }
+                }
+                // The corners stay put.
+                let n = points.len();
+                assert_eq!(points[n - 4..], [300.0, 200.0, 0.0, 0.0]);
+            }
+        }
+    }
+
+    #[test]
+    fn respawns_are_spread_out() {
+        // The same point leaving at the same spot in different steps, or
+        // different points leaving at the same spot, land in different places.
+        let d = PointDynamics::default();
+        let leaving = [301.0, 100.0, 40.0, 0.0];
+        let a = step_points(&leaving, 300.0, 200.0, 0.1, &d, 0);
+        let b = step_points(&leaving, 300.0, 200.0, 0.1, &d, 1);
+        let two = step_points(&[leaving, leaving].concat(), 300.0, 200.0, 0.1, &d, 0);
+        assert_ne!(a[..2], b[..2]);
+        assert_ne!(two[..2], two[4..6]);
+        for p in [&a[..], &b[..], &two[..]] {
+            let speed = p[2].hypot(p[3]);
+            assert!((d.min_speed..=d.max_speed).contains(&speed), "{speed}");
+        }
+    }
+}
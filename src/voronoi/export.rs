@@ -0,0 +1,415 @@
+//! Text serialisers for triangulations, Voronoi edges and cells.
+//!
+//! - SVG: one group per call, styled by [`SvgStyle`], with the view box
+//!   fitted to the drawing. Coordinates are written as they are, so the y
+//!   axis points down like on the canvas.
+//! - GeoJSON: a `FeatureCollection` with one `Polygon` feature per triangle
+//!   or non-empty cell; the `index` property is the triangle or site index.
+//! - Wavefront OBJ: the triangle mesh at `z = 0`, with the vertices that no
+//!   triangle references left out.
+//!
+//! Point buffers are read with a stride, so both the `[x,y,vx,vy,...]`
+//! layout of the demos (stride 4, as passed to
+//! [`VoronoiTests::delaunay_indices`]) and plain `[x,y,...]` coordinates
+//! (stride 2) work. The free functions are the same serialisers for native
+//! code, re-exported from the crate root as `export`.
+
+use super::cells::VoronoiCells;
+use super::delaunay::DelaunayTriangulation;
+use super::{Pt, VoronoiTests, read_xy, triangle_indices};
+use std::fmt::Write;
+use wasm_bindgen::prelude::*;
+
+/// Stroke and fill of the shapes in an SVG export. An empty colour or
+/// `"none"` disables stroke or fill.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct SvgStyle {
+    pub stroke: String,
+    pub stroke_width: f32,
+    pub fill: String,
+}
+
+#[wasm_bindgen]
+impl SvgStyle {
+    #[wasm_bindgen(constructor)]
+    pub fn new(stroke: String, stroke_width: f32, fill: String) -> SvgStyle {
+        SvgStyle {
+            stroke,
+            stroke_width,
+            fill,
+        }
+    }
+}
+
+/// `value` with the characters that would end an XML attribute escaped.
+fn escape_attr(value: &str) -> String {
+    let value = if value.is_empty() { "none" } else { value };
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('"', "&quot;")
+}
+
+/// Positions from a buffer with `stride` floats per point, x and y first;
+/// strides below 2 count as 2.
+fn read_strided(flat: &[f32], stride: usize) -> Vec<Pt> {
+    flat.chunks_exact(stride.max(2))
+        .map(|c| Pt {
+            x: c[0] as f64,
+            y: c[1] as f64,
+        })
+        .collect()
+}
+
+fn is_finite(p: Pt) -> bool {
+    p.x.is_finite() && p.y.is_finite()
+}
+
+/// Corners of the triangles in `indices`, skipping out-of-range ones.
+fn triangle_polygons(points: &[Pt], indices: &[u32]) -> Vec<Vec<Pt>> {
+    indices
+        .chunks_exact(3)
+        .map(|t| {
+            t.iter()
+                .filter_map(|&i| points.get(i as usize).copied())
+                .collect::<Vec<_>>()
+        })
+        .map(|t| if t.len() == 3 { t } else { Vec::new() })
+        .collect()
+}
+
+/// SVG document of closed polygons and open line segments in one style.
+pub(crate) fn svg(polygons: &[Vec<Pt>], segments: &[(Pt, Pt)], style: &SvgStyle) -> String {
+    let all = polygons
+        .iter()
+        .flatten()
+        .copied()
+        .chain(segments.iter().flat_map(|&(a, b)| [a, b]))
+        .filter(|&p| is_finite(p));
+    let (mut min, mut max) = (Pt { x: 0.0, y: 0.0 }, Pt { x: 0.0, y: 0.0 });
+    for (k, p) in all.enumerate() {
+        if k == 0 {
+            (min, max) = (p, p);
+        }
+        min = Pt {
+            x: min.x.min(p.x),
+            y: min.y.min(p.y),
+        };
+        max = Pt {
+            x: max.x.max(p.x),
+            y: max.y.max(p.y),
+        };
+    }
+    let pad = (style.stroke_width as f64).max(0.0);
+    let (x, y) = ((min.x - pad) as f32, (min.y - pad) as f32);
+    let (w, h) = (
+        (max.x - min.x + 2.0 * pad) as f32,
+        (max.y - min.y + 2.0 * pad) as f32,
+    );
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{x} {y} {w} {h}" width="{w}" height="{h}">"#
+    );
+    let _ = writeln!(
+        out,
+        r#"<g stroke="{}" stroke-width="{}" fill="{}" stroke-linejoin="round">"#,
+        escape_attr(&style.stroke),
+        style.stroke_width,
+        escape_attr(&style.fill),
+    );
+    for poly in polygons {
+        if poly.len() < 2 || !poly.iter().all(|&p| is_finite(p)) {
+            continue;
+        }
+        out.push_str(r#"<path d=""#);
+        for (k, p) in poly.iter().enumerate() {
+            let cmd = if k == 0 { 'M' } else { 'L' };
+            let _ = write!(out, "{cmd}{} {}", p.x as f32, p.y as f32);
+        }
+        out.push_str("Z\"/>\n");
+    }
+    let segments: Vec<_> = segments
+        .iter()
+        .filter(|&&(a, b)| is_finite(a) && is_finite(b))
+        .collect();
+    if !segments.is_empty() {
+        out.push_str(r#"<path fill="none" d=""#);
+        for (a, b) in segments {
+            let _ = write!(
+                out,
+                "M{} {}L{} {}",
+                a.x as f32, a.y as f32, b.x as f32, b.y as f32
+            );
+        }
+        out.push_str("\"/>\n");
+    }
+    out.push_str("</g>\n</svg>\n");
+    out
+}
+
+/// GeoJSON `FeatureCollection` of the polygons; empty ones are left out but
+/// keep their number in the `index` property.
+pub(crate) fn geojson(polygons: &[Vec<Pt>]) -> String {
+    let mut out = String::from(r#"{"type":"FeatureCollection","features":["#);
+    let mut first = true;
+    for (index, poly) in polygons.iter().enumerate() {
+        if poly.len() < 3 || !poly.iter().all(|&p| is_finite(p)) {
+            continue;
+        }
+        if !first {
+            out.push(',');
+        }
+        first = false;
+        let _ = write!(
+            out,
+            r#"{{"type":"Feature","properties":{{"index":{index}}},"geometry":{{"type":"Polygon","coordinates":[["#
+        );
+        // Rings are closed by repeating the first position.
+        for (k, p) in poly.iter().chain(&poly[..1]).enumerate() {
+            if k > 0 {
+                out.push(',');
+            }
+            let _ = write!(out, "[{},{}]", p.x as f32, p.y as f32);
+        }
+        out.push_str("]]}}");
+    }
+    out.push_str("]}");
+    out
+}
+
+/// Wavefront OBJ of the triangles in `indices`.
+pub(crate) fn obj(points: &[Pt], indices: &[u32]) -> String {
+    // OBJ indices are 1-based and refer to the vertices written so far.
+    let mut remap = vec![0usize; points.len()];
+    let mut out = String::new();
+    let mut count = 0;
+    let mut faces = String::new();
+    for t in indices.chunks_exact(3) {
+        if t.iter().any(|&i| i as usize >= points.len()) {
+            continue;
+        }
+        faces.push('f');
+        for &i in t {
+            let i = i as usize;
+            if remap[i] == 0 {
+                count += 1;
+                remap[i] = count;
+                let _ = writeln!(out, "v {} {} 0", points[i].x as f32, points[i].y as f32);
+            }
+            let _ = write!(faces, " {}", remap[i]);
+        }
+        faces.push('\n');
+    }
+    out.push_str(&faces);
+    out
+}
+
+/// SVG of the triangles `indices` over `points`, read with `stride` floats
+/// per point.
+pub fn triangles_svg(points: &[f32], stride: usize, indices: &[u32], style: &SvgStyle) -> String {
+    svg(
+        &triangle_polygons(&read_strided(points, stride), indices),
+        &[],
+        style,
+    )
+}
+
+/// SVG of line segments [x1,y1,x2,y2,...].
+pub fn edges_svg(segments: &[f32], style: &SvgStyle) -> String {
+    let ends = read_xy(segments);
+    let segments: Vec<_> = ends.chunks_exact(2).map(|s| (s[0], s[1])).collect();
+    svg(&[], &segments, style)
+}
+
+/// GeoJSON of the triangles `indices` over `points`, read with `stride`
+/// floats per point.
+pub fn triangles_geojson(points: &[f32], stride: usize, indices: &[u32]) -> String {
+    geojson(&triangle_polygons(&read_strided(points, stride), indices))
+}
+
+/// One polygon per cell of a `VoronoiCells` buffer; cells whose offsets
+/// are out of range or decreasing are empty.
+fn cell_polygons(coords: &[f32], offsets: &[u32]) -> Vec<Vec<Pt>> {
+    let points = read_xy(coords);
+    offsets
+        .windows(2)
+        .map(|w| {
+            points
+                .get(w[0] as usize..w[1] as usize)
+                .map_or_else(Vec::new, <[Pt]>::to_vec)
+        })
+        .collect()
+}
+
+/// SVG of Voronoi cells given as the `coords` and `offsets` of
+/// `VoronoiCells`, one path per non-empty cell.
+pub fn cells_svg(coords: &[f32], offsets: &[u32], style: &SvgStyle) -> String {
+    svg(&cell_polygons(coords, offsets), &[], style)
+}
+
+/// GeoJSON of Voronoi cells given as the `coords` and `offsets` of
+/// `VoronoiCells`; the `index` property is the site.
+pub fn cells_geojson(coords: &[f32], offsets: &[u32]) -> String {
+    geojson(&cell_polygons(coords, offsets))
+}
+
+/// Wavefront OBJ of the triangles `indices` over `points`, read with
+/// `stride` floats per point.
+pub fn triangles_obj(points: &[f32], stride: usize, indices: &[u32]) -> String {
+    obj(&read_strided(points, stride), indices)
+}
+
+#[wasm_bindgen]
+impl VoronoiTests {
+    /// SVG of the triangles `indices` over `points`, which have `stride`
+    /// floats each: 4 for the [x,y,vx,vy,...] input of
+    /// [`VoronoiTests::delaunay_indices`], 2 for [x0,y0,x1,y1,...].
+    pub fn triangles_svg(points: &[f32], stride: u32, indices: &[u32], style: &SvgStyle) -> String {
+        triangles_svg(points, stride as usize, indices, style)
+    }
+
+    /// SVG of line segments [x1,y1,x2,y2,...], e.g. from
+    /// [`VoronoiTests::voronoi_edges`]. The fill of the style is ignored.
+    pub fn edges_svg(segments: &[f32], style: &SvgStyle) -> String {
+        edges_svg(segments, style)
+    }
+
+    /// SVG of Voronoi cells, one path per non-empty cell.
+    pub fn cells_svg(cells: &VoronoiCells, style: &SvgStyle) -> String {
+        cells_svg(&cells.coords, &cells.offsets, style)
+    }
+
+    /// GeoJSON of the triangles `indices` over `points`, read like in
+    /// [`VoronoiTests::triangles_svg`].
+    pub fn triangles_geojson(points: &[f32], stride: u32, indices: &[u32]) -> String {
+        triangles_geojson(points, stride as usize, indices)
+    }
+
+    /// GeoJSON of Voronoi cells; the `index` property is the site.
+    pub fn cells_geojson(cells: &VoronoiCells) -> String {
+        cells_geojson(&cells.coords, &cells.offsets)
+    }
+
+    /// Wavefront OBJ of the triangles `indices` over `points`, read like in
+    /// [`VoronoiTests::triangles_svg`].
+    pub fn triangles_obj(points: &[f32], stride: u32, indices: &[u32]) -> String {
+        triangles_obj(points, stride as usize, indices)
+    }
+}
+
+#[wasm_bindgen]
+impl DelaunayTriangulation {
+    /// SVG of the current triangles.
+    pub fn to_svg(&self, style: &SvgStyle) -> String {
+        let indices = triangle_indices(&self.tri);
+        svg(&triangle_polygons(&self.tri.points, &indices), &[], style)
+    }
+
+    /// GeoJSON of the current triangles.
+    pub fn to_geojson(&self) -> String {
+        let indices = triangle_indices(&self.tri);
+        geojson(&triangle_polygons(&self.tri.points, &indices))
+    }
+
+    /// Wavefront OBJ of the current triangles.
+    pub fn to_obj(&self) -> String {
+        obj(&self.tri.points, &triangle_indices(&self.tri))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A square with a point inside, in the [x,y,vx,vy,...] layout.
+    const POINTS: [f32; 20] = [
+        0.0, 0.0, 1.0, 1.0, 10.0, 0.0, 1.0, 1.0, 0.0, 10.0, 1.0, 1.0, 10.0, 10.0, 1.0, 1.0, 5.0,
+        4.0, 1.0, 1.0,
+    ];
+
+    #[test]
+    fn strides_read_the_same_points() {
+        let indices = VoronoiTests::delaunay_indices(&POINTS, 0.0)
+            .unwrap()
+            .triangles;
+        let xy: Vec<f32> = POINTS.chunks(4).flat_map(|c| [c[0], c[1]]).collect();
+        let style = SvgStyle::new("black".into(), 1.0, String::new());
+        assert_eq!(
+            triangles_svg(&POINTS, 4, &indices, &style),
+            triangles_svg(&xy, 2, &indices, &style)
+        );
+        assert_eq!(
+            triangles_geojson(&POINTS, 4, &indices),
+            triangles_geojson(&xy, 2, &indices)
+        );
+        assert_eq!(
+            triangles_obj(&POINTS, 4, &indices),
+            triangles_obj(&xy, 2, &indices)
+        );
+        // Every triangle of the square makes it into the output.
+        let geojson = triangles_geojson(&POINTS, 4, &indices);
+        assert_eq!(geojson.matches("\"Polygon\"").count(), indices.len() / 3);
+        assert!(!geojson.contains("[1,1]"), "velocities read as points");
+    }
+
+    #[test]
+    fn svg_attributes_are_escaped() {
+        let style = SvgStyle::new(r#"a"b<c&d"#.into(), 2.0, String::new());
+        let svg = triangles_svg(&[0.0, 0.0, 1.0, 0.0, 0.0, 1.0], 2, &[0, 1, 2], &style);
+        assert!(svg.contains(r#"stroke="a&quot;b&lt;c&amp;d""#), "{svg}");
+        assert!(svg.contains(r#"fill="none""#));
+        assert!(svg.contains(r#"d="M0 0L1 0L0 1Z""#));
+        // The view box is padded by the stroke width.
+        assert!(svg.contains(r#"viewBox="-2 -2 5 5""#), "{svg}");
+    }
+
+    #[test]
+    fn geojson_rings_are_closed() {
+        let points = [0.0, 0.0, 4.0, 0.0, 0.0, 3.0, f32::NAN, 0.0];
+        // The second triangle has a NaN corner, the third an index out of
+        // range; both are left out but keep their number.
+        let json = triangles_geojson(&points, 2, &[0, 1, 2, 0, 3, 1, 0, 1, 9, 2, 1, 0]);
+        assert_eq!(json.matches("\"Feature\"").count(), 2);
+        assert!(json.contains(r#""properties":{"index":0},"geometry":{"type":"Polygon","coordinates":[[[0,0],[4,0],[0,3],[0,0]]]}"#), "{json}");
+        assert!(json.contains(r#""properties":{"index":3}"#));
+        assert!(json.contains("[[[0,3],[4,0],[0,0],[0,3]]]"));
+        assert_eq!(
+            triangles_geojson(&[], 2, &[]),
+            r#"{"type":"FeatureCollection","features":[]}"#
+        );
+    }
+
+    #[test]
+    fn cells_serialise_like_the_wasm_methods() {
+        let cells = VoronoiTests::voronoi_cells(&POINTS, 0.0, 0.0, 10.0, 10.0);
+        let style = SvgStyle::new("red".into(), 0.5, "blue".into());
+        assert_eq!(
+            cells_svg(&cells.coords, &cells.offsets, &style),
+            VoronoiTests::cells_svg(&cells, &style)
+        );
+        let json = cells_geojson(&cells.coords, &cells.offsets);
+        assert_eq!(json, VoronoiTests::cells_geojson(&cells));
+        assert_eq!(json.matches("\"Polygon\"").count(), 5);
+
+        // Malformed offsets give empty cells instead of a panic.
+        let json = cells_geojson(&[0.0, 0.0, 1.0, 0.0, 0.0, 1.0], &[0, 3, 9, 1]);
+        assert_eq!(json.matches("\"Feature\"").count(), 1);
+    }
+
+    #[test]
+    fn obj_indices_are_one_based() {
+        // Point 0 is unused, so the first vertex written is point 1.
+        let points = [9.0, 9.0, 0.0, 0.0, 2.0, 0.0, 0.0, 2.0, 2.0, 2.0];
+        let obj = triangles_obj(&points, 2, &[1, 2, 3, 3, 2, 4, 1, 2, 7]);
+        let lines: Vec<&str> = obj.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "v 0 0 0", "v 2 0 0", "v 0 2 0", "v 2 2 0", "f 1 2 3", "f 3 2 4"
+            ]
+        );
+    }
+}
@@ -0,0 +1,335 @@
+//! Proximity graphs derived from the Delaunay triangulation.
+//!
+//! Euclidean minimum spanning tree ⊆ relative neighbourhood graph ⊆ Gabriel
+//! graph ⊆ Delaunay triangulation, and every point's nearest neighbours are
+//! reachable along Delaunay edges. So all of them are found by filtering or
+//! searching the Delaunay edges instead of looking at all pairs of points.
+//!
+//! Edges are returned as flat index pairs `[a0,b0,a1,b1,...]`. Duplicate
+//! points take no part; points that all lie on one line are connected in
+//! order along it.
+
+use super::cells::collinear_order;
+use super::triangulation::{GHOST, Triangulation, next, prev};
+use super::{VoronoiTests, read_points};
+use std::collections::HashSet;
+use wasm_bindgen::prelude::*;
+
+/// Every Delaunay edge once, as `(a, b)` vertex pairs.
+pub(crate) fn delaunay_edges(tri: &Triangulation) -> Vec<(usize, usize)> {
+    if !tri.has_triangles() {
+        let order = collinear_order(&tri.points);
+        return order.windows(2).map(|w| (w[0], w[1])).collect();
+    }
+    let mut edges = Vec::with_capacity(tri.verts.len() / 2);
+    for (e, &twin) in tri.twins.iter().enumerate() {
+        let (a, b) = (tri.verts[e], tri.verts[next(e)]);
+        if twin < e || a == GHOST || b == GHOST || !tri.is_live(e / 3) {
+            continue;
+        }
+        edges.push((a, b));
+    }
+    edges
+}
+
+/// Kruskal's algorithm over the Delaunay edges.
+pub(crate) fn euclidean_mst(tri: &Triangulation) -> Vec<(usize, usize)> {
+    let points = &tri.points;
+    let mut edges = delaunay_edges(tri);
+    edges.sort_by(|&(a, b), &(c, d)| {
+        let l0 = points[a].sub(points[b]).len2();
+        let l1 = points[c].sub(points[d]).len2();
+        l0.total_cmp(&l1)
+    });
+    let mut sets = DisjointSets::new(points.len());
+    edges.retain(|&(a, b)| sets.union(a, b));
+    edges
+}
+
+/// Delaunay edges whose closed diametral disc contains no other point: the
+/// angle opposite the edge is acute in both adjacent triangles.
+pub(crate) fn gabriel_edges(tri: &Triangulation) -> Vec<(usize, usize)> {
+    if !tri.has_triangles() {
+        return delaunay_edges(tri);
+    }
+    let points = &tri.points;
+    let acute_opposite = |e: usize| {
+        let c = tri.verts[prev(e)];
+        if c == GHOST {
+            return true;
+        }
+        let (a, b) = (tri.verts[e], tri.verts[next(e)]);
+        points[a].sub(points[c]).dot(points[b].sub(points[c])) > 0.0
+    };
+    let mut edges = Vec::new();
+    for (e, &twin) in tri.twins.iter().enumerate() {
+        let (a, b) = (tri.verts[e], tri.verts[next(e)]);
+        if twin < e || a == GHOST || b == GHOST || !tri.is_live(e / 3) {
+            continue;
+        }
+        if acute_opposite(e) && acute_opposite(twin) {
+            edges.push((a, b));
+        }
+    }
+    edges
+}
+
+/// Gabriel edges `a - b` with no point `c` closer to both `a` and `b` than
+/// they are to each other. Such a `c` is closer to `a` than `b` is, and the
+/// points within a given distance of `a` are connected by Delaunay edges, so
+/// a flood fill from `a` that stays within that distance finds it.
+pub(crate) fn relative_neighborhood_edges(tri: &Triangulation) -> Vec<(usize, usize)> {
+    let points = &tri.points;
+    let mut edges = gabriel_edges(tri);
+    if !tri.has_triangles() {
+        return edges;
+    }
+    let mut seen = HashSet::new();
+    let mut stack = Vec::new();
+    edges.retain(|&(a, b)| {
+        let ab = points[a].sub(points[b]).len2();
+        seen.clear();
+        seen.insert(a);
+        stack.clear();
+        stack.push(a);
+        while let Some(v) = stack.pop() {
+            for c in tri.neighbors(v) {
+                if c == GHOST || points[c].sub(points[a]).len2() >= ab || !seen.insert(c) {
+                    continue;
+                }
+                if points[c].sub(points[b]).len2() < ab {
+                    return false;
+                }
+                stack.push(c);
+            }
+        }
+        true
+    });
+    edges
+}
+
+/// Directed edges from every vertex to its `k` nearest other vertices,
+/// nearest first. With `k = 1` this is the nearest-neighbour graph.
+pub(crate) fn knn_graph(tri: &mut Triangulation, k: usize) -> Vec<(usize, usize)> {
+    let mut edges = Vec::new();
+    if !tri.has_triangles() {
+        // Along a line the nearest neighbours are found by merging outwards.
+        let order = collinear_order(&tri.points);
+        let dist = |i: usize, j: usize| tri.points[order[i]].sub(tri.points[order[j]]).len2();
+        for i in 0..order.len() {
+            let (mut lo, mut hi) = (i, i + 1);
+            for _ in 0..k.min(order.len() - 1) {
+                let j = if hi == order.len() || (lo > 0 && dist(i, lo - 1) <= dist(i, hi)) {
+                    lo -= 1;
+                    lo
+                } else {
+                    hi += 1;
+                    hi - 1
+                };
+                edges.push((order[i], order[j]));
+            }
+        }
+        return edges;
+    }
+    for v in 0..tri.points.len() {
+        if !tri.is_vertex(v) {
+            continue;
+        }
+        let near = tri.k_nearest(tri.points[v], k + 1);
+        edges.extend(near.into_iter().filter(|&w| w != v).map(|w| (v, w)));
+    }
+    edges
+}
+
+/// Union-find with path halving and union by size.
+struct DisjointSets {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl DisjointSets {
+    fn new(n: usize) -> Self {
+        Self {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+        }
+    }
+
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    /// Merge the sets of `a` and `b`; `false` if they already were one.
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        true
+    }
+}
+
+fn flatten(edges: &[(usize, usize)]) -> Vec<u32> {
+    edges
+        .iter()
+        .flat_map(|&(a, b)| [a as u32, b as u32])
+        .collect()
+}
+
+#[wasm_bindgen]
+impl VoronoiTests {
+    /// Euclidean minimum spanning tree as index pairs [a0,b0,a1,b1,...]
+    /// Input layout: [x,y,vx,vy,...]
+    pub fn emst_edges(points_flat: &[f32]) -> Vec<u32> {
+        let tri = Triangulation::new(&read_points(points_flat));
+        flatten(&euclidean_mst(&tri))
+    }
+
+    /// Gabriel graph as index pairs [a0,b0,a1,b1,...]
+    pub fn gabriel_edges(points_flat: &[f32]) -> Vec<u32> {
+        let tri = Triangulation::new(&read_points(points_flat));
+        flatten(&gabriel_edges(&tri))
+    }
+
+    /// Relative neighbourhood graph as index pairs [a0,b0,a1,b1,...]
+    pub fn relative_neighborhood_edges(points_flat: &[f32]) -> Vec<u32> {
+        let tri = Triangulation::new(&read_points(points_flat));
+        flatten(&relative_neighborhood_edges(&tri))
+    }
+
+    /// Directed k-nearest-neighbour graph as index pairs [from,to,...], `k`
+    /// edges per point, nearest first. `k = 1` gives the nearest-neighbour
+    /// graph.
+    pub fn knn_graph_edges(points_flat: &[f32], k: u32) -> Vec<u32> {
+        let mut tri = Triangulation::new(&read_points(points_flat));
+        flatten(&knn_graph(&mut tri, k as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voronoi::Pt;
+    use crate::voronoi::test_util::{pt, random_points};
+
+    fn edge_set(edges: &[(usize, usize)]) -> HashSet<(usize, usize)> {
+        edges.iter().map(|&(a, b)| (a.min(b), a.max(b))).collect()
+    }
+
+    fn length(points: &[Pt], (a, b): (usize, usize)) -> f64 {
+        points[a].sub(points[b]).len2().sqrt()
+    }
+
+    /// Weight of the minimum spanning tree of the complete graph (Prim).
+    fn prim_weight(points: &[Pt]) -> f64 {
+        let n = points.len();
+        let mut best = vec![f64::INFINITY; n];
+        let mut done = vec![false; n];
+        best[0] = 0.0;
+        let mut total = 0.0;
+        for _ in 0..n {
+            let u = (0..n)
+                .filter(|&i| !done[i])
+                .min_by(|&i, &j| best[i].total_cmp(&best[j]))
+                .unwrap();
+            done[u] = true;
+            total += best[u];
+            for v in 0..n {
+                if !done[v] {
+                    best[v] = best[v].min(length(points, (u, v)));
+                }
+            }
+        }
+        total
+    }
+
+    #[test]
+    fn emst_matches_prim() {
+        for seed in 1..4 {
+            let points = random_points(300, seed, 100.0);
+            let tri = Triangulation::new(&points);
+            let mst = euclidean_mst(&tri);
+            assert_eq!(mst.len(), points.len() - 1);
+            let weight: f64 = mst.iter().map(|&e| length(&points, e)).sum();
+            assert!((weight - prim_weight(&points)).abs() < 1e-9 * weight);
+        }
+    }
+
+    /// Every point's edges lead to its `k` nearest other points, nearest
+    /// first; ties may go either way.
+    fn assert_knn(points: &[Pt], edges: &[(usize, usize)], k: usize) {
+        assert_eq!(edges.len(), points.len() * k);
+        for (v, p) in points.iter().enumerate() {
+            let found: Vec<f64> = edges
+                .iter()
+                .filter(|&&(a, _)| a == v)
+                .map(|&(_, b)| points[b].sub(*p).len2())
+                .collect();
+            let mut all: Vec<f64> = (0..points.len())
+                .filter(|&w| w != v)
+                .map(|w| points[w].sub(*p).len2())
+                .collect();
+            all.sort_by(f64::total_cmp);
+            assert_eq!(found, all[..k], "point {v}");
+        }
+    }
+
+    #[test]
+    fn proximity_graphs_nest() {
+        let points = random_points(300, 9, 100.0);
+        let tri = Triangulation::new(&points);
+        let delaunay = edge_set(&delaunay_edges(&tri));
+        let gabriel = edge_set(&gabriel_edges(&tri));
+        let rng = edge_set(&relative_neighborhood_edges(&tri));
+        let mst = edge_set(&euclidean_mst(&tri));
+        assert!(mst.is_subset(&rng));
+        assert!(rng.is_subset(&gabriel));
+        assert!(gabriel.is_subset(&delaunay));
+        assert!(mst.len() < rng.len() && rng.len() < gabriel.len());
+
+        let mut tri = tri;
+        for k in [1, 3, 8] {
+            assert_knn(&points, &knn_graph(&mut tri, k), k);
+        }
+        let nearest = edge_set(&knn_graph(&mut tri, 1));
+        assert!(nearest.is_subset(&mst));
+
+        // Points on one line, in shuffled order.
+        let line: Vec<Pt> = (0..40)
+            .map(|i| {
+                let x = ((i * 17) % 40) as f64 + 0.25 * (i % 3) as f64;
+                pt(x, 2.0 * x + 1.0)
+            })
+            .collect();
+        let mut collinear = Triangulation::new(&line);
+        assert!(!collinear.has_triangles());
+        for k in [1, 2, 5] {
+            assert_knn(&line, &knn_graph(&mut collinear, k), k);
+        }
+
+        // Check both definitions against all points.
+        for &(a, b) in &delaunay {
+            let (pa, pb) = (points[a], points[b]);
+            let mid = pa.add(pb).mul(0.5);
+            let r2 = pb.sub(pa).len2();
+            let others = (0..points.len()).filter(|&c| c != a && c != b);
+            let in_disc = others
+                .clone()
+                .any(|c| points[c].sub(mid).len2() < r2 * 0.25);
+            let in_lune = others
+                .into_iter()
+                .any(|c| points[c].sub(pa).len2() < r2 && points[c].sub(pb).len2() < r2);
+            assert_eq!(gabriel.contains(&(a, b)), !in_disc, "Gabriel {a}-{b}");
+            assert_eq!(rng.contains(&(a, b)), !in_lune, "RNG {a}-{b}");
+        }
+    }
+}
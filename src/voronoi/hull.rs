@@ -0,0 +1,470 @@
+//! Outlines of point clouds: convex hull, alpha shapes and concave hull.
+//!
+//! The convex hull is available both from Andrew's monotone chain, which
+//! needs no triangulation, and directly from the ghost triangles of the
+//! Delaunay triangulation. Alpha shapes and the concave hull are subsets of
+//! the Delaunay triangles; their outlines are traced as closed loops of
+//! vertex ids, counter-clockwise for outer boundaries and clockwise for holes.
+
+use super::cells::collinear_order;
+use super::delaunay::DelaunayTriangulation;
+use super::predicates::orient2d;
+use super::triangulation::{GHOST, NONE, Triangulation, next, prev};
+use super::{Pt, VoronoiTests, circumcircle, read_points};
+use std::collections::BinaryHeap;
+use wasm_bindgen::prelude::*;
+
+/// Triangles of an alpha shape and its boundary loops.
+///
+/// Loop `i` consists of the vertex ids `loops[loop_offsets[i]..loop_offsets[i + 1]]`.
+/// Outer boundaries run counter-clockwise, holes clockwise.
+#[wasm_bindgen(getter_with_clone)]
+pub struct AlphaShape {
+    /// Kept Delaunay triangles as index triplets.
+    #[wasm_bindgen(readonly)]
+    pub triangles: Vec<u32>,
+    #[wasm_bindgen(readonly)]
+    pub loops: Vec<u32>,
+    #[wasm_bindgen(readonly)]
+    pub loop_offsets: Vec<u32>,
+}
+
+/// Convex hull by Andrew's monotone chain, counter-clockwise, starting at the
+/// lowest-leftmost point. Points on hull edges and duplicates are left out.
+pub(crate) fn convex_hull(points: &[Pt]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by(|&a, &b| {
+        let (p, q) = (points[a], points[b]);
+        p.x.total_cmp(&q.x).then(p.y.total_cmp(&q.y))
+    });
+    order.dedup_by(|b, a| points[*a] == points[*b]);
+    if order.len() < 3 {
+        return order;
+    }
+
+    // Lower chain left to right, upper chain right to left.
+    let mut hull = half_hull(points, order.iter().copied());
+    hull.extend(half_hull(points, order.iter().rev().copied()));
+    hull
+}
+
+/// One monotone chain, without its last point (which starts the other one).
+fn half_hull(points: &[Pt], ids: impl Iterator<Item = usize>) -> Vec<usize> {
+    let mut out: Vec<usize> = Vec::new();
+    for i in ids {
+        while let [.., a, b] = out[..] {
+            if orient2d(points[a], points[b], points[i]) > 0.0 {
+                break;
+            }
+            out.pop();
+        }
+        out.push(i);
+    }
+    out.pop();
+    out
+}
+
+impl Triangulation {
+    /// Hull vertices in counter-clockwise order, read off the ghost
+    /// triangles. Unlike [`convex_hull`] this keeps points that lie on a hull
+    /// edge, since they are vertices of the triangulation's boundary.
+    pub(crate) fn hull(&self) -> Vec<usize> {
+        let Some(start) = (0..self.tri_count()).find(|&t| self.is_live(t) && self.is_ghost(t))
+        else {
+            return collinear_order(&self.points);
+        };
+        // In ghost (y, x, GHOST) of hull edge x -> y, let `g` be GHOST -> y;
+        // its twin y -> GHOST lies in the ghost of the next hull edge.
+        let mut g = (0..3)
+            .map(|k| 3 * start + k)
+            .find(|&e| self.verts[e] == GHOST)
+            .expect("ghost triangle without ghost vertex");
+        let mut hull = Vec::new();
+        loop {
+            hull.push(self.verts[prev(g)]);
+            g = next(self.twins[g]);
+            if g / 3 == start {
+                return hull;
+            }
+        }
+    }
+}
+
+/// Trace the boundary of the union of the triangles with `kept[t]` as closed
+/// loops of vertex ids. Pinch vertices are visited once per loop through them.
+fn boundary_loops(tri: &Triangulation, kept: &[bool]) -> Vec<Vec<usize>> {
+    let is_kept = |e: usize| e != NONE && kept[e / 3];
+    let is_boundary = |e: usize| is_kept(e) && !is_kept(tri.twins[e]);
+    let mut visited = vec![false; tri.verts.len()];
+    let mut loops = Vec::new();
+    for start in 0..tri.verts.len() {
+        if visited[start] || !is_boundary(start) {
+            continue;
+        }
+        let mut ring = Vec::new();
+        let mut e = start;
+        while !visited[e] {
+            visited[e] = true;
+            ring.push(tri.verts[e]);
+            // Turn around the end vertex through kept triangles until the
+            // next edge on the boundary.
+            let mut f = next(e);
+            while is_kept(tri.twins[f]) {
+                f = next(tri.twins[f]);
+            }
+            e = f;
+        }
+        loops.push(ring);
+    }
+    loops
+}
+
+/// Solid triangles with a circumradius of at most `alpha`.
+pub(crate) fn alpha_triangles(tri: &Triangulation, alpha: f64) -> Vec<bool> {
+    let r2 = alpha * alpha;
+    (0..tri.tri_count())
+        .map(|t| {
+            if !tri.is_solid(t) {
+                return false;
+            }
+            let [a, b, c] = tri.tri_verts(t).map(|v| tri.points[v]);
+            circumcircle(a, b, c).is_some_and(|cc| cc.r2 <= r2)
+        })
+        .collect()
+}
+
+/// Longest boundary edge first.
+struct BoundaryEdge {
+    len2: f64,
+    e: usize,
+}
+
+impl PartialEq for BoundaryEdge {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for BoundaryEdge {}
+
+impl PartialOrd for BoundaryEdge {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BoundaryEdge {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.len2.total_cmp(&other.len2).then(self.e.cmp(&other.e))
+    }
+}
+
+/// Concave hull after Duckham et al. ("chi-shapes"): starting from the
+/// Delaunay triangulation, repeatedly remove the boundary triangle behind the
+/// longest boundary edge while that edge is longer than `max_edge` and the
+/// outline stays a simple polygon. Returns the outline counter-clockwise.
+pub(crate) fn concave_hull(tri: &Triangulation, max_edge: f64) -> Vec<usize> {
+    if !tri.has_triangles() {
+        return tri.hull();
+    }
+    let mut kept: Vec<bool> = (0..tri.tri_count()).map(|t| tri.is_solid(t)).collect();
+    let mut on_boundary = vec![false; tri.points.len()];
+    let mut heap = BinaryHeap::new();
+    let len2 = |e: usize| {
+        let (a, b) = (tri.verts[e], tri.verts[next(e)]);
+        tri.points[a].sub(tri.points[b]).len2()
+    };
+    for v in tri.hull() {
+        on_boundary[v] = true;
+    }
+    for (e, &twin) in tri.twins.iter().enumerate() {
+        if kept[e / 3] && twin != NONE && tri.is_ghost(twin / 3) {
+            heap.push(BoundaryEdge { len2: len2(e), e });
+        }
+    }
+
+    let limit = max_edge * max_edge;
+    while let Some(BoundaryEdge { len2: l, e }) = heap.pop() {
+        if l <= limit {
+            break;
+        }
+        let t = e / 3;
+        let c = tri.verts[prev(e)];
+        // Removing the triangle would pinch the outline at `c`.
+        if !kept[t] || on_boundary[c] {
+            continue;
+        }
+        kept[t] = false;
+        on_boundary[c] = true;
+        for f in [tri.twins[next(e)], tri.twins[prev(e)]] {
+            if kept[f / 3] {
+                heap.push(BoundaryEdge {
+                    len2: len2(f),
+                    e: f,
+                });
+            }
+        }
+    }
+    boundary_loops(tri, &kept)
+        .into_iter()
+        .next()
+        .unwrap_or_default()
+}
+
+fn to_u32(ids: &[usize]) -> Vec<u32> {
+    ids.iter().map(|&i| i as u32).collect()
+}
+
+#[wasm_bindgen]
+impl VoronoiTests {
+    /// Convex hull as point indices in counter-clockwise order (monotone
+    /// chain). Input layout: [x,y,vx,vy,...]
+    pub fn convex_hull(points_flat: &[f32]) -> Vec<u32> {
+        to_u32(&convex_hull(&read_points(points_flat)))
+    }
+
+    /// Convex hull taken from the boundary of the Delaunay triangulation,
+    /// counter-clockwise, including points that lie on hull edges.
+    pub fn delaunay_hull(points_flat: &[f32]) -> Vec<u32> {
+        to_u32(&Triangulation::new(&read_points(points_flat)).hull())
+    }
+
+    /// Alpha shape: the Delaunay triangles with a circumradius of at most
+    /// `alpha`, and the loops bounding them.
+    pub fn alpha_shape(points_flat: &[f32], alpha: f32) -> AlphaShape {
+        let tri = Triangulation::new(&read_points(points_flat));
+        let kept = alpha_triangles(&tri, alpha as f64);
+        let mut triangles = Vec::new();
+        for t in (0..kept.len()).filter(|&t| kept[t]) {
+            triangles.extend(tri.tri_verts(t).map(|v| v as u32));
+        }
+        let mut loops = Vec::new();
+        let mut loop_offsets = vec![0];
+        for ring in boundary_loops(&tri, &kept) {
+            loops.extend(ring.into_iter().map(|v| v as u32));
+            loop_offsets.push(loops.len() as u32);
+        }
+        AlphaShape {
+            triangles,
+            loops,
+            loop_offsets,
+        }
+    }
+
+    /// Concave hull as point indices in counter-clockwise order. Boundary
+    /// edges longer than `max_edge` are cut into the point cloud as long as
+    /// the outline stays a simple polygon; `Infinity` gives the convex hull.
+    pub fn concave_hull(points_flat: &[f32], max_edge: f32) -> Vec<u32> {
+        let tri = Triangulation::new(&read_points(points_flat));
+        to_u32(&concave_hull(&tri, max_edge as f64))
+    }
+}
+
+#[wasm_bindgen]
+impl DelaunayTriangulation {
+    /// Ids of the hull vertices in counter-clockwise order. While the live
+    /// points do not span a triangle, all of them in order along their line.
+    pub fn hull(&self) -> Vec<u32> {
+        if self.tri.has_triangles() {
+            return to_u32(&self.tri.hull());
+        }
+        // The collinear order has to skip removed ids.
+        let live: Vec<usize> = (0..self.removed.len())
+            .filter(|&i| !self.removed[i])
+            .collect();
+        let points: Vec<Pt> = live.iter().map(|&i| self.tri.points[i]).collect();
+        let order: Vec<usize> = collinear_order(&points)
+            .into_iter()
+            .map(|k| live[k])
+            .collect();
+        to_u32(&order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voronoi::cells::signed_area;
+    use crate::voronoi::test_util::{inside, pt, random_points};
+
+    /// [x,y,vx,vy,...] for the given positions.
+    fn flat(points: &[Pt]) -> Vec<f32> {
+        points
+            .iter()
+            .flat_map(|p| [p.x as f32, p.y as f32, 0.0, 0.0])
+            .collect()
+    }
+
+    fn outline(points: &[Pt], ids: &[usize]) -> Vec<Pt> {
+        ids.iter().map(|&i| points[i]).collect()
+    }
+
+    /// `ring` as a rotation of `expected`.
+    fn same_ring(mut ring: Vec<usize>, expected: &[usize]) -> bool {
+        match ring.iter().position(|&i| Some(&i) == expected.first()) {
+            Some(start) => {
+                ring.rotate_left(start);
+                ring == expected
+            }
+            None => expected.is_empty() && ring.is_empty(),
+        }
+    }
+
+    #[test]
+    fn hull_of_a_collinear_remainder() {
+        // Points on y = x, two off it, and a copy of (2, 2) after them.
+        let mut dt = DelaunayTriangulation::from_points(vec![
+            pt(2.0, 2.0),
+            pt(0.0, 0.0),
+            pt(5.0, 5.0),
+            pt(0.0, 4.0),
+            pt(4.0, 0.0),
+            pt(3.0, 3.0),
+        ]);
+        let copy = dt.insert_point(pt(1.0, 1.0));
+        assert_eq!(dt.insert_point(pt(2.0, 2.0)), 0);
+        let mut hull = dt.hull();
+        let start = hull.iter().position(|&i| i == 1).unwrap();
+        hull.rotate_left(start);
+        assert_eq!(hull, vec![1, 4, 2, 3]);
+
+        assert!(dt.remove_point(3));
+        assert!(dt.remove_point(4));
+        assert!(dt.delaunay_indices().is_empty());
+        assert_eq!(dt.hull(), vec![1, copy as u32, 0, 5, 2]);
+
+        // A removed id never comes back, also not through a later copy.
+        assert!(dt.remove_point(0));
+        let again = dt.insert_point(pt(2.0, 2.0));
+        assert_ne!(again, 0);
+        assert!(dt.remove_point(again));
+        assert_eq!(dt.hull(), vec![1, copy as u32, 5, 2]);
+    }
+
+    #[test]
+    fn hull_chains_agree() {
+        // Rounded, so that points repeat and lie on hull edges.
+        let points: Vec<Pt> = random_points(200, 17, 100.0)
+            .into_iter()
+            .map(|p| pt(p.x.round(), p.y.round()))
+            .collect();
+        let chain = convex_hull(&points);
+        let mut ghosts = Triangulation::new(&points).hull();
+        // The ghost hull also has the points on hull edges.
+        ghosts.retain(|&i| chain.contains(&i));
+        let start = ghosts.iter().position(|&i| i == chain[0]).unwrap();
+        ghosts.rotate_left(start);
+        assert_eq!(ghosts, chain);
+    }
+
+    #[test]
+    fn alpha_shape_of_a_ring_has_a_hole() {
+        // Three rings of 24 points each, at radii 6, 8 and 10.
+        let mut points = Vec::new();
+        for r in [6.0, 8.0, 10.0] {
+            for k in 0..24 {
+                let a = k as f64 * std::f64::consts::TAU / 24.0;
+                points.push(pt(20.0 + r * a.cos(), 20.0 + r * a.sin()));
+            }
+        }
+        let shape = VoronoiTests::alpha_shape(&flat(&points), 3.0);
+        assert_eq!(shape.loop_offsets, [0, 24, 48]);
+        let rings: Vec<Vec<usize>> = shape
+            .loop_offsets
+            .windows(2)
+            .map(|w| {
+                shape.loops[w[0] as usize..w[1] as usize]
+                    .iter()
+                    .map(|&i| i as usize)
+                    .collect()
+            })
+            .collect();
+        let (outer, hole) = if rings[0].contains(&48) {
+            (&rings[0], &rings[1])
+        } else {
+            (&rings[1], &rings[0])
+        };
+        // Outer boundary counter-clockwise, the hole clockwise.
+        assert!(outer.iter().all(|&i| i >= 48));
+        assert!(signed_area(&outline(&points, outer)) > 0.0);
+        assert!(hole.iter().all(|&i| i < 24));
+        assert!(signed_area(&outline(&points, hole)) < 0.0);
+        // Two triangles per quad between neighbouring rings.
+        assert_eq!(shape.triangles.len(), 3 * 2 * 2 * 24);
+    }
+
+    #[test]
+    fn infinite_alpha_gives_the_convex_hull() {
+        for seed in 1..5 {
+            let points = random_points(150, seed, 100.0);
+            let tri = Triangulation::new(&points);
+            let shape = VoronoiTests::alpha_shape(&flat(&points), f32::INFINITY);
+            let solid = (0..tri.tri_count()).filter(|&t| tri.is_solid(t)).count();
+            assert_eq!(shape.triangles.len(), 3 * solid);
+            assert_eq!(shape.loop_offsets, [0, shape.loops.len() as u32]);
+            let ring: Vec<usize> = shape.loops.iter().map(|&i| i as usize).collect();
+            assert!(same_ring(ring, &convex_hull(&points)), "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn concave_hull_is_simple_and_encloses_the_points() {
+        for seed in 1..5 {
+            let points = random_points(200, seed, 100.0);
+            let tri = Triangulation::new(&points);
+            assert!(same_ring(
+                concave_hull(&tri, f64::INFINITY),
+                &convex_hull(&points)
+            ));
+
+            let max_edge = 12.0;
+            let ids = concave_hull(&tri, max_edge);
+            let poly = outline(&points, &ids);
+            assert!(signed_area(&poly) > 0.0);
+            assert!(signed_area(&poly) < signed_area(&outline(&points, &convex_hull(&points))));
+
+            // No vertex twice and no two edges crossing.
+            let mut sorted = ids.clone();
+            sorted.sort_unstable();
+            sorted.dedup();
+            assert_eq!(sorted.len(), ids.len(), "seed {seed}: pinched outline");
+            let n = ids.len();
+            for i in 0..n {
+                for j in i + 2..n {
+                    if (j + 1) % n == i {
+                        continue;
+                    }
+                    let (a, b) = (poly[i], poly[(i + 1) % n]);
+                    let (c, d) = (poly[j], poly[(j + 1) % n]);
+                    let crosses = orient2d(a, b, c) * orient2d(a, b, d) < 0.0
+                        && orient2d(c, d, a) * orient2d(c, d, b) < 0.0;
+                    assert!(!crosses, "seed {seed}: edges {i} and {j} cross");
+                }
+            }
+
+            for (i, &p) in points.iter().enumerate() {
+                assert!(
+                    ids.contains(&i) || inside(std::slice::from_ref(&poly), p),
+                    "seed {seed}: point {i} is outside"
+                );
+            }
+
+            // A long edge stays only if cutting the triangle behind it would
+            // pinch the outline at its third corner.
+            for k in 0..n {
+                let (a, b) = (ids[k], ids[(k + 1) % n]);
+                if points[a].sub(points[b]).len2() <= max_edge * max_edge {
+                    continue;
+                }
+                let t = (0..tri.tri_count())
+                    .find(|&t| {
+                        let v = tri.tri_verts(t);
+                        tri.is_solid(t) && (0..3).any(|m| v[m] == a && v[(m + 1) % 3] == b)
+                    })
+                    .unwrap();
+                let c = tri.tri_verts(t).into_iter().find(|&v| v != a && v != b);
+                assert!(ids.contains(&c.unwrap()), "seed {seed}: edge {a}-{b}");
+            }
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! Interpolation of values given at scattered sites.
+//!
+//! Linear interpolation blends the three corners of the Delaunay triangle
+//! containing the query point with its barycentric weights; the result is
+//! continuous but has creases along the triangle edges. Sibson's natural
+//! neighbour interpolation weights every site by the area its Voronoi cell
+//! would lose to the query point if that were inserted as a new site, which
+//! is smooth everywhere except at the sites themselves.
+//!
+//! Every site carries `dims` values (one for scalar data, two for vectors,
+//! ...). Outside the convex hull of the sites both methods are undefined and
+//! give `NaN`.
+
+use super::cells::{clip_half_plane, signed_area};
+use super::raster::RasterGrid;
+use super::triangulation::Triangulation;
+use super::{Pt, VoronoiTests, circumcircle, read_points, read_xy};
+use wasm_bindgen::prelude::*;
+
+/// How to blend the values of the sites around a query point.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Barycentric weights in the containing Delaunay triangle.
+    Linear = 0,
+    /// Sibson's natural neighbour coordinates.
+    NaturalNeighbor = 1,
+}
+
+impl Triangulation {
+    /// Sites around `p` with their barycentric weights, or `None` outside
+    /// the convex hull.
+    pub(crate) fn linear_weights(&mut self, p: Pt) -> Option<Vec<(usize, f64)>> {
+        let (verts, weights) = self.locate_barycentric(p)?;
+        Some(verts.into_iter().zip(weights).collect())
+    }
+
+    /// Natural neighbours of `p` with their Sibson coordinates, or `None`
+    /// outside the convex hull. On the hull boundary, where the coordinates
+    /// degenerate to linear interpolation along the hull edge, the linear
+    /// weights are returned.
+    pub(crate) fn sibson_weights(&mut self, p: Pt) -> Option<Vec<(usize, f64)>> {
+        let linear = self.linear_weights(p)?;
+        if let Some(&(v, _)) = linear.iter().find(|&&(v, _)| self.points[v] == p) {
+            return Some(vec![(v, 1.0)]);
+        }
+        let Some(nbrs) = self.natural_neighbors(p) else {
+            return Some(linear);
+        };
+
+        // The Voronoi cell `p` would get, with a corner for every pair of
+        // consecutive natural neighbours.
+        let mut cell = Vec::with_capacity(nbrs.len());
+        for (k, &a) in nbrs.iter().enumerate() {
+            let b = nbrs[(k + 1) % nbrs.len()];
+            match circumcircle(p, self.points[a], self.points[b]) {
+                Some(cc) => cell.push(cc.c),
+                None => return Some(linear),
+            }
+        }
+
+        // The part of it taken from a neighbour is where that neighbour was
+        // the closest site before.
+        let mut weights = Vec::with_capacity(nbrs.len());
+        let mut total = 0.0;
+        for &v in &nbrs {
+            let pv = self.points[v];
+            let mut stolen = cell.clone();
+            for &u in nbrs.iter().filter(|&&u| u != v) {
+                let pu = self.points[u];
+                stolen = clip_half_plane(&stolen, pv.add(pu).mul(0.5), pu.sub(pv));
+                if stolen.is_empty() {
+                    break;
+                }
+            }
+            let area = signed_area(&stolen).max(0.0);
+            total += area;
+            weights.push((v, area));
+        }
+        if total <= 0.0 || total.is_nan() {
+            return Some(linear);
+        }
+        for (_, w) in &mut weights {
+            *w /= total;
+        }
+        Some(weights)
+    }
+}
+
+/// Values at the sites, `dims` per site, and the triangulation to blend them.
+struct Interpolator<'a> {
+    tri: Triangulation,
+    values: &'a [f32],
+    dims: usize,
+    method: Interpolation,
+}
+
+impl<'a> Interpolator<'a> {
+    fn new(points_flat: &[f32], values: &'a [f32], method: Interpolation) -> Self {
+        let points = read_points(points_flat);
+        let dims = if points.is_empty() {
+            0
+        } else {
+            values.len() / points.len()
+        };
+        Self {
+            tri: Triangulation::new(&points),
+            values,
+            dims,
+            method,
+        }
+    }
+
+    /// Append the `dims` interpolated values at `p` to `out`.
+    fn push(&mut self, p: Pt, out: &mut Vec<f32>) {
+        let weights = match self.method {
+            Interpolation::Linear => self.tri.linear_weights(p),
+            Interpolation::NaturalNeighbor => self.tri.sibson_weights(p),
+        };
+        let Some(weights) = weights else {
+            out.extend(std::iter::repeat_n(f32::NAN, self.dims));
+            return;
+        };
+        for d in 0..self.dims {
+            let value: f64 = weights
+                .iter()
+                .map(|&(v, w)| w * self.values[v * self.dims + d] as f64)
+                .sum();
+            out.push(value as f32);
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl VoronoiTests {
+    /// Interpolate the values of the sites at the query points
+    /// [x0,y0,x1,y1,...]. `values` holds the same number of components for
+    /// every site, e.g. [v0,v1,...] for scalars or [u0,v0,u1,v1,...] for
+    /// vectors, and the result has that many per query point; `NaN` outside
+    /// the convex hull. Of coinciding sites only the first one's values are
+    /// used. Input layout: [x,y,vx,vy,...]
+    pub fn interpolate(
+        points_flat: &[f32],
+        values: &[f32],
+        queries: &[f32],
+        method: Interpolation,
+    ) -> Vec<f32> {
+        let mut interp = Interpolator::new(points_flat, values, method);
+        let queries = read_xy(queries);
+        let mut out = Vec::with_capacity(queries.len() * interp.dims);
+        for p in queries {
+            interp.push(p, &mut out);
+        }
+        out
+    }
+
+    /// [`VoronoiTests::interpolate`] at the centre of every pixel of `grid`,
+    /// row by row from `min_y` upwards.
+    pub fn interpolate_grid(
+        points_flat: &[f32],
+        values: &[f32],
+        grid: &RasterGrid,
+        method: Interpolation,
+    ) -> Vec<f32> {
+        let mut interp = Interpolator::new(points_flat, values, method);
+        let (w, h) = grid.size();
+        let mut out = Vec::with_capacity(w * h * interp.dims);
+        for j in 0..h {
+            for i in 0..w {
+                interp.push(grid.center(i, j), &mut out);
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voronoi::test_util::{pt, random_points};
+
+    fn linear(p: Pt) -> f64 {
+        3.0 * p.x - 2.0 * p.y + 7.0
+    }
+
+    #[test]
+    fn linear_functions_are_reproduced() {
+        // Random sites, and a grid where many circumcircles coincide.
+        let grid: Vec<Pt> = (0..100)
+            .map(|k| pt((k % 10) as f64 * 10.0, (k / 10) as f64 * 10.0))
+            .collect();
+        for sites in [random_points(300, 9, 100.0), grid] {
+            let mut tri = Triangulation::new(&sites);
+            let mut inside = 0;
+            for q in random_points(2000, 11, 100.0) {
+                let linear_w = tri.linear_weights(q);
+                let sibson_w = tri.sibson_weights(q);
+                assert_eq!(linear_w.is_some(), sibson_w.is_some(), "{q:?}");
+                let (Some(l), Some(s)) = (linear_w, sibson_w) else {
+                    continue;
+                };
+                inside += 1;
+                for weights in [l, s] {
+                    let sum: f64 = weights.iter().map(|&(_, w)| w).sum();
+                    let at = |f: &dyn Fn(Pt) -> f64| -> f64 {
+                        weights.iter().map(|&(v, w)| w * f(sites[v])).sum()
+                    };
+                    assert!((sum - 1.0).abs() < 1e-9);
+                    assert!((at(&|p| p.x) - q.x).abs() < 1e-9);
+                    assert!((at(&|p| p.y) - q.y).abs() < 1e-9);
+                    assert!((at(&linear) - linear(q)).abs() < 1e-9, "{q:?}");
+                }
+            }
+            assert!(inside > 1000);
+        }
+    }
+
+    #[test]
+    fn sites_keep_their_values() {
+        let sites = random_points(100, 4, 100.0);
+        let flat: Vec<f32> = sites
+            .iter()
+            .flat_map(|p| [p.x as f32, p.y as f32, 0.0, 0.0])
+            .collect();
+        // Two components per site: a linear function and a random one.
+        let mut s = 17u32;
+        let values: Vec<f32> = sites
+            .iter()
+            .flat_map(|&p| [linear(p) as f32, crate::rand::frand01(&mut s)])
+            .collect();
+        let mut tri = Triangulation::new(&sites);
+        for (i, &p) in sites.iter().enumerate() {
+            for weights in [tri.linear_weights(p), tri.sibson_weights(p)] {
+                let weights = weights.unwrap();
+                let own: f64 = weights
+                    .iter()
+                    .filter(|&&(v, _)| v == i)
+                    .map(|&(_, w)| w)
+                    .sum();
+                assert!((own - 1.0).abs() < 1e-12, "site {i}: {weights:?}");
+            }
+        }
+        let queries: Vec<f32> = sites
+            .iter()
+            .flat_map(|p| [p.x as f32, p.y as f32])
+            .collect();
+        for method in [Interpolation::Linear, Interpolation::NaturalNeighbor] {
+            let out = VoronoiTests::interpolate(&flat, &values, &queries, method);
+            assert_eq!(out.len(), values.len());
+            for (k, (&got, &want)) in out.iter().zip(&values).enumerate() {
+                assert!(
+                    (got - want).abs() <= 1e-4 * want.abs().max(1.0),
+                    "{method:?} {k}"
+                );
+            }
+        }
+
+        // Away from the sites a linear function still comes out exactly,
+        // and outside the hull there is nothing to interpolate.
+        let queries = [50.0, 50.0, -1.0, 50.0];
+        for method in [Interpolation::Linear, Interpolation::NaturalNeighbor] {
+            let out = VoronoiTests::interpolate(&flat, &values, &queries, method);
+            assert!((out[0] - linear(pt(50.0, 50.0)) as f32).abs() < 1e-3);
+            assert!(out[2].is_nan() && out[3].is_nan());
+        }
+    }
+}
@@ -0,0 +1,303 @@
+//! Lloyd relaxation towards a centroidal Voronoi tessellation.
+//!
+//! Every iteration triangulates the sites, clips their Voronoi cells to the
+//! bounds and moves each site to the centroid of its cell. The weighted
+//! variant uses the centroid under a density given on a regular grid, which
+//! packs sites more tightly where the density is high.
+
+use super::cells::{clipped_cells, polygon_centroid, rect_polygon};
+use super::degenerate::{TriangulationError, TriangulationErrorKind};
+use super::triangulation::Triangulation;
+use super::{Pt, VoronoiTests, read_points};
+use wasm_bindgen::prelude::*;
+
+/// Relaxed sites and the convergence history.
+#[wasm_bindgen(getter_with_clone)]
+pub struct LloydResult {
+    /// Sites in the input layout `[x,y,vx,vy,...]`; velocities are kept.
+    #[wasm_bindgen(readonly)]
+    pub points: Vec<f32>,
+    /// Root-mean-square site displacement of every iteration.
+    #[wasm_bindgen(readonly)]
+    pub residuals: Vec<f32>,
+}
+
+/// A scalar density sampled on a `width x height` grid spanning the bounds.
+pub(crate) struct DensityGrid<'a> {
+    pub(crate) values: &'a [f32],
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) min: Pt,
+    pub(crate) max: Pt,
+}
+
+impl DensityGrid<'_> {
+    /// Bilinearly interpolated density at `p`, clamped to the grid.
+    fn sample(&self, p: Pt) -> f64 {
+        if self.width == 0 || self.height == 0 {
+            return 1.0;
+        }
+        // Position in grid units; a zero-width span has only its first column.
+        let along = |p: f64, min: f64, max: f64, n: usize| {
+            let t = if max > min {
+                (p - min) / (max - min)
+            } else {
+                0.0
+            };
+            (t * (n - 1) as f64).clamp(0.0, (n - 1) as f64)
+        };
+        let gx = along(p.x, self.min.x, self.max.x, self.width);
+        let gy = along(p.y, self.min.y, self.max.y, self.height);
+        let x0 = gx.floor() as usize;
+        let y0 = gy.floor() as usize;
+        let x1 = (x0 + 1).min(self.width - 1);
+        let y1 = (y0 + 1).min(self.height - 1);
+        let fx = gx - x0 as f64;
+        let fy = gy - y0 as f64;
+        let at = |x: usize, y: usize| self.values[y * self.width + x].max(0.0) as f64;
+        let top = at(x0, y0) * (1.0 - fx) + at(x1, y0) * fx;
+        let bottom = at(x0, y1) * (1.0 - fx) + at(x1, y1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    /// Size of one grid cell along its longer side.
+    fn cell_size(&self) -> f64 {
+        let sx = (self.max.x - self.min.x) / self.width.max(1) as f64;
+        let sy = (self.max.y - self.min.y) / self.height.max(1) as f64;
+        sx.max(sy)
+    }
+
+    /// Check that `values` holds the `width x height` samples.
+    pub(crate) fn check(&self) -> Result<(), TriangulationError> {
+        match self.width.checked_mul(self.height) {
+            Some(n) if n > 0 && n <= self.values.len() => Ok(()),
+            _ => Err(TriangulationError::new(
+                TriangulationErrorKind::InvalidGrid,
+                format!(
+                    "a {} x {} density grid needs more than the {} values given",
+                    self.width,
+                    self.height,
+                    self.values.len()
+                ),
+            )),
+        }
+    }
+
+    /// Density-weighted centroid of a convex polygon. The polygon is fanned
+    /// into triangles, which are subdivided finely enough to resolve the grid.
+    fn centroid(&self, poly: &[Pt]) -> Option<Pt> {
+        let origin = *poly.first()?;
+        let cell = self.cell_size();
+        let mut mass = 0.0;
+        let mut mx = 0.0;
+        let mut my = 0.0;
+        for k in 1..poly.len().saturating_sub(1) {
+            let (a, b, c) = (origin, poly[k], poly[k + 1]);
+            let longest = a
+                .sub(b)
+                .len2()
+                .max(b.sub(c).len2())
+                .max(c.sub(a).len2())
+                .sqrt();
+            // Also 1 for degenerate bounds, where `cell` is 0.
+            let s = if cell > 0.0 {
+                ((longest / cell).ceil() as usize).clamp(1, 32)
+            } else {
+                1
+            };
+            let u = b.sub(a).mul(1.0 / s as f64);
+            let v = c.sub(a).mul(1.0 / s as f64);
+            let area = 0.5 * (u.x * v.y - u.y * v.x).abs();
+            for i in 0..s {
+                for j in 0..s - i {
+                    let base = a.add(u.mul(i as f64)).add(v.mul(j as f64));
+                    // Upward sub-triangle, and the downward one next to it.
+                    let mut centers = [Some(base.add(u.add(v).mul(1.0 / 3.0))), None];
+                    if i + j + 1 < s {
+                        centers[1] = Some(base.add(u.add(v).mul(2.0 / 3.0)));
+                    }
+                    for center in centers.into_iter().flatten() {
+                        let w = self.sample(center) * area;
+                        mass += w;
+                        mx += center.x * w;
+                        my += center.y * w;
+                    }
+                }
+            }
+        }
+        if mass <= 0.0 {
+            return polygon_centroid(poly);
+        }
+        Some(Pt {
+            x: mx / mass,
+            y: my / mass,
+        })
+    }
+}
+
+/// Run `iterations` Lloyd steps on `points` within the convex polygon `clip`.
+/// Returns the RMS displacement of every iteration. Sites whose cell is
+/// empty (duplicates, or sites far outside the bounds) stay where they are.
+pub(crate) fn lloyd_relax(
+    points: &mut [Pt],
+    iterations: usize,
+    clip: &[Pt],
+    density: Option<&DensityGrid>,
+) -> Vec<f64> {
+    let mut residuals = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let tri = Triangulation::new(points);
+        let cells = clipped_cells(&tri, clip);
+        let mut moved2 = 0.0;
+        for (p, cell) in points.iter_mut().zip(&cells) {
+            let target = match density {
+                Some(d) => d.centroid(cell),
+                None => polygon_centroid(cell),
+            };
+            if let Some(c) = target {
+                moved2 += c.sub(*p).len2();
+                *p = c;
+            }
+        }
+        residuals.push((moved2 / points.len().max(1) as f64).sqrt());
+    }
+    residuals
+}
+
+fn relax_flat(
+    points_flat: &[f32],
+    iterations: u32,
+    clip: &[Pt],
+    density: Option<&DensityGrid>,
+) -> LloydResult {
+    let mut pts = read_points(points_flat);
+    let residuals = lloyd_relax(&mut pts, iterations as usize, clip, density);
+    let mut out = points_flat.to_vec();
+    for (i, p) in pts.iter().enumerate() {
+        out[i * 4] = p.x as f32;
+        out[i * 4 + 1] = p.y as f32;
+    }
+    LloydResult {
+        points: out,
+        residuals: residuals.into_iter().map(|r| r as f32).collect(),
+    }
+}
+
+#[wasm_bindgen]
+impl VoronoiTests {
+    /// Move every site to the centroid of its Voronoi cell, clipped to the
+    /// rectangle `[min_x, max_x] x [min_y, max_y]`, `iterations` times.
+    /// Input and output layout: [x,y,vx,vy,...]
+    pub fn lloyd_relax(
+        points_flat: &[f32],
+        iterations: u32,
+        min_x: f32,
+        min_y: f32,
+        max_x: f32,
+        max_y: f32,
+    ) -> LloydResult {
+        let clip = rect_polygon(min_x as f64, min_y as f64, max_x as f64, max_y as f64);
+        relax_flat(points_flat, iterations, &clip, None)
+    }
+
+    /// Weighted Lloyd relaxation: sites move to the centroid of their cell
+    /// under the density `density` (row-major, `grid_width x grid_height`
+    /// samples spanning the bounds, bilinearly interpolated). Fails if
+    /// `density` has fewer values than the grid.
+    #[allow(clippy::too_many_arguments)]
+    pub fn lloyd_relax_weighted(
+        points_flat: &[f32],
+        iterations: u32,
+        min_x: f32,
+        min_y: f32,
+        max_x: f32,
+        max_y: f32,
+        density: &[f32],
+        grid_width: usize,
+        grid_height: usize,
+    ) -> Result<LloydResult, TriangulationError> {
+        let clip = rect_polygon(min_x as f64, min_y as f64, max_x as f64, max_y as f64);
+        let grid = DensityGrid {
+            values: density,
+            width: grid_width,
+            height: grid_height,
+            min: clip[0],
+            max: clip[2],
+        };
+        grid.check()?;
+        Ok(relax_flat(points_flat, iterations, &clip, Some(&grid)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voronoi::test_util::random_points;
+
+    #[test]
+    fn relaxation_converges() {
+        let clip = rect_polygon(0.0, 0.0, 100.0, 100.0);
+        let mut points = random_points(200, 5, 100.0);
+        let residuals = lloyd_relax(&mut points, 40, &clip, None);
+        assert!(residuals[39] < residuals[0] * 0.05, "{residuals:?}");
+        assert!(residuals.windows(10).all(|w| w[9] <= w[0]));
+        assert!(points.iter().all(|p| (0.0..=100.0).contains(&p.x)));
+    }
+
+    #[test]
+    fn density_pulls_sites_in() {
+        // Nine times the density on the left half.
+        let values = [9.0, 9.0, 1.0, 1.0];
+        let grid = DensityGrid {
+            values: &values,
+            width: 4,
+            height: 1,
+            min: Pt { x: 0.0, y: 0.0 },
+            max: Pt { x: 100.0, y: 100.0 },
+        };
+        grid.check().unwrap();
+        let clip = rect_polygon(0.0, 0.0, 100.0, 100.0);
+
+        // A single site goes to the density centroid: the density falls
+        // linearly from 9 at x = 33 to 1 at x = 67.
+        let mut single = [Pt { x: 50.0, y: 50.0 }];
+        lloyd_relax(&mut single, 1, &clip, Some(&grid));
+        assert!((single[0].x - 30.0).abs() < 1e-6 && (single[0].y - 50.0).abs() < 1e-6);
+
+        // Two sites share the mass, so their bisector moves to the dense side.
+        let mut pair = [Pt { x: 25.0, y: 50.0 }, Pt { x: 75.0, y: 50.0 }];
+        lloyd_relax(&mut pair, 50, &clip, Some(&grid));
+        let bisector = (pair[0].x + pair[1].x) / 2.0;
+        assert!(bisector < 45.0, "{pair:?}");
+    }
+
+    #[test]
+    fn bad_density_grids() {
+        let values = [1.0; 6];
+        let grid = |width, height| DensityGrid {
+            values: &values,
+            width,
+            height,
+            min: Pt { x: 0.0, y: 0.0 },
+            max: Pt { x: 10.0, y: 10.0 },
+        };
+        assert!(grid(3, 2).check().is_ok());
+        for (w, h) in [(4, 2), (0, 2), (usize::MAX, 2)] {
+            let err = grid(w, h).check().unwrap_err();
+            assert_eq!(err.kind, TriangulationErrorKind::InvalidGrid);
+        }
+
+        // Zero-width bounds give no cells, and no NaN.
+        let flat = grid(3, 2);
+        let collapsed = DensityGrid {
+            max: Pt { x: 0.0, y: 10.0 },
+            ..flat
+        };
+        let clip = rect_polygon(0.0, 0.0, 0.0, 10.0);
+        let mut points = random_points(20, 2, 100.0);
+        let residuals = lloyd_relax(&mut points, 2, &clip, Some(&collapsed));
+        assert!(residuals.iter().all(|r| r.is_finite()));
+        assert!(points.iter().all(|p| p.x.is_finite() && p.y.is_finite()));
+        assert!(collapsed.sample(Pt { x: 0.0, y: 5.0 }).is_finite());
+    }
+}
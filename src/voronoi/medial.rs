@@ -0,0 +1,277 @@
+//! Medial axis of polygons from the Voronoi diagram of their sampled
+//! outlines.
+//!
+//! The Voronoi vertices of densely sampled outlines that lie inside the
+//! shape approximate the centers of its maximal inscribed circles, with the
+//! circumradius as the circle's radius. Between two samples close to each
+//! other along the same outline the Voronoi edges only follow the sampling
+//! and the small bumps of the outline, so every edge is weighted by the
+//! length of the outline between the two samples it separates (the shorter
+//! way round; edges between different outlines always count) and dropped
+//! below a threshold. Twice the spacing removes the sampling noise; larger
+//! values also trim the branches into convex corners and small features.
+//!
+//! Outlines can be given in either orientation; a point is inside if it is
+//! enclosed by an odd number of them, so holes are outlines inside others.
+
+use super::triangulation::{NONE, Triangulation, next};
+use super::{Pt, VoronoiTests, circumcircle, read_xy};
+use wasm_bindgen::prelude::*;
+
+/// Upper bound on the number of samples (besides the corners) of
+/// [`sample_outlines`]; smaller spacings are raised to fit.
+const MAX_SAMPLES: f64 = (1 << 20) as f64;
+
+/// The skeleton of a shape as a graph. Node `i` is at `nodes[2i..2i + 2]`
+/// and is the center of an inscribed circle of radius `radii[i]`; edges
+/// are node index pairs `[a0,b0,a1,b1,...]`.
+#[wasm_bindgen(getter_with_clone)]
+pub struct MedialAxis {
+    #[wasm_bindgen(readonly)]
+    pub nodes: Vec<f32>,
+    #[wasm_bindgen(readonly)]
+    pub radii: Vec<f32>,
+    #[wasm_bindgen(readonly)]
+    pub edges: Vec<u32>,
+}
+
+/// Points on closed outlines, each with the outline it belongs to and its
+/// distance along that outline from the outline's first corner.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OutlineSamples {
+    pub(crate) points: Vec<Pt>,
+    pub(crate) outline: Vec<usize>,
+    pub(crate) arc: Vec<f64>,
+    /// Perimeter of every outline.
+    pub(crate) lengths: Vec<f64>,
+    /// Largest distance between consecutive samples.
+    pub(crate) max_gap: f64,
+}
+
+impl OutlineSamples {
+    /// Length of the outline between samples `i` and `j` the shorter way
+    /// round, infinite if they are on different outlines.
+    fn separation(&self, i: usize, j: usize) -> f64 {
+        let k = self.outline[i];
+        if k != self.outline[j] {
+            return f64::INFINITY;
+        }
+        let d = (self.arc[i] - self.arc[j]).abs();
+        d.min(self.lengths[k] - d)
+    }
+}
+
+/// Polygon `i` has the points `offsets[i]..offsets[i + 1]` of
+/// `coords` = [x0,y0,x1,y1,...], the layout of `VoronoiCells`.
+pub(crate) fn read_outlines(coords: &[f32], offsets: &[u32]) -> Vec<Vec<Pt>> {
+    let points = read_xy(coords);
+    offsets
+        .windows(2)
+        .filter_map(|w| points.get(w[0] as usize..w[1] as usize))
+        .filter(|poly| !poly.is_empty())
+        .map(<[Pt]>::to_vec)
+        .collect()
+}
+
+/// Sample closed polygons so that neighbouring samples are at most
+/// `spacing` apart; every corner is a sample. The spacing is raised if the
+/// outlines would take more than [`MAX_SAMPLES`].
+pub(crate) fn sample_outlines(polygons: &[Vec<Pt>], spacing: f64) -> OutlineSamples {
+    let perimeter: f64 = polygons
+        .iter()
+        .flat_map(|poly| {
+            (0..poly.len()).map(|k| poly[(k + 1) % poly.len()].sub(poly[k]).len2().sqrt())
+        })
+        .filter(|len| len.is_finite())
+        .sum();
+    let spacing = spacing.max(perimeter / MAX_SAMPLES);
+    let mut out = OutlineSamples::default();
+    for (i, poly) in polygons.iter().enumerate() {
+        let mut arc = 0.0;
+        for (k, &a) in poly.iter().enumerate() {
+            let b = poly[(k + 1) % poly.len()];
+            let len = b.sub(a).len2().sqrt();
+            if !len.is_finite() {
+                continue;
+            }
+            let pieces = if spacing > 0.0 {
+                (len / spacing).ceil().max(1.0)
+            } else {
+                1.0
+            };
+            out.max_gap = out.max_gap.max(len / pieces);
+            for s in 0..pieces as usize {
+                let t = s as f64 / pieces;
+                out.points.push(a.add(b.sub(a).mul(t)));
+                out.outline.push(i);
+                out.arc.push(arc + len * t);
+            }
+            arc += len;
+        }
+        out.lengths.push(arc);
+    }
+    out
+}
+
+/// `true` if `p` is enclosed by an odd number of the polygons.
+pub(crate) fn inside(polygons: &[Vec<Pt>], p: Pt) -> bool {
+    let mut odd = false;
+    for poly in polygons {
+        for (k, &a) in poly.iter().enumerate() {
+            let b = poly[(k + 1) % poly.len()];
+            if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+                odd = !odd;
+            }
+        }
+    }
+    odd
+}
+
+pub(crate) fn medial_axis(polygons: &[Vec<Pt>], spacing: f64, min_significance: f64) -> MedialAxis {
+    let samples = sample_outlines(polygons, spacing);
+    let points = &samples.points;
+    let tri = Triangulation::new(points);
+
+    // Interior Voronoi vertices; only those on a kept edge get an index.
+    let mut center = vec![None; tri.tri_count()];
+    for (t, c) in center.iter_mut().enumerate() {
+        if !tri.is_solid(t) {
+            continue;
+        }
+        let [a, b, c_] = tri.tri_verts(t);
+        *c = circumcircle(points[a], points[b], points[c_])
+            .filter(|cc| inside(polygons, cc.c))
+            .map(|cc| (cc.c, cc.r2.sqrt()));
+    }
+
+    let mut axis = MedialAxis {
+        nodes: Vec::new(),
+        radii: Vec::new(),
+        edges: Vec::new(),
+    };
+    let mut index = vec![NONE; tri.tri_count()];
+    for (e, &twin) in tri.twins.iter().enumerate() {
+        if twin == NONE || twin < e {
+            continue;
+        }
+        let (s, t) = (e / 3, twin / 3);
+        if center[s].is_none() || center[t].is_none() {
+            continue;
+        }
+        if samples.separation(tri.verts[e], tri.verts[next(e)]) < min_significance {
+            continue;
+        }
+        for u in [s, t] {
+            if index[u] == NONE {
+                let (c, r) = center[u].expect("interior vertex");
+                index[u] = axis.radii.len();
+                axis.nodes.extend([c.x as f32, c.y as f32]);
+                axis.radii.push(r as f32);
+            }
+            axis.edges.push(index[u] as u32);
+        }
+    }
+    axis
+}
+
+#[wasm_bindgen]
+impl VoronoiTests {
+    /// Medial axis of polygons, whose outlines are sampled every `spacing`,
+    /// or coarser if that would take more than about a million samples.
+    /// Polygon `i` has the points `offsets[i]..offsets[i + 1]` of
+    /// `coords` = [x0,y0,x1,y1,...]; polygons inside others are holes.
+    /// Edges separating samples less than `min_significance` apart along
+    /// the outline are left out.
+    pub fn medial_axis(
+        coords: &[f32],
+        offsets: &[u32],
+        spacing: f32,
+        min_significance: f32,
+    ) -> MedialAxis {
+        let polygons = read_outlines(coords, offsets);
+        medial_axis(&polygons, spacing as f64, min_significance as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voronoi::test_util::pt;
+
+    #[test]
+    fn skeleton_of_a_frame() {
+        // A 100 x 40 rectangle with a 60 x 20 hole leaves bars 10 wide.
+        let polygons = [
+            vec![pt(0.0, 0.0), pt(100.0, 0.0), pt(100.0, 40.0), pt(0.0, 40.0)],
+            vec![
+                pt(20.0, 10.0),
+                pt(80.0, 10.0),
+                pt(80.0, 30.0),
+                pt(20.0, 30.0),
+            ],
+        ];
+        let axis = medial_axis(&polygons, 0.5, 1.0);
+        let nodes: Vec<Pt> = axis
+            .nodes
+            .chunks(2)
+            .map(|c| pt(c[0] as f64, c[1] as f64))
+            .collect();
+        assert!(!axis.edges.is_empty());
+
+        // Every node is inside the frame and its radius is its distance to
+        // the outline, up to the sampling.
+        for (p, &r) in nodes.iter().zip(&axis.radii) {
+            assert!(inside(&polygons, *p));
+            let wall = [p.x, 100.0 - p.x, p.y, 40.0 - p.y]
+                .into_iter()
+                .chain((p.x > 20.0 && p.x < 80.0).then(|| (p.y - 30.0).max(10.0 - p.y)))
+                .chain((p.y > 10.0 && p.y < 30.0).then(|| (p.x - 80.0).max(20.0 - p.x)))
+                .fold(f64::INFINITY, f64::min);
+            assert!((r as f64 - wall).abs() < 0.3, "{p:?}: {r} vs {wall}");
+        }
+
+        // The middle of the top bar is on the skeleton, 5 from either side.
+        let (k, _) = nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| (p.x - 50.0).abs() < 1.0)
+            .min_by(|(_, p), (_, q)| (p.y - 35.0).abs().total_cmp(&(q.y - 35.0).abs()))
+            .unwrap();
+        assert!((nodes[k].y - 35.0).abs() < 0.1);
+        assert!((axis.radii[k] - 5.0).abs() < 0.1);
+
+        // A loop around the hole: all nodes connected, no node of degree 1
+        // left once the corner branches are trimmed away.
+        let trimmed = medial_axis(&polygons, 0.5, 30.0);
+        let mut degree = vec![0; trimmed.radii.len()];
+        for &v in &trimmed.edges {
+            degree[v as usize] += 1;
+        }
+        let n = degree.len();
+        assert!(n > 0);
+        let mut seen = vec![false; n];
+        let mut stack = vec![0];
+        while let Some(u) = stack.pop() {
+            if std::mem::replace(&mut seen[u], true) {
+                continue;
+            }
+            for e in trimmed.edges.chunks(2) {
+                let (a, b) = (e[0] as usize, e[1] as usize);
+                if a == u || b == u {
+                    stack.push(a + b - u);
+                }
+            }
+        }
+        assert!(seen.iter().all(|&s| s));
+        assert!(degree.iter().all(|&d| d >= 2));
+    }
+
+    #[test]
+    fn sample_count_is_bounded() {
+        let square = [vec![pt(0.0, 0.0), pt(1e4, 0.0), pt(1e4, 1e4), pt(0.0, 1e4)]];
+        let samples = sample_outlines(&square, 1e-6);
+        assert!(samples.points.len() <= MAX_SAMPLES as usize + 4);
+        assert!(samples.max_gap >= 4e4 / MAX_SAMPLES);
+        assert_eq!(sample_outlines(&square, 10.0).points.len(), 4000);
+    }
+}
@@ -0,0 +1,349 @@
+#![allow(dead_code)]
+
+mod cdt;
+mod cells;
+mod degenerate;
+mod delaunay;
+mod dynamics;
+pub mod export;
+mod graphs;
+mod hull;
+mod interpolate;
+mod lloyd;
+mod medial;
+mod periodic;
+mod predicates;
+mod query;
+mod raster;
+mod refine;
+mod render;
+mod roadmap;
+mod spherical;
+mod stats;
+mod triangulation;
+mod validate;
+mod weighted;
+
+use crate::rand::frand01;
+use degenerate::{DelaunayResult, TriangulationError, triangulate_merged};
+use predicates::orient2d;
+use triangulation::{NONE, Triangulation};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct VoronoiTests;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Pt {
+    x: f64,
+    y: f64,
+}
+
+impl Pt {
+    #[inline]
+    fn sub(self, o: Pt) -> Pt {
+        Pt {
+            x: self.x - o.x,
+            y: self.y - o.y,
+        }
+    }
+
+    #[inline]
+    fn add(self, o: Pt) -> Pt {
+        Pt {
+            x: self.x + o.x,
+            y: self.y + o.y,
+        }
+    }
+
+    #[inline]
+    fn mul(self, s: f64) -> Pt {
+        Pt {
+            x: self.x * s,
+            y: self.y * s,
+        }
+    }
+
+    #[inline]
+    fn dot(self, o: Pt) -> f64 {
+        self.x * o.x + self.y * o.y
+    }
+
+    #[inline]
+    fn len2(self) -> f64 {
+        self.dot(self)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Circumcircle {
+    c: Pt,
+    r2: f64,
+}
+
+fn circumcircle(a: Pt, b: Pt, c: Pt) -> Option<Circumcircle> {
+    // Exactly collinear triangles have no circumcircle; everything else is
+    // solved relative to `a` in f64 to keep cancellation small.
+    if orient2d(a, b, c) == 0.0 {
+        return None;
+    }
+    let ab = b.sub(a);
+    let ac = c.sub(a);
+    let d = 2.0 * (ab.x * ac.y - ab.y * ac.x);
+    let b2 = ab.len2();
+    let c2 = ac.len2();
+    let offset = Pt {
+        x: (ac.y * b2 - ab.y * c2) / d,
+        y: (ab.x * c2 - ac.x * b2) / d,
+    };
+    Some(Circumcircle {
+        c: a.add(offset),
+        r2: offset.len2(),
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Edge {
+    a: usize,
+    b: usize,
+}
+
+impl Edge {
+    fn new(i: usize, j: usize) -> Self {
+        if i < j {
+            Edge { a: i, b: j }
+        } else {
+            Edge { a: j, b: i }
+        }
+    }
+}
+
+/// What a forced edge of a constrained triangulation stands for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Constraint {
+    /// A required edge that does not separate regions.
+    Segment,
+    /// Part of a polygon boundary: crossing it toggles between inside and
+    /// outside (even-odd rule).
+    Boundary,
+}
+
+impl Constraint {
+    /// Kind of an edge that is forced by both `self` and `other`. Two
+    /// boundaries on the same edge cancel out, but the edge stays forced.
+    fn merge(self, other: Constraint) -> Constraint {
+        match (self, other) {
+            (Constraint::Boundary, Constraint::Boundary) => Constraint::Segment,
+            (Constraint::Segment, Constraint::Segment) => Constraint::Segment,
+            _ => Constraint::Boundary,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Tri {
+    a: usize,
+    b: usize,
+    c: usize,
+}
+
+impl Tri {
+    fn edges(&self) -> [Edge; 3] {
+        [
+            Edge::new(self.a, self.b),
+            Edge::new(self.b, self.c),
+            Edge::new(self.c, self.a),
+        ]
+    }
+}
+
+fn compute_voronoi_edges(tri: &Triangulation) -> Vec<(Pt, Pt)> {
+    let points = &tri.points;
+
+    // Precompute circumcenters (fallback to centroid if degenerate)
+    let mut centers: Vec<Pt> = Vec::with_capacity(tri.tri_count());
+    for t in 0..tri.tri_count() {
+        if !tri.is_solid(t) {
+            centers.push(Pt { x: 0.0, y: 0.0 });
+            continue;
+        }
+        let [a, b, c] = tri.tri_verts(t);
+        if let Some(cc) = circumcircle(points[a], points[b], points[c]) {
+            centers.push(cc.c);
+        } else {
+            let cen = Pt {
+                x: (points[a].x + points[b].x + points[c].x) / 3.0,
+                y: (points[a].y + points[b].y + points[c].y) / 3.0,
+            };
+            centers.push(cen);
+        }
+    }
+
+    // Every interior Delaunay edge (visited once via its lower half-edge)
+    // yields the Voronoi edge between the two adjacent circumcenters.
+    let mut segments: Vec<(Pt, Pt)> = Vec::new();
+    for (e, &twin) in tri.twins.iter().enumerate() {
+        if twin == NONE || twin < e {
+            continue;
+        }
+        let (t0, t1) = (e / 3, twin / 3);
+        if tri.is_solid(t0) && tri.is_solid(t1) {
+            segments.push((centers[t0], centers[t1]));
+        }
+    }
+    segments
+}
+
+/// Vertex indices of all triangles as triplets.
+fn triangle_indices(tri: &Triangulation) -> Vec<u32> {
+    let tris = tri.triangles();
+    let mut out = Vec::with_capacity(tris.len() * 3);
+    for t in tris {
+        out.push(t.a as u32);
+        out.push(t.b as u32);
+        out.push(t.c as u32);
+    }
+    out
+}
+
+/// Voronoi edges as line segments [x1,y1,x2,y2,...]
+fn voronoi_edges_flat(tri: &Triangulation) -> Vec<f32> {
+    let segs = compute_voronoi_edges(tri);
+    let mut out = Vec::with_capacity(segs.len() * 4);
+    for (a, b) in segs {
+        out.push(a.x as f32);
+        out.push(a.y as f32);
+        out.push(b.x as f32);
+        out.push(b.y as f32);
+    }
+    out
+}
+
+/// Read site positions from the `[x,y,vx,vy,...]` layout used by the demos.
+fn read_points(points_flat: &[f32]) -> Vec<Pt> {
+    points_flat
+        .chunks(4)
+        .filter(|c| c.len() >= 2)
+        .map(|c| Pt {
+            x: c[0] as f64,
+            y: c[1] as f64,
+        })
+        .collect()
+}
+
+/// Read plain coordinates from the `[x0,y0,x1,y1,...]` layout used for
+/// polygons and shapes.
+fn read_xy(flat: &[f32]) -> Vec<Pt> {
+    flat.chunks_exact(2)
+        .map(|c| Pt {
+            x: c[0] as f64,
+            y: c[1] as f64,
+        })
+        .collect()
+}
+
+#[wasm_bindgen]
+impl VoronoiTests {
+    /// Create seeded points with small velocities. Layout: [x,y,vx,vy,...]
+    pub fn voronoi_create_points(
+        count: usize,
+        width: f32,
+        height: f32,
+        seed: u32,
+        speed: f32,
+    ) -> Vec<f32> {
+        let mut s = if seed == 0 { 1 } else { seed };
+        // We always append 4 fixed corner points with zero velocity at the end
+        // of the buffer. The caller-provided `count` represents the number of
+        // moving points. Total returned points = count (moving) + 4 (fixed).
+        let mut out = Vec::with_capacity((count + 4) * 4);
+        for i in 0..count {
+            // Positions within bounds
+            let rx = frand01(&mut s);
+            let ry = frand01(&mut s);
+            let x = rx * width;
+            let y = ry * height;
+
+            // Angle and speed
+            let ang = frand01(&mut s) * std::f32::consts::TAU;
+            let spd = (0.2 + 0.8 * frand01(&mut s)) * speed; // small random velocity around provided speed
+            let vx = ang.cos() * spd;
+            let vy = ang.sin() * spd;
+            out.push(x);
+            out.push(y);
+            out.push(vx);
+            out.push(vy);
+
+            // decorrelate with index to keep determinism but variety
+            s ^= (i as u32).wrapping_mul(0x9E37_79B9);
+        }
+
+        // Append four fixed corner points (vx = vy = 0). These should never be removed.
+        // Order: (0,0), (width,0), (0,height), (width,height)
+        let corners = [
+            (0.0f32, 0.0f32),
+            (width, 0.0f32),
+            (0.0f32, height),
+            (width, height),
+        ];
+        for &(x, y) in &corners {
+            out.push(x);
+            out.push(y);
+            out.push(0.0);
+            out.push(0.0);
+        }
+        out
+    }
+
+    /// Integrate positions; if a point leaves the viewport, respawn at a deterministic random location with small random velocity
+    pub fn voronoi_step_points(points: &[f32], width: f32, height: f32, dt: f32) -> Vec<f32> {
+        dynamics::step_points(points, width, height, dt, &Default::default(), 0)
+    }
+
+    /// Compute Delaunay triangulation indices (triplets) of the points
+    /// merged within `epsilon`, like [`VoronoiTests::delaunay`] but without
+    /// the Voronoi edges.
+    pub fn delaunay_indices(
+        points_flat: &[f32],
+        epsilon: f32,
+    ) -> Result<DelaunayResult, TriangulationError> {
+        let (tri, mut result) = triangulate_merged(points_flat, epsilon)?;
+        result.triangles = triangle_indices(&tri);
+        Ok(result)
+    }
+
+    /// Compute Voronoi edges as line segments [x1,y1,x2,y2,...] of the
+    /// points merged within `epsilon`, like [`VoronoiTests::delaunay`] but
+    /// without the triangles.
+    pub fn voronoi_edges(
+        points_flat: &[f32],
+        epsilon: f32,
+    ) -> Result<DelaunayResult, TriangulationError> {
+        let (tri, mut result) = triangulate_merged(points_flat, epsilon)?;
+        result.voronoi_edges = voronoi_edges_flat(&tri);
+        Ok(result)
+    }
+}
+
+/// Fixtures shared by the tests of the submodules.
+#[cfg(test)]
+pub(crate) mod test_util {
+    use super::Pt;
+
+    pub(crate) use super::medial::inside;
+
+    pub(crate) fn pt(x: f64, y: f64) -> Pt {
+        Pt { x, y }
+    }
+
+    /// `n` pseudo-random points in `[0, scale)²`.
+    pub(crate) fn random_points(n: usize, seed: u32, scale: f64) -> Vec<Pt> {
+        let mut s = seed;
+        (0..n)
+            .map(|_| {
+                let x = crate::rand::frand01(&mut s) as f64 * scale;
+                pt(x, crate::rand::frand01(&mut s) as f64 * scale)
+            })
+            .collect()
+    }
+}
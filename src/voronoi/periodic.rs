@@ -0,0 +1,246 @@
+//! Periodic (toroidal) Delaunay triangulation and Voronoi cells.
+//!
+//! The domain `[0, width) x [0, height)` wraps on both axes. The sites are
+//! wrapped into it and triangulated together with their eight translates
+//! by one period, which covers the neighbourhood of every original site.
+//! The cover is only trusted around the central copy: a triangle is
+//! reported once, by the translate that has its smallest site (by index,
+//! then by shift) in the central copy, and cells are cut out for the
+//! central sites only.
+//!
+//! Cells are returned unwrapped around their site, so cells near the
+//! domain edge stick out of it; shifted by the periods they tile the plane
+//! seamlessly.
+
+use super::cells::{VoronoiCells, clip_bisector, rect_polygon};
+use super::triangulation::{GHOST, Triangulation};
+use super::{Pt, VoronoiTests, read_points};
+use wasm_bindgen::prelude::*;
+
+/// Period shifts of the copies; the central copy comes first, so that its
+/// points win over their translates wherever the order matters.
+const SHIFTS: [(i32, i32); 9] = [
+    (0, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Periodic triangles. Corner `k` is site `triangles[k]` shifted by
+/// `(shifts[2k] * width, shifts[2k + 1] * height)`; every triangle is
+/// counter-clockwise and appears once.
+#[wasm_bindgen(getter_with_clone)]
+pub struct PeriodicTriangulation {
+    #[wasm_bindgen(readonly)]
+    pub triangles: Vec<u32>,
+    #[wasm_bindgen(readonly)]
+    pub shifts: Vec<i32>,
+}
+
+/// The sites wrapped into the domain and their triangulated 3 x 3 cover.
+pub(crate) struct PeriodicCover {
+    pub(crate) tri: Triangulation,
+    pub(crate) sites: usize,
+}
+
+impl PeriodicCover {
+    pub(crate) fn new(points: &[Pt], width: f64, height: f64) -> Self {
+        let wrapped: Vec<Pt> = points
+            .iter()
+            .map(|p| Pt {
+                x: p.x.rem_euclid(width),
+                y: p.y.rem_euclid(height),
+            })
+            .collect();
+        let cover: Vec<Pt> = SHIFTS
+            .iter()
+            .flat_map(|&(sx, sy)| {
+                let shift = Pt {
+                    x: sx as f64 * width,
+                    y: sy as f64 * height,
+                };
+                wrapped.iter().map(move |&p| p.add(shift))
+            })
+            .collect();
+        Self {
+            tri: Triangulation::new(&cover),
+            sites: points.len(),
+        }
+    }
+
+    /// Site and period shift of cover point `k`.
+    fn site(&self, k: usize) -> (usize, (i32, i32)) {
+        (k % self.sites, SHIFTS[k / self.sites])
+    }
+
+    /// Every periodic triangle once, as `(site, shift)` corners.
+    pub(crate) fn triangles(&self) -> Vec<[(usize, (i32, i32)); 3]> {
+        self.tri
+            .triangles()
+            .into_iter()
+            .map(|t| [t.a, t.b, t.c].map(|k| self.site(k)))
+            .filter(|corners| {
+                let first = corners.iter().min().expect("three corners");
+                first.1 == (0, 0)
+            })
+            .collect()
+    }
+
+    /// Voronoi cell of every site around its wrapped position. Coinciding
+    /// sites get an empty cell, except for the first one.
+    pub(crate) fn cells(&self, width: f64, height: f64) -> Vec<Vec<Pt>> {
+        let points = &self.tri.points;
+        // Any cell of a central site lies well inside the cover.
+        let bound = rect_polygon(-width, -height, 2.0 * width, 2.0 * height);
+        (0..self.sites)
+            .map(|i| {
+                if !self.tri.has_triangles() || !self.tri.is_vertex(i) {
+                    return Vec::new();
+                }
+                let mut cell = bound.clone();
+                for j in self.tri.neighbors(i).filter(|&j| j != GHOST) {
+                    cell = clip_bisector(&cell, points[i], points[j]);
+                }
+                cell
+            })
+            .collect()
+    }
+}
+
+/// The cover of the sites, or `None` for an empty input or domain.
+fn cover_for(points_flat: &[f32], width: f32, height: f32) -> Option<PeriodicCover> {
+    let points = read_points(points_flat);
+    // `!(x > 0)` also rejects NaN.
+    if points.is_empty() || !(width > 0.0 && height > 0.0) {
+        return None;
+    }
+    Some(PeriodicCover::new(&points, width as f64, height as f64))
+}
+
+#[wasm_bindgen]
+impl VoronoiTests {
+    /// Delaunay triangulation of the sites on the torus
+    /// `[0, width) x [0, height)`. Input layout: [x,y,vx,vy,...]
+    pub fn periodic_delaunay(
+        points_flat: &[f32],
+        width: f32,
+        height: f32,
+    ) -> PeriodicTriangulation {
+        let mut out = PeriodicTriangulation {
+            triangles: Vec::new(),
+            shifts: Vec::new(),
+        };
+        let Some(cover) = cover_for(points_flat, width, height) else {
+            return out;
+        };
+        for corners in cover.triangles() {
+            for (site, (sx, sy)) in corners {
+                out.triangles.push(site as u32);
+                out.shifts.extend([sx, sy]);
+            }
+        }
+        out
+    }
+
+    /// Voronoi cells of the sites on the torus `[0, width) x [0, height)`,
+    /// one per site around its position wrapped into the domain. Cells at
+    /// the domain edge extend past it; the part outside is the piece that
+    /// shows up on the opposite side. Input layout: [x,y,vx,vy,...]
+    pub fn periodic_cells(points_flat: &[f32], width: f32, height: f32) -> VoronoiCells {
+        let cells = cover_for(points_flat, width, height)
+            .map(|cover| cover.cells(width as f64, height as f64))
+            .unwrap_or_default();
+        VoronoiCells::from_polygons(&cells)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voronoi::cells::signed_area;
+    use crate::voronoi::circumcircle;
+    use crate::voronoi::predicates::orient2d;
+
+    #[test]
+    fn torus_is_covered_once() {
+        let (w, h) = (100.0, 60.0);
+        for (n, seed) in [(1, 1u32), (2, 2), (3, 3), (5, 4), (40, 5), (300, 6)] {
+            // Some sites start outside the domain and are wrapped in.
+            let mut s = seed;
+            let points: Vec<Pt> = (0..n)
+                .map(|_| Pt {
+                    x: crate::rand::frand01(&mut s) as f64 * w * 1.5 - 20.0,
+                    y: crate::rand::frand01(&mut s) as f64 * h,
+                })
+                .collect();
+            let cover = PeriodicCover::new(&points, w, h);
+            let triangles = cover.triangles();
+            assert_eq!(triangles.len(), 2 * n, "n = {n}");
+
+            let cell_area: f64 = cover.cells(w, h).iter().map(|c| signed_area(c)).sum();
+            assert!(
+                (cell_area - w * h).abs() < 1e-6 * w * h,
+                "n = {n}: {cell_area}"
+            );
+
+            let wrapped = |i: usize, (sx, sy): (i32, i32)| Pt {
+                x: points[i].x.rem_euclid(w) + sx as f64 * w,
+                y: points[i].y.rem_euclid(h) + sy as f64 * h,
+            };
+            let mut triangle_area = 0.0;
+            for t in &triangles {
+                let [a, b, c] = t.map(|(i, shift)| wrapped(i, shift));
+                let area = orient2d(a, b, c) / 2.0;
+                assert!(area > 0.0);
+                triangle_area += area;
+                // No translate of any site inside the circumcircle.
+                let cc = circumcircle(a, b, c).unwrap();
+                for i in 0..n {
+                    for sx in -2..=2 {
+                        for sy in -2..=2 {
+                            let q = wrapped(i, (sx, sy));
+                            assert!(q.sub(cc.c).len2() >= cc.r2 * (1.0 - 1e-9), "n = {n}");
+                        }
+                    }
+                }
+            }
+            assert!(
+                (triangle_area - w * h).abs() < 1e-6 * w * h,
+                "n = {n}: {triangle_area}"
+            );
+        }
+    }
+
+    #[test]
+    fn flat_output_matches_the_cover() {
+        let flat = VoronoiTests::voronoi_create_points(30, 80.0, 50.0, 3, 10.0);
+        let out = VoronoiTests::periodic_delaunay(&flat[..120], 80.0, 50.0);
+        assert_eq!(out.triangles.len(), 3 * 60);
+        assert_eq!(out.shifts.len(), 2 * out.triangles.len());
+        assert!(out.shifts.iter().all(|s| (-1..=1).contains(s)));
+        let cells = VoronoiTests::periodic_cells(&flat[..120], 80.0, 50.0);
+        assert_eq!(cells.offsets.len(), 31);
+
+        // Nothing to triangulate without sites or without a domain.
+        assert!(
+            VoronoiTests::periodic_delaunay(&[], 80.0, 50.0)
+                .triangles
+                .is_empty()
+        );
+        assert!(
+            VoronoiTests::periodic_delaunay(&flat, 0.0, 50.0)
+                .triangles
+                .is_empty()
+        );
+        assert!(
+            VoronoiTests::periodic_delaunay(&flat, f32::NAN, 50.0)
+                .triangles
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,364 @@
+//! Adaptive exact geometric predicates after Shewchuk, "Adaptive Precision
+//! Floating-Point Arithmetic and Fast Robust Geometric Predicates" (1997).
+//!
+//! Each predicate first evaluates the determinant in plain `f64` together with
+//! a forward error bound. Only if the sign is not certain the determinant is
+//! recomputed exactly with floating-point expansions, so the common case costs
+//! hardly more than the naive formula while degenerate inputs (collinear,
+//! cocircular, clustered) always get the correct sign.
+
+use super::Pt;
+
+/// Half an ulp of 1.0, i.e. the relative rounding error of one operation.
+const EPSILON: f64 = f64::EPSILON * 0.5;
+/// 2^ceil(53 / 2) + 1, used to split a double into two 26-bit halves.
+const SPLITTER: f64 = 134_217_729.0;
+const CCW_ERR_BOUND_A: f64 = (3.0 + 16.0 * EPSILON) * EPSILON;
+const ICC_ERR_BOUND_A: f64 = (10.0 + 96.0 * EPSILON) * EPSILON;
+/// Like [`ICC_ERR_BOUND_A`], with room for subtracting the weights.
+const IPW_ERR_BOUND_A: f64 = (12.0 + 128.0 * EPSILON) * EPSILON;
+
+/// Orientation of the triangle `a`, `b`, `c`: positive if counter-clockwise,
+/// negative if clockwise and exactly zero if the points are collinear.
+pub(crate) fn orient2d(a: Pt, b: Pt, c: Pt) -> f64 {
+    let detleft = (a.x - c.x) * (b.y - c.y);
+    let detright = (a.y - c.y) * (b.x - c.x);
+    let det = detleft - detright;
+
+    let detsum = if detleft > 0.0 {
+        if detright <= 0.0 {
+            return det;
+        }
+        detleft + detright
+    } else if detleft < 0.0 {
+        if detright >= 0.0 {
+            return det;
+        }
+        -detleft - detright
+    } else {
+        return det;
+    };
+
+    if det.abs() >= CCW_ERR_BOUND_A * detsum {
+        return det;
+    }
+    orient2d_exact(a, b, c)
+}
+
+/// Position of `d` relative to the circumcircle of the counter-clockwise
+/// triangle `a`, `b`, `c`: positive inside, negative outside, zero on it.
+pub(crate) fn incircle(a: Pt, b: Pt, c: Pt, d: Pt) -> f64 {
+    let adx = a.x - d.x;
+    let bdx = b.x - d.x;
+    let cdx = c.x - d.x;
+    let ady = a.y - d.y;
+    let bdy = b.y - d.y;
+    let cdy = c.y - d.y;
+
+    let bdxcdy = bdx * cdy;
+    let cdxbdy = cdx * bdy;
+    let alift = adx * adx + ady * ady;
+
+    let cdxady = cdx * ady;
+    let adxcdy = adx * cdy;
+    let blift = bdx * bdx + bdy * bdy;
+
+    let adxbdy = adx * bdy;
+    let bdxady = bdx * ady;
+    let clift = cdx * cdx + cdy * cdy;
+
+    let det = alift * (bdxcdy - cdxbdy) + blift * (cdxady - adxcdy) + clift * (adxbdy - bdxady);
+    let permanent = (bdxcdy.abs() + cdxbdy.abs()) * alift
+        + (cdxady.abs() + adxcdy.abs()) * blift
+        + (adxbdy.abs() + bdxady.abs()) * clift;
+
+    if det.abs() > ICC_ERR_BOUND_A * permanent {
+        return det;
+    }
+    incircle_exact(a, b, c, d)
+}
+
+/// Power test of the weighted point `d` against the orthogonal circle of the
+/// counter-clockwise weighted triangle `a`, `b`, `c` (each point paired with
+/// its weight, i.e. squared radius): positive if `d` is closer to it in the
+/// power distance, meaning the triangle is not regular. With all weights
+/// equal this is [`incircle`].
+pub(crate) fn inpower(a: (Pt, f64), b: (Pt, f64), c: (Pt, f64), d: (Pt, f64)) -> f64 {
+    let ((a, wa), (b, wb), (c, wc), (d, wd)) = (a, b, c, d);
+    let adx = a.x - d.x;
+    let bdx = b.x - d.x;
+    let cdx = c.x - d.x;
+    let ady = a.y - d.y;
+    let bdy = b.y - d.y;
+    let cdy = c.y - d.y;
+    let (adw, bdw, cdw) = (wa - wd, wb - wd, wc - wd);
+
+    let bdxcdy = bdx * cdy;
+    let cdxbdy = cdx * bdy;
+    let alift = adx * adx + ady * ady - adw;
+
+    let cdxady = cdx * ady;
+    let adxcdy = adx * cdy;
+    let blift = bdx * bdx + bdy * bdy - bdw;
+
+    let adxbdy = adx * bdy;
+    let bdxady = bdx * ady;
+    let clift = cdx * cdx + cdy * cdy - cdw;
+
+    let det = alift * (bdxcdy - cdxbdy) + blift * (cdxady - adxcdy) + clift * (adxbdy - bdxady);
+    let permanent = (bdxcdy.abs() + cdxbdy.abs()) * (adx * adx + ady * ady + adw.abs())
+        + (cdxady.abs() + adxcdy.abs()) * (bdx * bdx + bdy * bdy + bdw.abs())
+        + (adxbdy.abs() + bdxady.abs()) * (cdx * cdx + cdy * cdy + cdw.abs());
+
+    if det.abs() > IPW_ERR_BOUND_A * permanent {
+        return det;
+    }
+    inpower_exact([a, b, c, d], [wa, wb, wc, wd])
+}
+
+fn orient2d_exact(a: Pt, b: Pt, c: Pt) -> f64 {
+    let acx = two_diff(a.x, c.x);
+    let bcy = two_diff(b.y, c.y);
+    let acy = two_diff(a.y, c.y);
+    let bcx = two_diff(b.x, c.x);
+    let left = mul(&acx, &bcy);
+    let right = mul(&acy, &bcx);
+    estimate(&sub(&left, &right))
+}
+
+fn incircle_exact(a: Pt, b: Pt, c: Pt, d: Pt) -> f64 {
+    let adx = two_diff(a.x, d.x);
+    let ady = two_diff(a.y, d.y);
+    let bdx = two_diff(b.x, d.x);
+    let bdy = two_diff(b.y, d.y);
+    let cdx = two_diff(c.x, d.x);
+    let cdy = two_diff(c.y, d.y);
+
+    let alift = sum(&mul(&adx, &adx), &mul(&ady, &ady));
+    let blift = sum(&mul(&bdx, &bdx), &mul(&bdy, &bdy));
+    let clift = sum(&mul(&cdx, &cdx), &mul(&cdy, &cdy));
+
+    let bc = sub(&mul(&bdx, &cdy), &mul(&cdx, &bdy));
+    let ca = sub(&mul(&cdx, &ady), &mul(&adx, &cdy));
+    let ab = sub(&mul(&adx, &bdy), &mul(&bdx, &ady));
+
+    let det = sum(
+        &sum(&mul(&alift, &bc), &mul(&blift, &ca)),
+        &mul(&clift, &ab),
+    );
+    estimate(&det)
+}
+
+fn inpower_exact(p: [Pt; 4], w: [f64; 4]) -> f64 {
+    let [a, b, c, d] = p;
+    let adx = two_diff(a.x, d.x);
+    let ady = two_diff(a.y, d.y);
+    let bdx = two_diff(b.x, d.x);
+    let bdy = two_diff(b.y, d.y);
+    let cdx = two_diff(c.x, d.x);
+    let cdy = two_diff(c.y, d.y);
+
+    // The weight differences are exact as expansions too, so the lifts are.
+    let lift = |dx: &[f64], dy: &[f64], wi: f64| {
+        sub(&sum(&mul(dx, dx), &mul(dy, dy)), &two_diff(wi, w[3]))
+    };
+    let alift = lift(&adx, &ady, w[0]);
+    let blift = lift(&bdx, &bdy, w[1]);
+    let clift = lift(&cdx, &cdy, w[2]);
+
+    let bc = sub(&mul(&bdx, &cdy), &mul(&cdx, &bdy));
+    let ca = sub(&mul(&cdx, &ady), &mul(&adx, &cdy));
+    let ab = sub(&mul(&adx, &bdy), &mul(&bdx, &ady));
+
+    let det = sum(
+        &sum(&mul(&alift, &bc), &mul(&blift, &ca)),
+        &mul(&clift, &ab),
+    );
+    estimate(&det)
+}
+
+// ---------------------------------------------------------------------------
+// Expansion arithmetic. An expansion is a sum of non-overlapping doubles,
+// stored in order of increasing magnitude with zero components removed.
+// ---------------------------------------------------------------------------
+
+#[inline]
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let x = a + b;
+    let bv = x - a;
+    let av = x - bv;
+    (x, (a - av) + (b - bv))
+}
+
+#[inline]
+fn split(a: f64) -> (f64, f64) {
+    let c = SPLITTER * a;
+    let abig = c - a;
+    let hi = c - abig;
+    (hi, a - hi)
+}
+
+#[inline]
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let x = a * b;
+    let (ahi, alo) = split(a);
+    let (bhi, blo) = split(b);
+    let err1 = x - ahi * bhi;
+    let err2 = err1 - alo * bhi;
+    let err3 = err2 - ahi * blo;
+    (x, alo * blo - err3)
+}
+
+/// The exact difference `a - b` as an expansion.
+fn two_diff(a: f64, b: f64) -> Vec<f64> {
+    let (x, y) = two_sum(a, -b);
+    [y, x].into_iter().filter(|&v| v != 0.0).collect()
+}
+
+/// Add a single double to an expansion (Shewchuk's GROW-EXPANSION).
+fn grow(e: &[f64], b: f64) -> Vec<f64> {
+    let mut out = Vec::with_capacity(e.len() + 1);
+    let mut q = b;
+    for &ei in e {
+        let (sum, err) = two_sum(q, ei);
+        if err != 0.0 {
+            out.push(err);
+        }
+        q = sum;
+    }
+    if q != 0.0 {
+        out.push(q);
+    }
+    out
+}
+
+fn sum(e: &[f64], f: &[f64]) -> Vec<f64> {
+    f.iter().fold(e.to_vec(), |acc, &fi| grow(&acc, fi))
+}
+
+fn sub(e: &[f64], f: &[f64]) -> Vec<f64> {
+    f.iter().fold(e.to_vec(), |acc, &fi| grow(&acc, -fi))
+}
+
+/// Multiply an expansion by a single double (Shewchuk's SCALE-EXPANSION).
+fn scale(e: &[f64], b: f64) -> Vec<f64> {
+    let mut out = Vec::with_capacity(2 * e.len());
+    let Some((&e0, rest)) = e.split_first() else {
+        return out;
+    };
+    let (mut q, h) = two_product(e0, b);
+    if h != 0.0 {
+        out.push(h);
+    }
+    for &ei in rest {
+        let (p1, p0) = two_product(ei, b);
+        let (s, h) = two_sum(q, p0);
+        if h != 0.0 {
+            out.push(h);
+        }
+        let (s2, h2) = two_sum(p1, s);
+        if h2 != 0.0 {
+            out.push(h2);
+        }
+        q = s2;
+    }
+    if q != 0.0 {
+        out.push(q);
+    }
+    out
+}
+
+fn mul(e: &[f64], f: &[f64]) -> Vec<f64> {
+    f.iter()
+        .fold(Vec::new(), |acc, &fi| sum(&acc, &scale(e, fi)))
+}
+
+/// Approximate value of an expansion. The components do not overlap, so the
+/// largest one dominates and the sign of the result is exact.
+fn estimate(e: &[f64]) -> f64 {
+    e.iter().sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voronoi::test_util::pt;
+    use std::cmp::Ordering;
+
+    #[test]
+    fn orient2d_near_collinear() {
+        // Points nudged off the line y = x by single ulps, where the naive
+        // determinant is dominated by rounding error.
+        let ulp = 2f64.powi(-53);
+        let q = pt(12.0, 12.0);
+        let r = pt(24.0, 24.0);
+        for i in 0..32 {
+            for j in 0..32 {
+                let p = pt(0.5 + i as f64 * ulp, 0.5 + j as f64 * ulp);
+                let det = orient2d(p, q, r);
+                match j.cmp(&i) {
+                    Ordering::Greater => assert!(det > 0.0, "({i}, {j}): {det}"),
+                    Ordering::Less => assert!(det < 0.0, "({i}, {j}): {det}"),
+                    Ordering::Equal => assert_eq!(det, 0.0, "({i}, {j})"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn incircle_cocircular_is_zero() {
+        let offset = 1.0e6;
+        let on = [
+            (25.0, 0.0),
+            (24.0, 7.0),
+            (20.0, 15.0),
+            (15.0, 20.0),
+            (7.0, 24.0),
+            (0.0, 25.0),
+            (-20.0, 15.0),
+            (-25.0, 0.0),
+            (-7.0, -24.0),
+            (15.0, -20.0),
+        ];
+        let a = pt(offset + 25.0, offset);
+        let b = pt(offset, offset + 25.0);
+        let c = pt(offset - 25.0, offset);
+        for &(x, y) in &on {
+            assert_eq!(incircle(a, b, c, pt(offset + x, offset + y)), 0.0);
+        }
+    }
+
+    #[test]
+    fn inpower_matches_incircle_and_weights() {
+        let offset = 1.0e6;
+        let a = pt(offset + 25.0, offset);
+        let b = pt(offset, offset + 25.0);
+        let c = pt(offset - 25.0, offset);
+        for &(x, y) in &[(7.0, 24.0), (0.0, 0.0), (30.0, 30.0)] {
+            let d = pt(offset + x, offset + y);
+            let w = |p| (p, 3.0);
+            let expected = incircle(a, b, c, d);
+            assert_eq!(inpower(w(a), w(b), w(c), w(d)).signum(), expected.signum());
+        }
+        // A point on the circumcircle is in conflict exactly when it is
+        // heavier than the triangle's vertices.
+        let on = pt(offset + 7.0, offset + 24.0);
+        let v = |p| (p, 1.0);
+        assert_eq!(inpower(v(a), v(b), v(c), (on, 1.0)), 0.0);
+        assert!(inpower(v(a), v(b), v(c), (on, 1.0 + 1e-9)) > 0.0);
+        assert!(inpower(v(a), v(b), v(c), (on, 1.0 - 1e-9)) < 0.0);
+    }
+
+    #[test]
+    fn incircle_tiny_perturbation() {
+        let offset = 1.0e6;
+        let a = pt(offset + 25.0, offset);
+        let b = pt(offset, offset + 25.0);
+        let c = pt(offset - 25.0, offset);
+        let y = offset - 25.0;
+        let inside = pt(offset, y + (y * f64::EPSILON));
+        let outside = pt(offset, y - (y * f64::EPSILON));
+        assert!(incircle(a, b, c, inside) > 0.0);
+        assert!(incircle(a, b, c, outside) < 0.0);
+    }
+}
@@ -0,0 +1,258 @@
+//! Point location and nearest-site queries on the triangulation.
+//!
+//! All queries start with the same walk that insertion uses to find the
+//! triangle containing the query point. The nearest site is then found by
+//! greedy descent along Delaunay edges: a site that is not the nearest always
+//! has a Delaunay neighbour closer to the query. The `k` nearest sites form a
+//! connected subgraph around the nearest one, so a best-first search over the
+//! Delaunay graph reports them in order of distance.
+
+use super::Pt;
+use super::delaunay::DelaunayTriangulation;
+use super::predicates::orient2d;
+use super::triangulation::{GHOST, Triangulation};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+use wasm_bindgen::prelude::*;
+
+/// Triangle containing a query point, with the point's barycentric weights.
+/// `u * a + v * b + w * c` reproduces the point, and `u + v + w == 1`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct PointLocation {
+    pub a: u32,
+    pub b: u32,
+    pub c: u32,
+    pub u: f32,
+    pub v: f32,
+    pub w: f32,
+}
+
+/// Heap entry ordered so that `BinaryHeap` pops the closest site first.
+struct Candidate {
+    d2: f64,
+    v: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.d2.total_cmp(&self.d2).then(other.v.cmp(&self.v))
+    }
+}
+
+impl Triangulation {
+    /// The solid triangle containing `p` as `[a, b, c]` with the barycentric
+    /// weights of `p`, or `None` if `p` lies outside the convex hull.
+    pub(crate) fn locate_barycentric(&mut self, p: Pt) -> Option<([usize; 3], [f64; 3])> {
+        if !self.has_triangles() {
+            return None;
+        }
+        let t = self.locate(p);
+        if self.is_ghost(t) {
+            return None;
+        }
+        let [a, b, c] = self.tri_verts(t);
+        let (pa, pb, pc) = (self.points[a], self.points[b], self.points[c]);
+        let area = orient2d(pa, pb, pc);
+        let u = orient2d(p, pb, pc) / area;
+        let v = orient2d(pa, p, pc) / area;
+        Some(([a, b, c], [u, v, 1.0 - u - v]))
+    }
+
+    /// The vertex closest to `p`, or `None` without triangles.
+    pub(crate) fn nearest_vertex(&mut self, p: Pt) -> Option<usize> {
+        if !self.has_triangles() {
+            return None;
+        }
+        let t = self.locate(p);
+        let mut v = self
+            .tri_verts(t)
+            .into_iter()
+            .find(|&v| v != GHOST)
+            .expect("triangle without vertices");
+        let mut best = self.points[v].sub(p).len2();
+        loop {
+            let closer = self
+                .neighbors(v)
+                .filter(|&w| w != GHOST)
+                .map(|w| (w, self.points[w].sub(p).len2()))
+                .filter(|&(_, d2)| d2 < best)
+                .min_by(|x, y| x.1.total_cmp(&y.1));
+            match closer {
+                Some((w, d2)) => {
+                    v = w;
+                    best = d2;
+                }
+                None => return Some(v),
+            }
+        }
+    }
+
+    /// The `k` vertices closest to `p`, nearest first.
+    pub(crate) fn k_nearest(&mut self, p: Pt, k: usize) -> Vec<usize> {
+        let mut out = Vec::with_capacity(k);
+        if k == 0 {
+            return out;
+        }
+        let Some(start) = self.nearest_vertex(p) else {
+            return out;
+        };
+        let mut seen = HashSet::from([start]);
+        let mut heap = BinaryHeap::from([Candidate {
+            d2: self.points[start].sub(p).len2(),
+            v: start,
+        }]);
+        while let Some(Candidate { v, .. }) = heap.pop() {
+            out.push(v);
+            if out.len() == k {
+                break;
+            }
+            for w in self.neighbors(v) {
+                if w != GHOST && seen.insert(w) {
+                    heap.push(Candidate {
+                        d2: self.points[w].sub(p).len2(),
+                        v: w,
+                    });
+                }
+            }
+        }
+        out
+    }
+}
+
+#[wasm_bindgen]
+impl DelaunayTriangulation {
+    /// Id of the point closest to `(x, y)`, or `undefined` if there is none.
+    pub fn nearest(&mut self, x: f32, y: f32) -> Option<u32> {
+        self.k_nearest(x, y, 1).first().copied()
+    }
+
+    /// Ids of the `k` points closest to `(x, y)`, nearest first.
+    pub fn k_nearest(&mut self, x: f32, y: f32, k: u32) -> Vec<u32> {
+        let p = Pt {
+            x: x as f64,
+            y: y as f64,
+        };
+        let k = k as usize;
+        let ids = if self.tri.has_triangles() {
+            self.tri.k_nearest(p, k)
+        } else {
+            // Fewer than three points or all on one line: just scan them.
+            let mut ids: Vec<usize> = (0..self.tri.points.len())
+                .filter(|&i| !self.removed[i])
+                .collect();
+            let d2 = |i: usize| self.tri.points[i].sub(p).len2();
+            ids.sort_by(|&i, &j| d2(i).total_cmp(&d2(j)));
+            ids.dedup_by(|j, i| self.tri.points[*i] == self.tri.points[*j]);
+            ids.truncate(k);
+            ids
+        };
+        ids.into_iter().map(|i| i as u32).collect()
+    }
+
+    /// Triangle containing `(x, y)` and the barycentric weights of the
+    /// point in it, or `undefined` outside the convex hull.
+    pub fn locate(&mut self, x: f32, y: f32) -> Option<PointLocation> {
+        let p = Pt {
+            x: x as f64,
+            y: y as f64,
+        };
+        let ([a, b, c], [u, v, w]) = self.tri.locate_barycentric(p)?;
+        Some(PointLocation {
+            a: a as u32,
+            b: b as u32,
+            c: c as u32,
+            u: u as f32,
+            v: v as f32,
+            w: w as f32,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voronoi::test_util::random_points;
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        for seed in 1..5 {
+            let mut points = random_points(300, seed, 400.0);
+            if seed % 2 == 0 {
+                // A coarse grid makes duplicates and equidistant sites.
+                for p in &mut points {
+                    p.x = (p.x / 25.0).round() * 25.0;
+                    p.y = (p.y / 25.0).round() * 25.0;
+                }
+            }
+            let mut tri = Triangulation::new(&points);
+            let mut s = seed + 100;
+            for _ in 0..200 {
+                let q = Pt {
+                    x: crate::rand::frand01(&mut s) as f64 * 500.0 - 50.0,
+                    y: crate::rand::frand01(&mut s) as f64 * 500.0 - 50.0,
+                };
+                // Squared distances of all vertices, closest first.
+                let mut all: Vec<f64> = (0..points.len())
+                    .filter(|&i| tri.is_vertex(i))
+                    .map(|i| points[i].sub(q).len2())
+                    .collect();
+                all.sort_by(f64::total_cmp);
+
+                let nearest = tri.nearest_vertex(q).unwrap();
+                assert_eq!(points[nearest].sub(q).len2(), all[0]);
+                let knn: Vec<f64> = tri
+                    .k_nearest(q, 10)
+                    .into_iter()
+                    .map(|i| points[i].sub(q).len2())
+                    .collect();
+                assert_eq!(knn, all[..10]);
+            }
+        }
+    }
+
+    #[test]
+    fn barycentric_weights_reproduce_the_point() {
+        let points = random_points(200, 7, 400.0);
+        let mut tri = Triangulation::new(&points);
+        let mut s = 8u32;
+        let mut inside = 0;
+        for _ in 0..500 {
+            let q = Pt {
+                x: crate::rand::frand01(&mut s) as f64 * 500.0 - 50.0,
+                y: crate::rand::frand01(&mut s) as f64 * 500.0 - 50.0,
+            };
+            let Some(([a, b, c], [u, v, w])) = tri.locate_barycentric(q) else {
+                // Outside the hull: no solid triangle contains it.
+                assert!(tri.triangles().iter().all(|t| {
+                    let (a, b, c) = (points[t.a], points[t.b], points[t.c]);
+                    orient2d(a, b, q) < 0.0 || orient2d(b, c, q) < 0.0 || orient2d(c, a, q) < 0.0
+                }));
+                continue;
+            };
+            inside += 1;
+            assert!((u + v + w - 1.0).abs() < 1e-12);
+            assert!(u >= -1e-12 && v >= -1e-12 && w >= -1e-12);
+            let r = points[a].mul(u).add(points[b].mul(v)).add(points[c].mul(w));
+            assert!(
+                r.sub(q).len2() < 1e-18 * q.len2().max(1.0),
+                "{r:?} vs {q:?}"
+            );
+        }
+        assert!(inside > 200);
+    }
+}
@@ -0,0 +1,368 @@
+//! Raster Voronoi diagrams for arbitrary metrics.
+//!
+//! Everything else in this module is Euclidean, because the Voronoi diagram
+//! is read off the Delaunay triangulation. A raster has no such restriction:
+//! every pixel simply stores the index of its nearest site under the chosen
+//! metric (the label buffer) and the distance to it (the distance buffer).
+//!
+//! The exact mode compares every pixel with every site. Jump flooding
+//! (Rong & Tan 2006) instead spreads the labels over the grid in passes
+//! with halving step sizes, which costs `O(pixels · log(size))` regardless
+//! of the number of sites; a final pass with step 1 removes most of its
+//! rare mislabelled pixels.
+
+use super::{Pt, VoronoiTests, read_points};
+use wasm_bindgen::prelude::*;
+
+/// Label of pixels that have no site (only if there are no sites at all).
+pub(crate) const NO_SITE: u32 = u32::MAX;
+
+/// Distance function for the raster diagram.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DistanceMetric {
+    /// `sqrt(dx² + dy²)`
+    Euclidean = 0,
+    /// `|dx| + |dy|`
+    Manhattan = 1,
+    /// `max(|dx|, |dy|)`
+    Chebyshev = 2,
+    /// `(|dx|^p + |dy|^p)^(1/p)` for `p >= 1`; smaller `p` is no metric
+    /// and is raised to 1.
+    Minkowski = 3,
+}
+
+/// A metric together with the exponent used by [`DistanceMetric::Minkowski`].
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Metric {
+    pub(crate) kind: DistanceMetric,
+    pub(crate) p: f64,
+}
+
+impl Metric {
+    /// `p` below 1 or NaN counts as 1, where the "distance" would no longer
+    /// satisfy the triangle inequality and cells would not be connected.
+    pub(crate) fn new(kind: DistanceMetric, p: f64) -> Self {
+        let p = if p >= 1.0 { p } else { 1.0 };
+        Self { kind, p }
+    }
+
+    pub(crate) fn dist(self, d: Pt) -> f64 {
+        let (x, y) = (d.x.abs(), d.y.abs());
+        match self.kind {
+            DistanceMetric::Euclidean => x.hypot(y),
+            DistanceMetric::Manhattan => x + y,
+            DistanceMetric::Chebyshev => x.max(y),
+            DistanceMetric::Minkowski if self.p.is_infinite() => x.max(y),
+            DistanceMetric::Minkowski => (x.powf(self.p) + y.powf(self.p)).powf(1.0 / self.p),
+        }
+    }
+}
+
+/// Pixel grid laid over the rectangle `[min_x, max_x] x [min_y, max_y]`;
+/// pixel `(i, j)` samples the point at its centre.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct RasterGrid {
+    pub width: u32,
+    pub height: u32,
+    pub min_x: f32,
+    pub min_y: f32,
+    pub max_x: f32,
+    pub max_y: f32,
+}
+
+#[wasm_bindgen]
+impl RasterGrid {
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        width: u32,
+        height: u32,
+        min_x: f32,
+        min_y: f32,
+        max_x: f32,
+        max_y: f32,
+    ) -> RasterGrid {
+        RasterGrid {
+            width,
+            height,
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        }
+    }
+}
+
+impl RasterGrid {
+    pub(crate) fn size(&self) -> (usize, usize) {
+        (self.width as usize, self.height as usize)
+    }
+
+    fn pixel_size(&self) -> Pt {
+        Pt {
+            x: (self.max_x - self.min_x) as f64 / self.width.max(1) as f64,
+            y: (self.max_y - self.min_y) as f64 / self.height.max(1) as f64,
+        }
+    }
+
+    pub(crate) fn center(&self, i: usize, j: usize) -> Pt {
+        let s = self.pixel_size();
+        Pt {
+            x: self.min_x as f64 + (i as f64 + 0.5) * s.x,
+            y: self.min_y as f64 + (j as f64 + 0.5) * s.y,
+        }
+    }
+
+    /// Pixel containing `p`, clamped to the grid.
+    fn pixel_of(&self, p: Pt) -> (usize, usize) {
+        let s = self.pixel_size();
+        let (w, h) = self.size();
+        let clamp = |v: f64, n: usize| (v.floor().max(0.0) as usize).min(n - 1);
+        (
+            clamp((p.x - self.min_x as f64) / s.x, w),
+            clamp((p.y - self.min_y as f64) / s.y, h),
+        )
+    }
+}
+
+/// Label and distance buffer, row by row from `min_y` upwards.
+#[wasm_bindgen(getter_with_clone)]
+pub struct RasterVoronoi {
+    pub width: u32,
+    pub height: u32,
+    /// Index of the nearest site per pixel.
+    #[wasm_bindgen(readonly)]
+    pub labels: Vec<u32>,
+    /// Distance from the pixel centre to that site under the metric.
+    #[wasm_bindgen(readonly)]
+    pub distances: Vec<f32>,
+}
+
+impl RasterVoronoi {
+    fn new(grid: &RasterGrid, labels: Vec<u32>, points: &[Pt], metric: Metric) -> Self {
+        let (w, _) = grid.size();
+        let distances = labels
+            .iter()
+            .enumerate()
+            .map(|(k, &l)| {
+                if l == NO_SITE {
+                    return f32::INFINITY;
+                }
+                let c = grid.center(k % w, k / w);
+                metric.dist(c.sub(points[l as usize])) as f32
+            })
+            .collect();
+        Self {
+            width: grid.width,
+            height: grid.height,
+            labels,
+            distances,
+        }
+    }
+}
+
+/// Exact labels: every pixel against every site; ties go to the lower index.
+pub(crate) fn raster_exact(points: &[Pt], grid: &RasterGrid, metric: Metric) -> Vec<u32> {
+    let (w, h) = grid.size();
+    let mut labels = vec![NO_SITE; w * h];
+    for j in 0..h {
+        for i in 0..w {
+            let c = grid.center(i, j);
+            let mut best = f64::INFINITY;
+            for (k, &p) in points.iter().enumerate() {
+                let d = metric.dist(c.sub(p));
+                if d < best {
+                    best = d;
+                    labels[j * w + i] = k as u32;
+                }
+            }
+        }
+    }
+    labels
+}
+
+/// Labels by jump flooding. Sites outside the grid are seeded at the
+/// nearest border pixel, distances are always measured to the site itself.
+pub(crate) fn raster_jump_flood(points: &[Pt], grid: &RasterGrid, metric: Metric) -> Vec<u32> {
+    let (w, h) = grid.size();
+    let mut labels = vec![NO_SITE; w * h];
+    if w == 0 || h == 0 {
+        return labels;
+    }
+    let dist = |l: u32, i: usize, j: usize| metric.dist(grid.center(i, j).sub(points[l as usize]));
+    for (k, &p) in points.iter().enumerate() {
+        let (i, j) = grid.pixel_of(p);
+        let cur = labels[j * w + i];
+        if cur == NO_SITE || dist(k as u32, i, j) < dist(cur, i, j) {
+            labels[j * w + i] = k as u32;
+        }
+    }
+
+    let mut next = labels.clone();
+    let mut steps = Vec::new();
+    let mut step = w.max(h).next_power_of_two() / 2;
+    while step >= 1 {
+        steps.push(step);
+        step /= 2;
+    }
+    steps.push(1);
+    for step in steps {
+        for j in 0..h {
+            for i in 0..w {
+                let mut best = labels[j * w + i];
+                let mut best_d = if best == NO_SITE {
+                    f64::INFINITY
+                } else {
+                    dist(best, i, j)
+                };
+                for (dx, dy) in (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dx, dy))) {
+                    let x = i as isize + dx * step as isize;
+                    let y = j as isize + dy * step as isize;
+                    if x < 0 || y < 0 || x >= w as isize || y >= h as isize {
+                        continue;
+                    }
+                    let l = labels[y as usize * w + x as usize];
+                    if l == NO_SITE || l == best {
+                        continue;
+                    }
+                    let d = dist(l, i, j);
+                    if d < best_d || (d == best_d && l < best) {
+                        best = l;
+                        best_d = d;
+                    }
+                }
+                next[j * w + i] = best;
+            }
+        }
+        std::mem::swap(&mut labels, &mut next);
+    }
+    labels
+}
+
+#[wasm_bindgen]
+impl VoronoiTests {
+    /// Voronoi diagram of the sites on a pixel grid under `metric` (`p` is
+    /// the Minkowski exponent, raised to 1 if smaller, and ignored
+    /// otherwise). Exact, at a cost of pixels × sites. Input layout:
+    /// [x,y,vx,vy,...]
+    pub fn raster_voronoi(
+        points_flat: &[f32],
+        grid: &RasterGrid,
+        metric: DistanceMetric,
+        p: f32,
+    ) -> RasterVoronoi {
+        let points = read_points(points_flat);
+        let metric = Metric::new(metric, p as f64);
+        let labels = raster_exact(&points, grid, metric);
+        RasterVoronoi::new(grid, labels, &points, metric)
+    }
+
+    /// Like [`VoronoiTests::raster_voronoi`], but by jump flooding, which
+    /// does not depend on the number of sites and may mislabel a few pixels
+    /// near cell boundaries.
+    pub fn raster_voronoi_jfa(
+        points_flat: &[f32],
+        grid: &RasterGrid,
+        metric: DistanceMetric,
+        p: f32,
+    ) -> RasterVoronoi {
+        let points = read_points(points_flat);
+        let metric = Metric::new(metric, p as f64);
+        let labels = raster_jump_flood(&points, grid, metric);
+        RasterVoronoi::new(grid, labels, &points, metric)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voronoi::test_util::random_points;
+
+    #[test]
+    fn jump_flood_matches_brute_force() {
+        let grid = RasterGrid::new(200, 150, 0.0, 0.0, 100.0, 100.0);
+        let metrics = [
+            Metric::new(DistanceMetric::Euclidean, 2.0),
+            Metric::new(DistanceMetric::Manhattan, 2.0),
+            Metric::new(DistanceMetric::Chebyshev, 2.0),
+            Metric::new(DistanceMetric::Minkowski, 3.0),
+            Metric::new(DistanceMetric::Minkowski, 0.7),
+        ];
+        for (seed, metric) in (1..).zip(metrics) {
+            let points = random_points(60, seed, 100.0);
+            let exact = raster_exact(&points, &grid, metric);
+            let flood = raster_jump_flood(&points, &grid, metric);
+            assert!(exact.iter().all(|&l| l != NO_SITE));
+            // Mislabelled pixels are rare. Jump flooding can pick a slightly
+            // farther site near a cell boundary, but never one much farther.
+            let mut wrong = 0;
+            for (k, (&a, &b)) in exact.iter().zip(&flood).enumerate() {
+                if a != b {
+                    wrong += 1;
+                    let c = grid.center(k % 200, k / 200);
+                    let [da, db] = [a, b].map(|l| metric.dist(c.sub(points[l as usize])));
+                    assert!(db - da < 1.0, "{metric:?}: pixel {k} is {db} vs {da} away");
+                }
+            }
+            assert!(
+                wrong * 200 < exact.len(),
+                "{metric:?}: {wrong} pixels differ"
+            );
+        }
+    }
+
+    #[test]
+    fn distances_match_the_exact_labels() {
+        let grid = RasterGrid::new(80, 60, -10.0, -10.0, 110.0, 110.0);
+        let points = random_points(40, 11, 100.0);
+        let flat: Vec<f32> = points
+            .iter()
+            .flat_map(|p| [p.x as f32, p.y as f32, 0.0, 0.0])
+            .collect();
+        // The sites as the wasm entry points read them.
+        let points = read_points(&flat);
+        for (kind, p) in [
+            (DistanceMetric::Euclidean, 2.0),
+            (DistanceMetric::Manhattan, 2.0),
+            (DistanceMetric::Chebyshev, 2.0),
+            (DistanceMetric::Minkowski, 1.5),
+        ] {
+            let metric = Metric::new(kind, p as f64);
+            let exact = VoronoiTests::raster_voronoi(&flat, &grid, kind, p);
+            assert_eq!(exact.labels, raster_exact(&points, &grid, metric));
+            let flood = VoronoiTests::raster_voronoi_jfa(&flat, &grid, kind, p);
+            for k in 0..exact.labels.len() {
+                let c = grid.center(k % 80, k / 80);
+                let nearest = points
+                    .iter()
+                    .map(|&q| metric.dist(c.sub(q)))
+                    .fold(f64::INFINITY, f64::min);
+                assert_eq!(exact.distances[k], nearest as f32, "{kind:?}: pixel {k}");
+                let own = metric.dist(c.sub(points[flood.labels[k] as usize]));
+                assert_eq!(flood.distances[k], own as f32, "{kind:?}: pixel {k}");
+                assert!(flood.distances[k] >= exact.distances[k]);
+            }
+        }
+
+        let empty = VoronoiTests::raster_voronoi(&[], &grid, DistanceMetric::Euclidean, 2.0);
+        assert!(empty.labels.iter().all(|&l| l == NO_SITE));
+        assert!(empty.distances.iter().all(|&d| d == f32::INFINITY));
+    }
+
+    #[test]
+    fn minkowski_exponent_is_at_least_one() {
+        let d = Pt { x: 3.0, y: -4.0 };
+        for p in [0.5, 0.0, -2.0, f64::NAN] {
+            let metric = Metric::new(DistanceMetric::Minkowski, p);
+            assert_eq!(metric.p, 1.0);
+            assert_eq!(metric.dist(d), 7.0);
+        }
+        let euclid = Metric::new(DistanceMetric::Minkowski, 2.0);
+        assert!((euclid.dist(d) - 5.0).abs() < 1e-12);
+        assert_eq!(
+            Metric::new(DistanceMetric::Minkowski, f64::INFINITY).dist(d),
+            4.0
+        );
+    }
+}
@@ -0,0 +1,578 @@
+//! Quality mesh generation by Delaunay refinement (Ruppert, Chew).
+//!
+//! Starting from the constrained triangulation of a shape, Steiner points are
+//! added until every triangle inside it meets a minimum angle and a maximum
+//! area. Forced edges whose diametral circle contains a vertex
+//! ("encroached" segments) are split first; a bad triangle then gets its
+//! circumcenter inserted, unless that would encroach on a segment, in which
+//! case the segment is split instead. Segments next to an input vertex are
+//! split on concentric circles around it (Shewchuk's "concentric shells"),
+//! and triangles that cannot be improved because of a small input angle are
+//! left alone, which keeps small angles in the input from causing endless
+//! refinement.
+
+use super::cdt::read_constraints;
+use super::degenerate::{TriangulationError, check_points};
+use super::predicates::orient2d;
+use super::triangulation::{GHOST, Insertion, Triangulation, next, prev};
+use super::{Constraint, Pt, VoronoiTests, circumcircle, read_xy};
+use std::collections::{HashMap, HashSet};
+use wasm_bindgen::prelude::*;
+
+/// Largest minimum angle in degrees that is accepted; refinement is only
+/// known to terminate up to about this bound.
+pub(crate) const MAX_MIN_ANGLE: f64 = 33.0;
+
+/// A refined triangle mesh of a shape.
+#[wasm_bindgen(getter_with_clone)]
+pub struct QualityMesh {
+    /// Vertex positions as [x,y,...].
+    #[wasm_bindgen(readonly)]
+    pub vertices: Vec<f32>,
+    /// Triangles as index triplets into `vertices`, counter-clockwise.
+    #[wasm_bindgen(readonly)]
+    pub indices: Vec<u32>,
+    /// Whether refinement stopped at `max_steiner` points with triangles or
+    /// segments left to improve, so the bounds may not be met.
+    #[wasm_bindgen(readonly)]
+    pub budget_exhausted: bool,
+}
+
+/// Bounds every triangle inside the shape has to meet.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Quality {
+    /// Smallest allowed angle in degrees, at most [`MAX_MIN_ANGLE`].
+    pub(crate) min_angle: f64,
+    /// Largest allowed area.
+    pub(crate) max_area: f64,
+    /// Stop after adding this many Steiner points.
+    pub(crate) max_steiner: usize,
+}
+
+/// What inserting the circumcenter of a bad triangle would do.
+enum Probe {
+    Free,
+    /// It lies behind or inside the diametral circle of these forced edges.
+    Encroaches(Vec<(usize, usize)>),
+    /// It coincides with a vertex or lies outside the triangulation.
+    Skip,
+}
+
+/// Even-odd test of `p` against closed rings.
+fn in_rings(rings: &[Vec<Pt>], p: Pt) -> bool {
+    let mut inside = false;
+    for ring in rings {
+        for (k, &a) in ring.iter().enumerate() {
+            let b = ring[(k + 1) % ring.len()];
+            if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) * (b.x - a.x) / (b.y - a.y) {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+/// `true` if `p` lies strictly inside the circle with diameter `a - b`.
+fn encroaches(a: Pt, b: Pt, p: Pt) -> bool {
+    a.sub(p).dot(b.sub(p)) < 0.0
+}
+
+struct Refiner<'a> {
+    tri: &'a mut Triangulation,
+    /// The shape as closed rings, for telling inside from outside.
+    domain: &'a [Vec<Pt>],
+    /// Points before refinement; only these act as shell centres.
+    input: usize,
+    /// Shell centre of every vertex that split a segment next to an input
+    /// vertex.
+    shell: HashMap<usize, usize>,
+    /// Largest allowed circumradius² / shortest edge².
+    max_ratio2: f64,
+    max_area: f64,
+    budget: usize,
+    /// Set once a split was needed after the budget ran out.
+    exhausted: bool,
+    segments: Vec<(usize, usize)>,
+    triangles: Vec<[usize; 3]>,
+}
+
+impl Refiner<'_> {
+    fn run(&mut self) -> Result<(), TriangulationError> {
+        self.segments = self.tri.constraints.keys().map(|e| (e.a, e.b)).collect();
+        self.triangles = (0..self.tri.tri_count())
+            .filter(|&t| self.tri.is_solid(t))
+            .map(|t| self.tri.tri_verts(t))
+            .collect();
+        loop {
+            while let Some((u, v)) = self.segments.pop() {
+                if self.is_encroached(u, v) {
+                    self.split_segment(u, v)?;
+                }
+            }
+            let Some(t) = self.triangles.pop() else {
+                return Ok(());
+            };
+            // The triangle may have been replaced since it was queued.
+            let Some(e) = self.tri.find_edge(t[0], t[1]) else {
+                continue;
+            };
+            if self.tri.verts[prev(e)] != t[2] || !self.is_bad(t) {
+                continue;
+            }
+            if self.budget == 0 {
+                self.exhausted = true;
+                return Ok(());
+            }
+            let [a, b, c] = t.map(|v| self.tri.points[v]);
+            let Some(cc) = circumcircle(a, b, c) else {
+                continue;
+            };
+            match self.probe(e / 3, cc.c) {
+                Probe::Free => self.insert(cc.c),
+                Probe::Encroaches(segments) => {
+                    let mut split = false;
+                    for (u, v) in segments {
+                        split |= self.split_segment(u, v)?;
+                    }
+                    // Try again once the segments are out of the way.
+                    if split {
+                        self.triangles.push(t);
+                    }
+                }
+                Probe::Skip => {}
+            }
+        }
+    }
+
+    /// `true` if `u - v` is still a forced edge and a vertex next to it lies
+    /// inside its diametral circle.
+    fn is_encroached(&self, u: usize, v: usize) -> bool {
+        let tri = &*self.tri;
+        let Some(e) = tri.find_edge(u, v) else {
+            return false;
+        };
+        if !tri.is_constrained(e) {
+            return false;
+        }
+        let (pu, pv) = (tri.points[u], tri.points[v]);
+        [prev(e), prev(tri.twins[e])]
+            .map(|h| tri.verts[h])
+            .into_iter()
+            .any(|x| x != GHOST && encroaches(pu, pv, tri.points[x]))
+    }
+
+    fn is_bad(&self, t: [usize; 3]) -> bool {
+        let [a, b, c] = t.map(|v| self.tri.points[v]);
+        let centroid = a.add(b).add(c).mul(1.0 / 3.0);
+        if !in_rings(self.domain, centroid) {
+            return false;
+        }
+        if orient2d(a, b, c) / 2.0 > self.max_area {
+            return true;
+        }
+        let (p, q) = [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])]
+            .into_iter()
+            .min_by(|&(p, q), &(r, s)| {
+                let l0 = self.tri.points[p].sub(self.tri.points[q]).len2();
+                let l1 = self.tri.points[r].sub(self.tri.points[s]).len2();
+                l0.total_cmp(&l1)
+            })
+            .expect("triangle without edges");
+        let shortest = self.tri.points[p].sub(self.tri.points[q]).len2();
+        let Some(cc) = circumcircle(a, b, c) else {
+            return false;
+        };
+        cc.r2 > self.max_ratio2 * shortest && !self.is_seditious(p, q)
+    }
+
+    /// `true` if `p - q` joins two vertices on the same shell around an
+    /// input vertex where segments meet at less than 60°. Splitting the
+    /// triangle would only create a smaller copy of it.
+    fn is_seditious(&self, p: usize, q: usize) -> bool {
+        let (Some(&x), Some(&y)) = (self.shell.get(&p), self.shell.get(&q)) else {
+            return false;
+        };
+        if x != y {
+            return false;
+        }
+        let [pp, pq, px] = [p, q, x].map(|i| self.tri.points[i]);
+        let (dp, dq) = (pp.sub(px), pq.sub(px));
+        let (lp, lq) = (dp.len2().sqrt(), dq.len2().sqrt());
+        (lp - lq).abs() <= 1e-6 * lp && dp.dot(dq) > 0.5 * lp * lq
+    }
+
+    /// Walk from triangle `t` to its circumcenter `c` and collect the forced
+    /// edges that `c` would encroach on once inserted.
+    fn probe(&self, t: usize, c: Pt) -> Probe {
+        let tri = &*self.tri;
+        let [a, b, d] = tri.tri_verts(t).map(|v| tri.points[v]);
+        let g = a.add(b).add(d).mul(1.0 / 3.0);
+
+        // Straight walk from the centroid of `t`; a forced edge on the way
+        // hides `c` from the triangle, which counts as encroaching on it.
+        let mut t = t;
+        loop {
+            let mut exit = None;
+            for e in 3 * t..3 * t + 3 {
+                let (x, y) = (tri.points[tri.verts[e]], tri.points[tri.verts[next(e)]]);
+                if orient2d(x, y, c) >= 0.0 {
+                    continue;
+                }
+                if orient2d(g, c, x) <= 0.0 && orient2d(g, c, y) >= 0.0 {
+                    exit = Some(e);
+                    break;
+                }
+                exit.get_or_insert(e);
+            }
+            let Some(e) = exit else {
+                break;
+            };
+            if tri.is_constrained(e) {
+                return Probe::Encroaches(vec![(tri.verts[e], tri.verts[next(e)])]);
+            }
+            t = tri.twins[e] / 3;
+            if tri.is_ghost(t) {
+                return Probe::Skip;
+            }
+        }
+        if tri.tri_verts(t).iter().any(|&v| tri.points[v] == c) {
+            return Probe::Skip;
+        }
+
+        // The forced edges on the boundary of the insertion cavity are the
+        // ones `c` can see.
+        let mut hit = Vec::new();
+        let mut seen = HashSet::from([t]);
+        let mut stack = vec![t];
+        while let Some(s) = stack.pop() {
+            for e in 3 * s..3 * s + 3 {
+                let (u, v) = (tri.verts[e], tri.verts[next(e)]);
+                if tri.is_constrained(e) {
+                    if encroaches(tri.points[u], tri.points[v], c) && !hit.contains(&(u, v)) {
+                        hit.push((u, v));
+                    }
+                    continue;
+                }
+                let n = tri.twins[e] / 3;
+                if !seen.contains(&n) && tri.in_conflict(n, c) {
+                    seen.insert(n);
+                    stack.push(n);
+                }
+            }
+        }
+        if hit.is_empty() {
+            Probe::Free
+        } else {
+            Probe::Encroaches(hit)
+        }
+    }
+
+    /// Split the forced edge `u - v`: on the concentric shell closest to its
+    /// middle if exactly one end is an input vertex, at the midpoint
+    /// otherwise. Returns `false` if no vertex was added.
+    fn split_segment(&mut self, u: usize, v: usize) -> Result<bool, TriangulationError> {
+        if self.tri.find_edge(u, v).is_none() {
+            return Ok(false);
+        }
+        if self.budget == 0 {
+            self.exhausted = true;
+            return Ok(false);
+        }
+        let (pu, pv) = (self.tri.points[u], self.tri.points[v]);
+        let centre = match (u < self.input, v < self.input) {
+            (true, false) => Some((u, v)),
+            (false, true) => Some((v, u)),
+            _ => None,
+        };
+        let (m, centre) = match centre {
+            Some((x, y)) => {
+                let (px, py) = (self.tri.points[x], self.tri.points[y]);
+                let len = px.sub(py).len2().sqrt();
+                let d = (len / 2.0).log2().round().exp2();
+                (px.add(py.sub(px).mul(d / len)), Some(x))
+            }
+            None => (pu.add(pv).mul(0.5), None),
+        };
+        let n = self.tri.points.len();
+        let w = self.tri.split_constraint(u, v, m)?;
+        if w == u || w == v {
+            return Ok(false);
+        }
+        if w >= n {
+            self.budget -= 1;
+        }
+        if let Some(x) = centre {
+            self.shell.insert(w, x);
+        }
+        self.touch(w);
+        Ok(true)
+    }
+
+    fn insert(&mut self, p: Pt) {
+        let i = self.tri.push_point(p);
+        match self.tri.insert(i) {
+            Insertion::Inserted => {
+                self.budget -= 1;
+                self.touch(i);
+            }
+            Insertion::Duplicate(_) | Insertion::Deferred | Insertion::Hidden => {
+                self.tri.pop_point()
+            }
+        }
+    }
+
+    /// Queue the triangles around the new vertex `w` and the forced edges
+    /// it might encroach on or that end at it.
+    fn touch(&mut self, w: usize) {
+        let tri = &*self.tri;
+        for e in tri.star(w) {
+            if tri.is_solid(e / 3) {
+                self.triangles.push(tri.tri_verts(e / 3));
+            }
+            for h in [e, next(e)] {
+                if tri.is_constrained(h) {
+                    self.segments.push((tri.verts[h], tri.verts[next(h)]));
+                }
+            }
+        }
+    }
+}
+
+/// Refine the constrained triangulation `tri` of the shape bounded by the
+/// closed rings `domain` (even-odd rule) until the triangles inside meet
+/// `quality` or the Steiner point budget runs out; returns `true` in the
+/// latter case. Fails like [`Triangulation::constrained`] if a forced edge
+/// cannot be split.
+pub(crate) fn refine(
+    tri: &mut Triangulation,
+    domain: &[Vec<Pt>],
+    quality: Quality,
+) -> Result<bool, TriangulationError> {
+    let angle = quality.min_angle.clamp(0.0, MAX_MIN_ANGLE).to_radians();
+    // r / l >= 1 / (2 sin θ) for a triangle with smallest angle θ.
+    let max_ratio2 = 1.0 / (4.0 * angle.sin().powi(2));
+    let input = tri.points.len();
+    let mut refiner = Refiner {
+        tri,
+        domain,
+        input,
+        shell: HashMap::new(),
+        max_ratio2,
+        max_area: quality.max_area,
+        budget: quality.max_steiner,
+        exhausted: false,
+        segments: Vec::new(),
+        triangles: Vec::new(),
+    };
+    refiner.run()?;
+    Ok(refiner.exhausted)
+}
+
+#[wasm_bindgen]
+impl VoronoiTests {
+    /// Quality triangle mesh of a shape, e.g. for finite element
+    /// experiments. Input as for [`VoronoiTests::constrained_delaunay`];
+    /// without rings the convex hull of the points is meshed.
+    ///
+    /// Steiner points are added until no triangle inside has an angle below
+    /// `min_angle` degrees (at most 33) or an area above `max_area` (pass
+    /// `Infinity` or 0 for no bound), or until `max_steiner` points were
+    /// added, which the result reports. Vertices outside the shape are left
+    /// out of the mesh. Fails like [`VoronoiTests::constrained_delaunay`].
+    pub fn refine_mesh(
+        points_flat: &[f32],
+        segments: &[u32],
+        rings: &[u32],
+        ring_offsets: &[u32],
+        min_angle: f32,
+        max_area: f32,
+        max_steiner: u32,
+    ) -> Result<QualityMesh, TriangulationError> {
+        let points = read_xy(points_flat);
+        if !points.is_empty() {
+            check_points(&points)?;
+        }
+        let (segments, mut rings) = read_constraints(segments, rings, ring_offsets);
+        rings.retain(|ring| ring.iter().all(|&i| i < points.len()));
+        let mut tri = Triangulation::constrained(points, &segments, &rings)?;
+        if !tri.has_triangles() {
+            return Ok(QualityMesh {
+                vertices: Vec::new(),
+                indices: Vec::new(),
+                budget_exhausted: false,
+            });
+        }
+        if rings.is_empty() {
+            let hull = tri.hull();
+            for (k, &a) in hull.iter().enumerate() {
+                tri.insert_segment(a, hull[(k + 1) % hull.len()], Constraint::Boundary)?;
+            }
+            rings.push(hull);
+        }
+        let domain: Vec<Vec<Pt>> = rings
+            .iter()
+            .map(|ring| ring.iter().map(|&i| tri.points[i]).collect())
+            .collect();
+        let max_area = if max_area > 0.0 {
+            max_area as f64
+        } else {
+            f64::INFINITY
+        };
+        let quality = Quality {
+            min_angle: min_angle as f64,
+            max_area,
+            max_steiner: max_steiner as usize,
+        };
+        let budget_exhausted = refine(&mut tri, &domain, quality)?;
+
+        // Keep only the vertices of the triangles inside, renumbered.
+        let kept = tri.interior_triangles();
+        let mut index = vec![u32::MAX; tri.points.len()];
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for t in (0..kept.len()).filter(|&t| kept[t]) {
+            for v in tri.tri_verts(t) {
+                if index[v] == u32::MAX {
+                    index[v] = (vertices.len() / 2) as u32;
+                    vertices.extend([tri.points[v].x as f32, tri.points[v].y as f32]);
+                }
+                indices.push(index[v]);
+            }
+        }
+        Ok(QualityMesh {
+            vertices,
+            indices,
+            budget_exhausted,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voronoi::test_util::pt;
+
+    fn square() -> Vec<Pt> {
+        vec![pt(0.0, 0.0), pt(10.0, 0.0), pt(10.0, 10.0), pt(0.0, 10.0)]
+    }
+
+    fn l_shape() -> Vec<Pt> {
+        vec![
+            pt(0.0, 0.0),
+            pt(4.0, 0.0),
+            pt(4.0, 1.0),
+            pt(1.0, 1.0),
+            pt(1.0, 4.0),
+            pt(0.0, 4.0),
+        ]
+    }
+
+    fn min_angle(a: Pt, b: Pt, c: Pt) -> f64 {
+        let angle = |p: Pt, q: Pt, r: Pt| {
+            let (u, v) = (q.sub(p), r.sub(p));
+            let cos = u.dot(v) / (u.len2() * v.len2()).sqrt();
+            cos.clamp(-1.0, 1.0).acos().to_degrees()
+        };
+        angle(a, b, c).min(angle(b, c, a)).min(angle(c, a, b))
+    }
+
+    /// Refine the polygon `shape` and return whether the budget ran out,
+    /// the area inside, the smallest angle and the largest triangle.
+    fn refine_shape(shape: &[Pt], quality: Quality) -> (bool, f64, f64, f64) {
+        let ring: Vec<usize> = (0..shape.len()).collect();
+        let mut tri = Triangulation::constrained(shape.to_vec(), &[], &[ring]).unwrap();
+        let exhausted = refine(&mut tri, &[shape.to_vec()], quality).unwrap();
+        let kept = tri.interior_triangles();
+        let (mut area, mut worst, mut biggest) = (0.0, 180.0f64, 0.0f64);
+        for t in (0..kept.len()).filter(|&t| kept[t]) {
+            let [a, b, c] = tri.tri_verts(t).map(|v| tri.points[v]);
+            let size = orient2d(a, b, c) / 2.0;
+            area += size;
+            worst = worst.min(min_angle(a, b, c));
+            biggest = biggest.max(size);
+        }
+        (exhausted, area, worst, biggest)
+    }
+
+    #[test]
+    fn triangles_meet_the_bounds() {
+        for (shape, want) in [(square(), 100.0), (l_shape(), 7.0)] {
+            for (min_angle, max_area) in [(20.0, f64::INFINITY), (30.0, 0.5), (33.0, 2.0)] {
+                let quality = Quality {
+                    min_angle,
+                    max_area,
+                    max_steiner: 100_000,
+                };
+                let (exhausted, area, worst, biggest) = refine_shape(&shape, quality);
+                assert!(!exhausted);
+                assert!((area - want).abs() < 1e-9, "{area}");
+                assert!(worst >= min_angle - 1e-9, "{min_angle}: {worst}");
+                assert!(biggest <= max_area + 1e-9, "{max_area}: {biggest}");
+            }
+
+            // A small budget runs out before the area bound is met.
+            let quality = Quality {
+                min_angle: 30.0,
+                max_area: 0.01,
+                max_steiner: 20,
+            };
+            let (exhausted, area, _, biggest) = refine_shape(&shape, quality);
+            assert!(exhausted);
+            assert!((area - want).abs() < 1e-9);
+            assert!(biggest > 0.01);
+        }
+    }
+
+    #[test]
+    fn boundary_segments_are_kept() {
+        // The L-shape with a square hole and a forced segment inside.
+        let mut flat: Vec<f32> = l_shape()
+            .iter()
+            .flat_map(|p| [p.x as f32, p.y as f32])
+            .collect();
+        flat.extend([
+            0.25, 0.25, 0.75, 0.25, 0.75, 0.75, 0.25, 0.75, 0.5, 2.0, 0.5, 3.5,
+        ]);
+        let points = read_xy(&flat);
+        let rings = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let mesh =
+            VoronoiTests::refine_mesh(&flat, &[10, 11], &rings, &[0, 6, 10], 30.0, 0.05, 10_000)
+                .unwrap();
+        assert!(!mesh.budget_exhausted);
+        let vertices = read_xy(&mesh.vertices);
+        let mut edges: Vec<(Pt, Pt)> = Vec::new();
+        for t in mesh.indices.chunks(3) {
+            for k in 0..3 {
+                edges.push((vertices[t[k] as usize], vertices[t[(k + 1) % 3] as usize]));
+            }
+        }
+
+        let outline = [(0..6).collect::<Vec<_>>(), (6..10).collect()];
+        let mut required: Vec<(usize, usize)> = vec![(10, 11)];
+        for ring in &outline {
+            for (k, &a) in ring.iter().enumerate() {
+                required.push((a, ring[(k + 1) % ring.len()]));
+            }
+        }
+        for (a, b) in required {
+            let (pa, pb) = (points[a], points[b]);
+            let len = pa.sub(pb).len2().sqrt();
+            let on = |q: Pt| {
+                let t = q.sub(pa).dot(pb.sub(pa)) / (len * len);
+                orient2d(pa, pb, q).abs() / len < 1e-5 && (-1e-6..=1.0 + 1e-6).contains(&t)
+            };
+            // Edges on the outline are in one triangle, those along the
+            // forced segment inside in two.
+            let covered: f64 = edges
+                .iter()
+                .filter(|(p, q)| on(*p) && on(*q))
+                .map(|(p, q)| p.sub(*q).len2().sqrt())
+                .sum();
+            let times = if a == 10 { 2.0 } else { 1.0 };
+            assert!(
+                (covered - times * len).abs() < 1e-4,
+                "{a} - {b}: {covered} of {len}"
+            );
+        }
+    }
+}
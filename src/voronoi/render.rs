@@ -0,0 +1,493 @@
+//! Software rasteriser for Voronoi cells, Delaunay wireframes and sites.
+//!
+//! Renders into a straight-alpha RGBA8 buffer in row-major order, the layout
+//! of `ImageData`, so JS can hand the result to `putImageData` directly.
+//! Cells are filled without anti-aliasing: every pixel belongs to the site
+//! nearest to its centre, so neighbouring cells meet without gaps or
+//! double-blended seams. Lines and site markers get their coverage from the
+//! distance of the pixel centre to the shape. Only basic
+//! arithmetic and square roots are used, so the output is bit-identical
+//! across platforms, which the golden-image tests rely on.
+
+use super::cells::{LabelledCell, labelled_cells, rect_polygon};
+use super::raster::NO_SITE;
+use super::triangulation::{NONE, Triangulation};
+use super::{Edge, Pt, VoronoiTests, read_points};
+use wasm_bindgen::prelude::*;
+
+/// Cell colours used when none are given (Tableau 10 at half opacity).
+const PALETTE: [u32; 10] = [
+    0x4E79_A780,
+    0xF28E_2B80,
+    0xE159_5980,
+    0x76B7_B280,
+    0x59A1_4F80,
+    0xEDC9_4880,
+    0xB07A_A180,
+    0xFF9D_A780,
+    0x9C75_5F80,
+    0xBAB0_AC80,
+];
+
+/// Colours and sizes for [`VoronoiTests::render_voronoi`] and
+/// [`VoronoiTests::render_delaunay`]. Colours are `0xRRGGBBAA`; sizes are
+/// in input units and scaled by `scale` like the points.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct RenderStyle {
+    pub background: u32,
+    pub edge_color: u32,
+    pub edge_width: f32,
+    pub site_color: u32,
+    /// Radius of the site markers; 0 hides them.
+    pub site_radius: f32,
+    pub antialias: bool,
+    /// Pixels per input unit, e.g. `devicePixelRatio`.
+    pub scale: f32,
+}
+
+impl Default for RenderStyle {
+    /// The colours of the Voronoi demo on a transparent background.
+    fn default() -> Self {
+        Self {
+            background: 0x0000_0000,
+            edge_color: 0xFFAA_66FF,
+            edge_width: 1.0,
+            site_color: 0xFFFF_FFFF,
+            site_radius: 2.2,
+            antialias: true,
+            scale: 1.0,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl RenderStyle {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> RenderStyle {
+        RenderStyle::default()
+    }
+}
+
+/// An RGBA8 image.
+pub(crate) struct Canvas {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) pixels: Vec<u8>,
+}
+
+impl Canvas {
+    pub(crate) fn new(width: usize, height: usize, background: u32) -> Self {
+        let pixels = (0..width * height)
+            .flat_map(|_| background.to_be_bytes())
+            .collect();
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Composite `color` over pixel `(x, y)` with the given coverage.
+    fn blend(&mut self, x: usize, y: usize, color: u32, coverage: f64) {
+        let [r, g, b, a] = color.to_be_bytes();
+        let sa = a as f64 / 255.0 * coverage;
+        if sa <= 0.0 {
+            return;
+        }
+        let i = (y * self.width + x) * 4;
+        let px = &mut self.pixels[i..i + 4];
+        let da = px[3] as f64 / 255.0;
+        let oa = sa + da * (1.0 - sa);
+        for (dst, src) in px.iter_mut().zip([r, g, b]) {
+            let v = (src as f64 * sa + *dst as f64 * da * (1.0 - sa)) / oa;
+            *dst = (v.clamp(0.0, 255.0) + 0.5) as u8;
+        }
+        px[3] = (oa * 255.0 + 0.5) as u8;
+    }
+
+    /// Rows `[lo, hi)` of the pixels whose centres can lie in `[min, max]`.
+    fn rows(&self, min: f64, max: f64) -> std::ops::Range<usize> {
+        let lo = (min - 0.5).ceil().max(0.0);
+        let hi = ((max - 0.5).floor() + 1.0).min(self.height as f64);
+        lo as usize..hi.max(lo) as usize
+    }
+
+    /// Like [`Canvas::rows`] for columns.
+    fn cols(&self, min: f64, max: f64) -> std::ops::Range<usize> {
+        let lo = (min - 0.5).ceil().max(0.0);
+        let hi = ((max - 0.5).floor() + 1.0).min(self.width as f64);
+        lo as usize..hi.max(lo) as usize
+    }
+
+    /// Stroke the segment `a`–`b` with round caps.
+    pub(crate) fn draw_line(&mut self, a: Pt, b: Pt, width: f64, color: u32, antialias: bool) {
+        if [a.x, a.y, b.x, b.y].iter().any(|v| !v.is_finite()) || width <= 0.0 || width.is_nan() {
+            return;
+        }
+        let half = width * 0.5;
+        // Anti-aliased edges fade out over one pixel.
+        let pad = if antialias { half + 0.5 } else { half };
+        let d = b.sub(a);
+        let len2 = d.len2();
+        for y in self.rows(a.y.min(b.y) - pad, a.y.max(b.y) + pad) {
+            let yc = y as f64 + 0.5;
+            // Points of the segment within `pad` of the row; the covered
+            // pixels lie within `pad` of those horizontally.
+            let (t0, t1) = if d.y == 0.0 {
+                (0.0, 1.0)
+            } else {
+                let t0 = ((yc - pad - a.y) / d.y).clamp(0.0, 1.0);
+                let t1 = ((yc + pad - a.y) / d.y).clamp(0.0, 1.0);
+                (t0.min(t1), t0.max(t1))
+            };
+            let (x0, x1) = (a.x + d.x * t0, a.x + d.x * t1);
+            for x in self.cols(x0.min(x1) - pad, x0.max(x1) + pad) {
+                let p = Pt {
+                    x: x as f64 + 0.5,
+                    y: yc,
+                };
+                let t = if len2 > 0.0 {
+                    (p.sub(a).dot(d) / len2).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let dist = p.sub(a.add(d.mul(t))).len2().sqrt();
+                let coverage = if antialias {
+                    (half + 0.5 - dist).clamp(0.0, 1.0)
+                } else if dist <= half {
+                    1.0
+                } else {
+                    0.0
+                };
+                self.blend(x, y, color, coverage);
+            }
+        }
+    }
+
+    /// Fill a disc of radius `r` around `c`.
+    pub(crate) fn draw_disc(&mut self, c: Pt, r: f64, color: u32, antialias: bool) {
+        if !c.x.is_finite() || !c.y.is_finite() || r <= 0.0 || r.is_nan() {
+            return;
+        }
+        let pad = if antialias { r + 0.5 } else { r };
+        for y in self.rows(c.y - pad, c.y + pad) {
+            for x in self.cols(c.x - pad, c.x + pad) {
+                let p = Pt {
+                    x: x as f64 + 0.5,
+                    y: y as f64 + 0.5,
+                };
+                let dist = p.sub(c).len2().sqrt();
+                let coverage = if antialias {
+                    (r + 0.5 - dist).clamp(0.0, 1.0)
+                } else if dist <= r {
+                    1.0
+                } else {
+                    0.0
+                };
+                self.blend(x, y, color, coverage);
+            }
+        }
+    }
+
+    fn draw_sites(&mut self, points: &[Pt], style: &RenderStyle) {
+        let r = style.site_radius as f64 * style.scale as f64;
+        for &p in points {
+            self.draw_disc(p, r, style.site_color, style.antialias);
+        }
+    }
+}
+
+/// Points scaled to pixels.
+fn scaled_points(points_flat: &[f32], scale: f32) -> Vec<Pt> {
+    read_points(points_flat)
+        .into_iter()
+        .map(|p| p.mul(scale as f64))
+        .collect()
+}
+
+/// Visit the pixels whose centres lie inside `poly` (even-odd rule).
+fn polygon_pixels(poly: &[Pt], width: usize, height: usize, mut visit: impl FnMut(usize, usize)) {
+    if poly.len() < 3 || poly.iter().any(|p| !p.x.is_finite() || !p.y.is_finite()) {
+        return;
+    }
+    let min_y = poly.iter().map(|p| p.y).fold(f64::INFINITY, f64::min);
+    let max_y = poly.iter().map(|p| p.y).fold(f64::NEG_INFINITY, f64::max);
+    let lo = (min_y - 0.5).ceil().max(0.0) as usize;
+    let hi = ((max_y - 0.5).ceil().min(height as f64)).max(0.0) as usize;
+    let mut xs = Vec::new();
+    for y in lo..hi.max(lo) {
+        let yc = y as f64 + 0.5;
+        xs.clear();
+        for k in 0..poly.len() {
+            let (a, b) = (poly[k], poly[(k + 1) % poly.len()]);
+            if (a.y <= yc) != (b.y <= yc) {
+                xs.push(a.x + (yc - a.y) / (b.y - a.y) * (b.x - a.x));
+            }
+        }
+        xs.sort_by(f64::total_cmp);
+        for span in xs.chunks_exact(2) {
+            // Centres in [span[0], span[1]).
+            let x0 = (span[0] - 0.5).ceil().max(0.0) as usize;
+            let x1 = ((span[1] - 0.5).ceil().min(width as f64)).max(0.0) as usize;
+            for x in x0..x1 {
+                visit(x, y);
+            }
+        }
+    }
+}
+
+/// Nearest finite site of every pixel centre; ties go to the lower index,
+/// and without finite sites every pixel is [`NO_SITE`]. The cells give the
+/// answer for almost all pixels; where rounding makes them overlap or leave
+/// a gap, the distances decide.
+fn pixel_sites(points: &[Pt], cells: &[LabelledCell], width: usize, height: usize) -> Vec<u32> {
+    let mut labels = vec![NO_SITE; width * height];
+    let finite: Vec<usize> = (0..points.len())
+        .filter(|&i| points[i].x.is_finite() && points[i].y.is_finite())
+        .collect();
+    let Some((&first, rest)) = finite.split_first() else {
+        return labels;
+    };
+    let center = |x: usize, y: usize| Pt {
+        x: x as f64 + 0.5,
+        y: y as f64 + 0.5,
+    };
+    let closer = |i: usize, j: u32, p: Pt| {
+        let (di, dj) = (p.sub(points[i]).len2(), p.sub(points[j as usize]).len2());
+        di < dj || (di == dj && i < j as usize)
+    };
+    for (i, cell) in cells.iter().enumerate() {
+        polygon_pixels(&cell.polygon, width, height, |x, y| {
+            let label = &mut labels[y * width + x];
+            if *label == NO_SITE || closer(i, *label, center(x, y)) {
+                *label = i as u32;
+            }
+        });
+    }
+    for (k, label) in labels.iter_mut().enumerate() {
+        if *label == NO_SITE {
+            let p = center(k % width, k / width);
+            *label = rest.iter().fold(first as u32, |best, &i| {
+                if closer(i, best, p) { i as u32 } else { best }
+            });
+        }
+    }
+    labels
+}
+
+/// Filled and outlined Voronoi cells clipped to the canvas, then the sites.
+pub(crate) fn render_voronoi(
+    points: &[Pt],
+    width: usize,
+    height: usize,
+    colors: &[u32],
+    style: &RenderStyle,
+) -> Canvas {
+    let mut canvas = Canvas::new(width, height, style.background);
+    let clip = rect_polygon(0.0, 0.0, width as f64, height as f64);
+    let cells = labelled_cells(&Triangulation::new(points), &clip);
+    let colors = if colors.is_empty() {
+        &PALETTE[..]
+    } else {
+        colors
+    };
+    let labels = pixel_sites(points, &cells, width, height);
+    for (k, &i) in labels.iter().enumerate() {
+        if i != NO_SITE {
+            canvas.blend(k % width, k / width, colors[i as usize % colors.len()], 1.0);
+        }
+    }
+    // Every edge once: from the lower site, or from the only cell along
+    // the canvas border.
+    let edge_width = style.edge_width as f64 * style.scale as f64;
+    for (i, cell) in cells.iter().enumerate() {
+        let n = cell.polygon.len();
+        for k in 0..n {
+            let j = cell.across[k];
+            if j == NONE || i < j {
+                let (a, b) = (cell.polygon[k], cell.polygon[(k + 1) % n]);
+                canvas.draw_line(a, b, edge_width, style.edge_color, style.antialias);
+            }
+        }
+    }
+    canvas.draw_sites(points, style);
+    canvas
+}
+
+/// Delaunay triangle edges, then the sites.
+pub(crate) fn render_delaunay(
+    points: &[Pt],
+    width: usize,
+    height: usize,
+    style: &RenderStyle,
+) -> Canvas {
+    let mut canvas = Canvas::new(width, height, style.background);
+    let mut edges: Vec<Edge> = Triangulation::new(points)
+        .triangles()
+        .iter()
+        .flat_map(|t| t.edges())
+        .collect();
+    // Sorted so that the blending order, and with it the image, is fixed.
+    edges.sort_unstable_by_key(|e| (e.a, e.b));
+    edges.dedup();
+    let edge_width = style.edge_width as f64 * style.scale as f64;
+    for e in edges {
+        canvas.draw_line(
+            points[e.a],
+            points[e.b],
+            edge_width,
+            style.edge_color,
+            style.antialias,
+        );
+    }
+    canvas.draw_sites(points, style);
+    canvas
+}
+
+#[wasm_bindgen]
+impl VoronoiTests {
+    /// Voronoi cells of the sites clipped to a `width` x `height` pixel
+    /// canvas, as an RGBA8 buffer for `putImageData`. Cell `i` is filled
+    /// with `colors[i % colors.len()]` (`0xRRGGBBAA`), or from a built-in
+    /// palette if `colors` is empty. Input layout: [x,y,vx,vy,...]
+    pub fn render_voronoi(
+        points_flat: &[f32],
+        width: u32,
+        height: u32,
+        colors: &[u32],
+        style: &RenderStyle,
+    ) -> Vec<u8> {
+        let points = scaled_points(points_flat, style.scale);
+        render_voronoi(&points, width as usize, height as usize, colors, style).pixels
+    }
+
+    /// Delaunay wireframe and sites on a `width` x `height` pixel canvas,
+    /// as an RGBA8 buffer for `putImageData`. Input layout: [x,y,vx,vy,...]
+    pub fn render_delaunay(
+        points_flat: &[f32],
+        width: u32,
+        height: u32,
+        style: &RenderStyle,
+    ) -> Vec<u8> {
+        let points = scaled_points(points_flat, style.scale);
+        render_delaunay(&points, width as usize, height as usize, style).pixels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voronoi::test_util::pt;
+    use std::path::PathBuf;
+
+    /// A handful of sites that exercise hull cells, a near-degenerate
+    /// quad and lines at many angles.
+    fn sites() -> Vec<Pt> {
+        [
+            (8.0, 6.0),
+            (30.5, 4.25),
+            (57.0, 9.0),
+            (88.0, 5.5),
+            (19.0, 27.0),
+            (44.0, 24.0),
+            (71.25, 30.0),
+            (5.0, 52.0),
+            (33.0, 45.5),
+            (60.0, 58.0),
+            (90.0, 47.0),
+            (47.0, 38.0),
+        ]
+        .into_iter()
+        .map(|(x, y)| pt(x, y))
+        .collect()
+    }
+
+    /// Compare with `tests/golden/<name>.pam`, or rewrite that file when
+    /// `UPDATE_GOLDEN` is set.
+    fn assert_golden(name: &str, canvas: &Canvas) {
+        let mut pam = format!(
+            "P7\nWIDTH {}\nHEIGHT {}\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n",
+            canvas.width, canvas.height
+        )
+        .into_bytes();
+        pam.extend_from_slice(&canvas.pixels);
+        let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden"]
+            .iter()
+            .collect::<PathBuf>()
+            .join(format!("{name}.pam"));
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, &pam).unwrap();
+            return;
+        }
+        let golden = std::fs::read(&path)
+            .unwrap_or_else(|e| panic!("{}: {e}; run with UPDATE_GOLDEN=1", path.display()));
+        let diff = golden.iter().zip(&pam).filter(|(a, b)| a != b).count();
+        assert!(
+            golden.len() == pam.len() && diff == 0,
+            "{name} differs from the golden image in {diff} bytes"
+        );
+    }
+
+    #[test]
+    fn voronoi_matches_golden() {
+        let style = RenderStyle {
+            background: 0x1010_18FF,
+            ..RenderStyle::default()
+        };
+        assert_golden("voronoi", &render_voronoi(&sites(), 96, 64, &[], &style));
+    }
+
+    #[test]
+    fn delaunay_matches_golden() {
+        let style = RenderStyle {
+            background: 0x1010_18FF,
+            edge_color: 0x66CC_FFFF,
+            edge_width: 1.5,
+            ..RenderStyle::default()
+        };
+        assert_golden("delaunay", &render_delaunay(&sites(), 96, 64, &style));
+        let aliased = RenderStyle {
+            antialias: false,
+            ..style
+        };
+        assert_golden(
+            "delaunay_aliased",
+            &render_delaunay(&sites(), 96, 64, &aliased),
+        );
+    }
+
+    #[test]
+    fn cells_cover_every_pixel_once() {
+        // Opaque cells over a transparent background leave no gaps, and
+        // half-transparent ones show no double-blended seams.
+        let style = RenderStyle {
+            edge_width: 0.0,
+            site_radius: 0.0,
+            ..RenderStyle::default()
+        };
+        let canvas = render_voronoi(&sites(), 96, 64, &[0x0000_FF80], &style);
+        assert!(canvas.pixels.chunks(4).all(|px| px == [0, 0, 255, 128]));
+
+        // A NaN site in front claims no pixels, also where the cells leave
+        // gaps to the distance fallback.
+        let mut points = vec![pt(f64::NAN, 10.0)];
+        points.extend(sites());
+        let canvas = render_voronoi(&points, 96, 64, &[0x0000_FF80], &style);
+        assert!(canvas.pixels.chunks(4).all(|px| px == [0, 0, 255, 128]));
+        let labels = pixel_sites(&points, &[], 96, 64);
+        for (k, &label) in labels.iter().enumerate() {
+            let p = pt((k % 96) as f64 + 0.5, (k / 96) as f64 + 0.5);
+            let best = (1..points.len())
+                .min_by(|&a, &b| p.sub(points[a]).len2().total_cmp(&p.sub(points[b]).len2()))
+                .unwrap();
+            assert_eq!(label, best as u32, "pixel {k}");
+        }
+        assert!(
+            pixel_sites(&points[..1], &[], 4, 4)
+                .iter()
+                .all(|&l| l == NO_SITE)
+        );
+    }
+}
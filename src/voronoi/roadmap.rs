@@ -0,0 +1,504 @@
+//! Path planning on the Voronoi diagram of obstacles.
+//!
+//! Every point of a Voronoi edge is equally far from the two obstacle sites
+//! it separates and farther from all others, so the edges form a roadmap
+//! that keeps as far from the obstacles as possible. The clearance along an
+//! edge is smallest where it passes its sites most closely, which is known
+//! exactly, so edges can be filtered by the clearance a path needs.
+//!
+//! Obstacle outlines are sampled into sites. A path that squeezes between
+//! two neighbouring samples comes within half their distance of them, so
+//! for sampled outlines only edges and connections with more clearance
+//! than half the largest sample gap are used; the spacing has to be well
+//! below the narrowest passage.
+//!
+//! Only the convex hull of the sites is covered, so a scene needs an
+//! outline around it.
+//!
+//! Start and goal are joined to the nearest roadmap vertices that they can
+//! reach in a straight line with enough clearance. The search then either
+//! finds the shortest path (A*), or the path whose tightest spot has the
+//! most clearance, and the shortest of those.
+
+use super::medial::{read_outlines, sample_outlines};
+use super::triangulation::{NONE, Triangulation};
+use super::{Pt, circumcircle, read_points};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use wasm_bindgen::prelude::*;
+
+/// Roadmap vertices a start or goal is joined to at most.
+const CONNECTIONS: usize = 8;
+/// Roadmap vertices tried for those connections, nearest first.
+const CONNECTION_CANDIDATES: usize = 32;
+
+/// What [`VoronoiRoadmap::find_path`] optimises.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathObjective {
+    /// The shortest path along the roadmap.
+    Shortest = 0,
+    /// The path whose smallest clearance on the roadmap is largest; the
+    /// shortest one among those.
+    Widest = 1,
+}
+
+/// A planned path. `clearance[k]` is the distance from point `k` to the
+/// nearest obstacle site; `min_clearance` is the smallest distance along
+/// the whole polyline, which can be less than at its points.
+#[wasm_bindgen(getter_with_clone)]
+pub struct RoadmapPath {
+    pub found: bool,
+    /// Path points [x0,y0,x1,y1,...] from start to goal.
+    #[wasm_bindgen(readonly)]
+    pub points: Vec<f32>,
+    #[wasm_bindgen(readonly)]
+    pub clearance: Vec<f32>,
+    pub length: f32,
+    pub min_clearance: f32,
+}
+
+impl RoadmapPath {
+    fn not_found() -> Self {
+        Self {
+            found: false,
+            points: Vec::new(),
+            clearance: Vec::new(),
+            length: 0.0,
+            min_clearance: 0.0,
+        }
+    }
+}
+
+struct RoadEdge {
+    a: usize,
+    b: usize,
+    length: f64,
+    /// Smallest distance to an obstacle site along the edge.
+    clearance: f64,
+}
+
+/// Heap entry ordered so that `BinaryHeap` pops the smallest key first.
+struct Label {
+    key: f64,
+    node: usize,
+}
+
+impl PartialEq for Label {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Label {}
+
+impl PartialOrd for Label {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Label {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .key
+            .total_cmp(&self.key)
+            .then(other.node.cmp(&self.node))
+    }
+}
+
+/// Distance from `p` to the segment `a`–`b`.
+fn segment_distance(p: Pt, a: Pt, b: Pt) -> f64 {
+    let d = b.sub(a);
+    let len2 = d.len2();
+    let t = if len2 > 0.0 {
+        (p.sub(a).dot(d) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    p.sub(a.add(d.mul(t))).len2().sqrt()
+}
+
+/// The Voronoi roadmap of a set of obstacles, reusable for many queries.
+#[wasm_bindgen]
+pub struct VoronoiRoadmap {
+    sites: Vec<Pt>,
+    nodes: Vec<Pt>,
+    node_clearance: Vec<f64>,
+    edges: Vec<RoadEdge>,
+    adjacency: Vec<Vec<usize>>,
+    /// Clearance that anything on a path has to exceed; half the largest
+    /// gap between outline samples, 0 for point obstacles.
+    wall_clearance: f64,
+}
+
+impl VoronoiRoadmap {
+    pub(crate) fn build(sites: Vec<Pt>, wall_clearance: f64) -> Self {
+        let sites: Vec<Pt> = sites
+            .into_iter()
+            .filter(|p| p.x.is_finite() && p.y.is_finite())
+            .collect();
+        let tri = Triangulation::new(&sites);
+        let mut node_of = vec![NONE; tri.tri_count()];
+        let mut nodes = Vec::new();
+        let mut node_clearance = Vec::new();
+        for (t, node) in node_of.iter_mut().enumerate() {
+            if !tri.is_solid(t) {
+                continue;
+            }
+            let [a, b, c] = tri.tri_verts(t);
+            if let Some(cc) = circumcircle(sites[a], sites[b], sites[c]) {
+                *node = nodes.len();
+                nodes.push(cc.c);
+                node_clearance.push(cc.r2.sqrt());
+            }
+        }
+
+        // One Voronoi edge per interior Delaunay edge, between the
+        // circumcenters on either side.
+        let mut edges = Vec::new();
+        for (e, &twin) in tri.twins.iter().enumerate() {
+            if twin == NONE || twin < e {
+                continue;
+            }
+            let (a, b) = (node_of[e / 3], node_of[twin / 3]);
+            if a == NONE || b == NONE {
+                continue;
+            }
+            let site = sites[tri.verts[e]];
+            edges.push(RoadEdge {
+                a,
+                b,
+                length: nodes[b].sub(nodes[a]).len2().sqrt(),
+                clearance: segment_distance(site, nodes[a], nodes[b]),
+            });
+        }
+        let mut adjacency = vec![Vec::new(); nodes.len()];
+        for (k, e) in edges.iter().enumerate() {
+            adjacency[e.a].push(k);
+            adjacency[e.b].push(k);
+        }
+        Self {
+            sites,
+            nodes,
+            node_clearance,
+            edges,
+            adjacency,
+            wall_clearance,
+        }
+    }
+
+    fn nearest_site_distance(&self, p: Pt) -> f64 {
+        self.sites
+            .iter()
+            .map(|s| s.sub(p).len2())
+            .fold(f64::INFINITY, f64::min)
+            .sqrt()
+    }
+
+    /// Smallest distance to an obstacle site along the segment `a`–`b`.
+    fn segment_clearance(&self, a: Pt, b: Pt) -> f64 {
+        self.sites
+            .iter()
+            .map(|&s| segment_distance(s, a, b))
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    fn clear(&self, clearance: f64, min_clearance: f64) -> bool {
+        clearance >= min_clearance && clearance > self.wall_clearance
+    }
+
+    /// Connections from `p` (as node `id`) to the nearest reachable
+    /// roadmap vertices.
+    fn connect(&self, p: Pt, id: usize, min_clearance: f64) -> Vec<RoadEdge> {
+        let mut order: Vec<usize> = (0..self.nodes.len())
+            .filter(|&v| self.clear(self.node_clearance[v], min_clearance))
+            .collect();
+        order.sort_by(|&u, &v| {
+            let du = self.nodes[u].sub(p).len2();
+            let dv = self.nodes[v].sub(p).len2();
+            du.total_cmp(&dv).then(u.cmp(&v))
+        });
+        order
+            .into_iter()
+            .take(CONNECTION_CANDIDATES)
+            .filter_map(|v| {
+                let clearance = self.segment_clearance(p, self.nodes[v]);
+                self.clear(clearance, min_clearance).then(|| RoadEdge {
+                    a: id,
+                    b: v,
+                    length: self.nodes[v].sub(p).len2().sqrt(),
+                    clearance,
+                })
+            })
+            .take(CONNECTIONS)
+            .collect()
+    }
+
+    pub(crate) fn plan(
+        &self,
+        start: Pt,
+        goal: Pt,
+        min_clearance: f64,
+        objective: PathObjective,
+    ) -> RoadmapPath {
+        let (start_clearance, goal_clearance) = (
+            self.nearest_site_distance(start),
+            self.nearest_site_distance(goal),
+        );
+        if !self.clear(start_clearance, min_clearance) || !self.clear(goal_clearance, min_clearance)
+        {
+            return RoadmapPath::not_found();
+        }
+
+        // Nodes `n` and `n + 1` are the start and the goal; their edges
+        // follow the roadmap edges.
+        let n = self.nodes.len();
+        let (s, g) = (n, n + 1);
+        let mut extra = self.connect(start, s, min_clearance);
+        extra.extend(self.connect(goal, g, min_clearance));
+        if objective == PathObjective::Shortest {
+            let clearance = self.segment_clearance(start, goal);
+            if self.clear(clearance, min_clearance) {
+                extra.push(RoadEdge {
+                    a: s,
+                    b: g,
+                    length: goal.sub(start).len2().sqrt(),
+                    clearance,
+                });
+            }
+        }
+        let edge = |k: usize| {
+            if k < self.edges.len() {
+                &self.edges[k]
+            } else {
+                &extra[k - self.edges.len()]
+            }
+        };
+        let mut adjacency: Vec<&[usize]> = self.adjacency.iter().map(Vec::as_slice).collect();
+        let mut extra_adjacency = vec![Vec::new(); n + 2];
+        for (k, e) in extra.iter().enumerate() {
+            extra_adjacency[e.a].push(self.edges.len() + k);
+            extra_adjacency[e.b].push(self.edges.len() + k);
+        }
+        adjacency.extend([&[][..], &[][..]]);
+        let incident = |u: usize| adjacency[u].iter().chain(&extra_adjacency[u]).copied();
+        let pos = |u: usize| match u {
+            _ if u == s => start,
+            _ if u == g => goal,
+            _ => self.nodes[u],
+        };
+
+        // Widest path first: the largest bottleneck any path can have.
+        let mut floor = min_clearance;
+        if objective == PathObjective::Widest {
+            let mut best = vec![f64::NEG_INFINITY; n + 2];
+            best[s] = f64::INFINITY;
+            let mut heap = BinaryHeap::from([Label {
+                key: f64::NEG_INFINITY,
+                node: s,
+            }]);
+            while let Some(Label { key, node: u }) = heap.pop() {
+                if -key < best[u] {
+                    continue;
+                }
+                for k in incident(u) {
+                    let e = edge(k);
+                    if !self.clear(e.clearance, min_clearance) {
+                        continue;
+                    }
+                    let v = if e.a == u { e.b } else { e.a };
+                    let width = best[u].min(e.clearance);
+                    if width > best[v] {
+                        best[v] = width;
+                        heap.push(Label {
+                            key: -width,
+                            node: v,
+                        });
+                    }
+                }
+            }
+            if best[g] == f64::NEG_INFINITY {
+                return RoadmapPath::not_found();
+            }
+            floor = floor.max(best[g]);
+        }
+
+        // A* over the edges that keep the clearance.
+        let usable = |e: &RoadEdge| self.clear(e.clearance, min_clearance) && e.clearance >= floor;
+        let mut dist = vec![f64::INFINITY; n + 2];
+        let mut via = vec![NONE; n + 2];
+        dist[s] = 0.0;
+        let heuristic = |u: usize| pos(u).sub(goal).len2().sqrt();
+        let mut heap = BinaryHeap::from([Label {
+            key: heuristic(s),
+            node: s,
+        }]);
+        while let Some(Label { key, node: u }) = heap.pop() {
+            if u == g {
+                break;
+            }
+            if key > dist[u] + heuristic(u) {
+                continue;
+            }
+            for k in incident(u) {
+                let e = edge(k);
+                if !usable(e) {
+                    continue;
+                }
+                let v = if e.a == u { e.b } else { e.a };
+                let d = dist[u] + e.length;
+                if d < dist[v] {
+                    dist[v] = d;
+                    via[v] = k;
+                    heap.push(Label {
+                        key: d + heuristic(v),
+                        node: v,
+                    });
+                }
+            }
+        }
+        if via[g] == NONE {
+            return RoadmapPath::not_found();
+        }
+
+        let mut nodes = vec![g];
+        let mut min = f64::INFINITY;
+        while let Some(&u) = nodes.last().filter(|&&u| u != s) {
+            let e = edge(via[u]);
+            min = min.min(e.clearance);
+            nodes.push(if e.a == u { e.b } else { e.a });
+        }
+        nodes.reverse();
+        RoadmapPath {
+            found: true,
+            points: nodes
+                .iter()
+                .flat_map(|&u| [pos(u).x as f32, pos(u).y as f32])
+                .collect(),
+            clearance: nodes
+                .iter()
+                .map(|&u| match u {
+                    _ if u == s => start_clearance as f32,
+                    _ if u == g => goal_clearance as f32,
+                    _ => self.node_clearance[u] as f32,
+                })
+                .collect(),
+            length: dist[g] as f32,
+            min_clearance: min as f32,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl VoronoiRoadmap {
+    /// Roadmap around point obstacles. Input layout: [x,y,vx,vy,...]
+    #[wasm_bindgen(constructor)]
+    pub fn new(points_flat: &[f32]) -> VoronoiRoadmap {
+        VoronoiRoadmap::build(read_points(points_flat), 0.0)
+    }
+
+    /// Roadmap around polygonal obstacles, sampled every `spacing` along
+    /// their outlines, or coarser if that would take more than about a
+    /// million samples. Polygon `i` has the points
+    /// `offsets[i]..offsets[i + 1]` of `coords` = [x0,y0,x1,y1,...], the
+    /// layout of `VoronoiCells`.
+    pub fn from_outlines(coords: &[f32], offsets: &[u32], spacing: f32) -> VoronoiRoadmap {
+        let samples = sample_outlines(&read_outlines(coords, offsets), spacing as f64);
+        VoronoiRoadmap::build(samples.points, samples.max_gap * 0.5)
+    }
+
+    /// The obstacle sites, [x0,y0,x1,y1,...].
+    pub fn sites(&self) -> Vec<f32> {
+        self.sites
+            .iter()
+            .flat_map(|p| [p.x as f32, p.y as f32])
+            .collect()
+    }
+
+    /// Roadmap edges with at least `min_clearance` as line segments
+    /// [x1,y1,x2,y2,...].
+    pub fn edges(&self, min_clearance: f32) -> Vec<f32> {
+        self.edges
+            .iter()
+            .filter(|e| self.clear(e.clearance, min_clearance as f64))
+            .flat_map(|e| {
+                let (a, b) = (self.nodes[e.a], self.nodes[e.b]);
+                [a.x as f32, a.y as f32, b.x as f32, b.y as f32]
+            })
+            .collect()
+    }
+
+    /// Path from start to goal that keeps at least `min_clearance` from
+    /// every obstacle site; `found` is `false` if there is none.
+    pub fn find_path(
+        &self,
+        start_x: f32,
+        start_y: f32,
+        goal_x: f32,
+        goal_y: f32,
+        min_clearance: f32,
+        objective: PathObjective,
+    ) -> RoadmapPath {
+        let start = Pt {
+            x: start_x as f64,
+            y: start_y as f64,
+        };
+        let goal = Pt {
+            x: goal_x as f64,
+            y: goal_y as f64,
+        };
+        self.plan(start, goal, min_clearance as f64, objective)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voronoi::test_util::pt;
+
+    #[test]
+    fn paths_go_through_the_widest_gap() {
+        // A 100 x 100 room split by a wall across x = 50 with a narrow gap
+        // at y = 20 and a wide one at y = 70.
+        let rect = |x0: f64, y0: f64, x1: f64, y1: f64| {
+            vec![pt(x0, y0), pt(x1, y0), pt(x1, y1), pt(x0, y1)]
+        };
+        let polygons = [
+            rect(0.0, 0.0, 100.0, 100.0),
+            rect(48.0, 0.0, 52.0, 16.0),
+            rect(48.0, 24.0, 52.0, 60.0),
+            rect(48.0, 80.0, 52.0, 100.0),
+        ];
+        let samples = sample_outlines(&polygons, 1.0);
+        assert!(samples.max_gap <= 1.0);
+        let roadmap = VoronoiRoadmap::build(samples.points, samples.max_gap * 0.5);
+        let (start, goal) = (pt(10.0, 20.0), pt(90.0, 20.0));
+
+        let shortest = roadmap.plan(start, goal, 1.0, PathObjective::Shortest);
+        assert!(shortest.found);
+        let crosses_at = |path: &RoadmapPath| {
+            let ys: Vec<f32> = path.points.chunks(2).map(|p| p[1]).collect();
+            let xs: Vec<f32> = path.points.chunks(2).map(|p| p[0]).collect();
+            (1..xs.len())
+                .find(|&k| xs[k - 1] < 50.0 && xs[k] >= 50.0)
+                .map(|k| (ys[k - 1] + ys[k]) / 2.0)
+                .unwrap()
+        };
+        assert!((crosses_at(&shortest) - 20.0).abs() < 3.0);
+        assert!(shortest.min_clearance >= 1.0 && shortest.min_clearance <= 4.0);
+
+        let widest = roadmap.plan(start, goal, 1.0, PathObjective::Widest);
+        assert!(widest.found);
+        assert!((crosses_at(&widest) - 70.0).abs() < 3.0);
+        assert!(widest.min_clearance > 5.0);
+        assert!(widest.length > shortest.length);
+
+        // Neither gap is wide enough for a clearance of 12.
+        assert!(
+            !roadmap
+                .plan(start, goal, 12.0, PathObjective::Shortest)
+                .found
+        );
+    }
+}
@@ -0,0 +1,391 @@
+//! Delaunay triangulation and Voronoi cells on the unit sphere.
+//!
+//! The spherical Delaunay triangulation is the convex hull of the points in
+//! 3D. Rather than building a 3D hull, the sphere is turned so that the
+//! first site sits at the north pole and the others are projected
+//! stereographically from there onto the plane. The projection maps circles
+//! on the sphere to circles in the plane, so the planar Delaunay
+//! triangulation of the projected sites is exactly the set of spherical
+//! Delaunay triangles that do not touch the pole site; the remaining ones
+//! fan from the pole site to the planar convex hull.
+//!
+//! The circumcenter of a spherical triangle is the normal of its plane, and
+//! the Voronoi cell of a site is the polygon of the circumcenters of its
+//! triangles, joined by minor great-circle arcs.
+
+use super::triangulation::Triangulation;
+use super::{Pt, VoronoiTests};
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct V3 {
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+impl V3 {
+    fn sub(self, o: V3) -> V3 {
+        V3 {
+            x: self.x - o.x,
+            y: self.y - o.y,
+            z: self.z - o.z,
+        }
+    }
+
+    fn dot(self, o: V3) -> f64 {
+        self.x * o.x + self.y * o.y + self.z * o.z
+    }
+
+    fn cross(self, o: V3) -> V3 {
+        V3 {
+            x: self.y * o.z - self.z * o.y,
+            y: self.z * o.x - self.x * o.z,
+            z: self.x * o.y - self.y * o.x,
+        }
+    }
+
+    /// `self` scaled to unit length, or `None` for zero and non-finite
+    /// vectors.
+    fn normalized(self) -> Option<V3> {
+        let len = self.dot(self).sqrt();
+        (len > 0.0 && len.is_finite()).then(|| V3 {
+            x: self.x / len,
+            y: self.y / len,
+            z: self.z / len,
+        })
+    }
+
+    fn from_lon_lat(lon: f64, lat: f64) -> V3 {
+        let (lon, lat) = (lon.to_radians(), lat.to_radians());
+        V3 {
+            x: lat.cos() * lon.cos(),
+            y: lat.cos() * lon.sin(),
+            z: lat.sin(),
+        }
+    }
+
+    fn lon_lat(self) -> (f64, f64) {
+        (
+            self.y.atan2(self.x).to_degrees(),
+            self.z.clamp(-1.0, 1.0).asin().to_degrees(),
+        )
+    }
+}
+
+/// Spherical Delaunay triangles and Voronoi cells.
+///
+/// Triangles are counter-clockwise seen from outside the sphere. Triangle
+/// `t` has its circumcenter at `centers[3t..3t + 3]` (a unit vector), or at
+/// `centers_lon_lat[2t..2t + 2]` in degrees. The cell of site `i` consists
+/// of the circumcenters of the triangles `cells[cell_offsets[i]..cell_offsets[i + 1]]`,
+/// counter-clockwise seen from outside; consecutive corners are joined by
+/// the shorter great-circle arc. Duplicate and invalid sites get an empty
+/// cell.
+#[wasm_bindgen(getter_with_clone)]
+pub struct SphericalVoronoi {
+    #[wasm_bindgen(readonly)]
+    pub triangles: Vec<u32>,
+    #[wasm_bindgen(readonly)]
+    pub centers: Vec<f32>,
+    #[wasm_bindgen(readonly)]
+    pub centers_lon_lat: Vec<f32>,
+    #[wasm_bindgen(readonly)]
+    pub cells: Vec<u32>,
+    #[wasm_bindgen(readonly)]
+    pub cell_offsets: Vec<u32>,
+}
+
+/// `1 - cos` of the angle below which a site coincides with the pole.
+const POLE_EPS: f64 = 1e-12;
+
+/// Counter-clockwise (from outside) Delaunay triangles of the unit vectors
+/// `points`; `None` entries are left out. Empty if the sites do not span
+/// the sphere, e.g. when they all lie on one great circle.
+pub(crate) fn spherical_delaunay(points: &[Option<V3>]) -> Vec<[usize; 3]> {
+    let Some(pole_id) = points.iter().position(Option::is_some) else {
+        return Vec::new();
+    };
+    let pole = points[pole_id].expect("pole site");
+
+    // A right-handed frame (u, v, pole).
+    let helper = if pole.x.abs() < 0.9 {
+        V3 {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        }
+    } else {
+        V3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        }
+    };
+    let u = helper
+        .cross(pole)
+        .normalized()
+        .expect("helper not parallel");
+    let v = pole.cross(u);
+
+    // Stereographic projection from the pole; the pole site and its
+    // duplicates have no image. Rounding can leave them a tiny positive
+    // denominator, so sites within about 1e-6 radians of the pole count as
+    // duplicates rather than being thrown far out into the plane.
+    let mut ids = Vec::new();
+    let mut plane = Vec::new();
+    for (i, p) in points.iter().enumerate() {
+        let Some(p) = *p else { continue };
+        let denom = 1.0 - p.dot(pole);
+        if denom <= POLE_EPS {
+            continue;
+        }
+        ids.push(i);
+        plane.push(Pt {
+            x: p.dot(u) / denom,
+            y: p.dot(v) / denom,
+        });
+    }
+    let tri = Triangulation::new(&plane);
+    if !tri.has_triangles() {
+        return Vec::new();
+    }
+
+    // Seen from outside, the projection mirrors the sphere, so the planar
+    // counter-clockwise triangles turn around.
+    let mut out: Vec<[usize; 3]> = tri
+        .triangles()
+        .into_iter()
+        .map(|t| [ids[t.a], ids[t.c], ids[t.b]])
+        .collect();
+    let hull = tri.hull();
+    for (k, &a) in hull.iter().enumerate() {
+        let b = hull[(k + 1) % hull.len()];
+        out.push([pole_id, ids[a], ids[b]]);
+    }
+    out
+}
+
+/// For every site the triangles around it, counter-clockwise from outside.
+fn cells_around(sites: usize, triangles: &[[usize; 3]]) -> Vec<Vec<usize>> {
+    // Directed edge a -> b to the triangle that contains it.
+    let mut edge_tri: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut first = vec![usize::MAX; sites];
+    for (t, &[a, b, c]) in triangles.iter().enumerate() {
+        for (x, y) in [(a, b), (b, c), (c, a)] {
+            edge_tri.insert((x, y), t);
+            first[x] = first[x].min(t);
+        }
+    }
+    (0..sites)
+        .map(|i| {
+            let mut ring = Vec::new();
+            let start = first[i];
+            if start == usize::MAX {
+                return ring;
+            }
+            let mut t = start;
+            loop {
+                ring.push(t);
+                // In (i, b, c) the next triangle around i shares i -> c.
+                let tri = triangles[t];
+                let k = tri.iter().position(|&v| v == i).expect("site in triangle");
+                let c = tri[(k + 2) % 3];
+                match edge_tri.get(&(i, c)) {
+                    Some(&n) if n != start => t = n,
+                    _ => break,
+                }
+            }
+            ring
+        })
+        .collect()
+}
+
+fn spherical_voronoi(points: &[Option<V3>]) -> SphericalVoronoi {
+    let triangles = spherical_delaunay(points);
+    let mut centers = Vec::with_capacity(triangles.len() * 3);
+    let mut centers_lon_lat = Vec::with_capacity(triangles.len() * 2);
+    for &[a, b, c] in &triangles {
+        let [a, b, c] = [a, b, c].map(|i| points[i].expect("triangle of valid sites"));
+        // The plane normal points outwards for counter-clockwise triangles.
+        let center = b.sub(a).cross(c.sub(a)).normalized().unwrap_or(a);
+        let (lon, lat) = center.lon_lat();
+        centers.extend([center.x as f32, center.y as f32, center.z as f32]);
+        centers_lon_lat.extend([lon as f32, lat as f32]);
+    }
+    let mut cells = Vec::new();
+    let mut cell_offsets = vec![0];
+    for ring in cells_around(points.len(), &triangles) {
+        cells.extend(ring.into_iter().map(|t| t as u32));
+        cell_offsets.push(cells.len() as u32);
+    }
+    SphericalVoronoi {
+        triangles: triangles.into_iter().flatten().map(|i| i as u32).collect(),
+        centers,
+        centers_lon_lat,
+        cells,
+        cell_offsets,
+    }
+}
+
+#[wasm_bindgen]
+impl VoronoiTests {
+    /// Spherical Delaunay triangulation and Voronoi cells of points given
+    /// as [x0,y0,z0,x1,y1,z1,...]. The vectors are normalized; zero ones
+    /// are left out.
+    pub fn spherical_voronoi(points: &[f32]) -> SphericalVoronoi {
+        let points: Vec<Option<V3>> = points
+            .chunks_exact(3)
+            .map(|c| {
+                V3 {
+                    x: c[0] as f64,
+                    y: c[1] as f64,
+                    z: c[2] as f64,
+                }
+                .normalized()
+            })
+            .collect();
+        spherical_voronoi(&points)
+    }
+
+    /// [`VoronoiTests::spherical_voronoi`] for points given as
+    /// [lon0,lat0,lon1,lat1,...] in degrees.
+    pub fn spherical_voronoi_lon_lat(lon_lat: &[f32]) -> SphericalVoronoi {
+        let points: Vec<Option<V3>> = lon_lat
+            .chunks_exact(2)
+            .map(|c| {
+                let p = V3::from_lon_lat(c[0] as f64, c[1] as f64);
+                p.normalized()
+            })
+            .collect();
+        spherical_voronoi(&points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `n` random points on a sphere of radius 3, as [x,y,z,...].
+    fn random_sphere(n: usize, seed: u32) -> Vec<f32> {
+        let mut s = seed;
+        let mut out = Vec::new();
+        for _ in 0..n {
+            let z = 2.0 * crate::rand::frand01(&mut s) as f64 - 1.0;
+            let phi = crate::rand::frand01(&mut s) as f64 * std::f64::consts::TAU;
+            let r = (1.0 - z * z).sqrt();
+            out.extend([r * phi.cos(), r * phi.sin(), z].map(|c| (3.0 * c) as f32));
+        }
+        out
+    }
+
+    fn unit(xyz: &[f32], i: u32) -> V3 {
+        let i = i as usize;
+        let v = V3 {
+            x: xyz[3 * i] as f64,
+            y: xyz[3 * i + 1] as f64,
+            z: xyz[3 * i + 2] as f64,
+        };
+        v.normalized().unwrap()
+    }
+
+    /// Triangle count, orientation and empty circumcaps for `n` distinct
+    /// sites among `xyz`.
+    fn assert_delaunay(xyz: &[f32], n: usize, result: &SphericalVoronoi) {
+        let sites = xyz.len() / 3;
+        let valid: Vec<u32> = (0..sites as u32)
+            .filter(|&i| {
+                xyz[3 * i as usize..3 * i as usize + 3]
+                    .iter()
+                    .any(|&c| c != 0.0)
+            })
+            .collect();
+        let t = result.triangles.len() / 3;
+        assert_eq!(t, 2 * n - 4, "Euler: {n} sites");
+        // Seen from outside, every edge is walked once in each direction.
+        let mut edges = HashMap::new();
+        for tri in result.triangles.chunks(3) {
+            for k in 0..3 {
+                *edges.entry((tri[k], tri[(k + 1) % 3])).or_insert(0) += 1;
+            }
+        }
+        for (&(a, b), &count) in &edges {
+            assert_eq!(count, 1, "edge {a} -> {b}");
+            assert_eq!(edges.get(&(b, a)), Some(&1), "edge {b} -> {a}");
+        }
+        for (k, tri) in result.triangles.chunks(3).enumerate() {
+            let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| unit(xyz, i));
+            let center = V3 {
+                x: result.centers[3 * k] as f64,
+                y: result.centers[3 * k + 1] as f64,
+                z: result.centers[3 * k + 2] as f64,
+            };
+            // Counter-clockwise seen from the circumcenter. A triangle is
+            // counter-clockwise around the origin too unless its cap is
+            // larger than a hemisphere, when all sites are on one side.
+            let cap = a.dot(center);
+            assert!(b.sub(a).cross(c.sub(a)).dot(center) > 0.0, "triangle {k}");
+            assert_eq!(a.dot(b.cross(c)) > 0.0, cap > 0.0, "triangle {k}");
+            for &i in &valid {
+                assert!(
+                    unit(xyz, i).dot(center) <= cap + 1e-5,
+                    "site {i} in cap {k}"
+                );
+            }
+        }
+        // Every triangle corner is the corner of three cells.
+        assert_eq!(result.cells.len(), 3 * t);
+        assert_eq!(result.cell_offsets.len(), sites + 1);
+    }
+
+    #[test]
+    fn random_sites_are_delaunay() {
+        for n in 4..=12 {
+            for seed in 1..20u32 {
+                let xyz = random_sphere(n, seed * 31 + n as u32);
+                let result = VoronoiTests::spherical_voronoi(&xyz);
+                assert_delaunay(&xyz, n, &result);
+                let cells = &result.cell_offsets;
+                assert!(
+                    (0..n).all(|i| cells[i + 1] > cells[i]),
+                    "n = {n}: empty cell"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn duplicates_and_zero_vectors_get_empty_cells() {
+        for n in 4..=12 {
+            let mut xyz = random_sphere(n, n as u32);
+            // A zero vector, a copy of the first site (the projection pole),
+            // a scaled copy of the third, and the first again.
+            xyz.extend([0.0, 0.0, 0.0]);
+            xyz.extend_from_within(..3);
+            xyz.extend(xyz[6..9].iter().map(|c| c * 0.5).collect::<Vec<_>>());
+            xyz.extend_from_within(..3);
+            let result = VoronoiTests::spherical_voronoi(&xyz);
+            assert_delaunay(&xyz, n, &result);
+            let cells = &result.cell_offsets;
+            for i in n..n + 4 {
+                assert_eq!(cells[i + 1], cells[i], "n = {n}: site {i} has a cell");
+            }
+            assert!(result.triangles.iter().all(|&i| (i as usize) < n));
+        }
+    }
+
+    #[test]
+    fn octahedron() {
+        let result = VoronoiTests::spherical_voronoi_lon_lat(&[
+            0.0, 90.0, 0.0, -90.0, 0.0, 0.0, 90.0, 0.0, 180.0, 0.0, -90.0, 0.0,
+        ]);
+        assert_eq!(result.triangles.len(), 3 * 8);
+        // The circumcenters are the corners of a cube, at latitude ±35.26°.
+        for c in result.centers_lon_lat.chunks(2) {
+            assert!((c[1].abs() - 35.264_39).abs() < 1e-3, "{c:?}");
+            assert!((c[0].abs() % 90.0 - 45.0).abs() < 1e-3, "{c:?}");
+        }
+        assert!(result.cell_offsets.windows(2).all(|w| w[1] - w[0] == 4));
+    }
+}
@@ -0,0 +1,266 @@
+//! Per-cell statistics of the clipped Voronoi diagram.
+//!
+//! The cells come from [`labelled_cells`], where every edge remembers the
+//! site whose bisector produced it, so the neighbours of a cell are the
+//! sites it shares an edge of positive length with. Delaunay neighbours
+//! whose common edge lies outside the clip polygon are not neighbours of
+//! the clipped cell.
+
+use super::cells::{
+    LabelledCell, labelled_cells, polygon_centroid, read_convex_polygon, rect_polygon, signed_area,
+};
+use super::degenerate::TriangulationError;
+use super::triangulation::{NONE, Triangulation};
+use super::{Pt, VoronoiTests, circumcircle, read_points};
+use wasm_bindgen::prelude::*;
+
+/// Statistics of every site's clipped Voronoi cell.
+///
+/// Site `i` has its centroid at `centroids[2i..2i + 2]` and its smallest
+/// enclosing circle at `bounding_circles[3i..3i + 3]` as `[x, y, r]`; both
+/// are NaN for sites without a cell (duplicates, or cells outside the clip
+/// polygon). Its neighbours are `neighbors[neighbor_offsets[i]..neighbor_offsets[i + 1]]`,
+/// counter-clockwise around the cell.
+#[wasm_bindgen(getter_with_clone)]
+pub struct CellStats {
+    #[wasm_bindgen(readonly)]
+    pub areas: Vec<f32>,
+    #[wasm_bindgen(readonly)]
+    pub perimeters: Vec<f32>,
+    #[wasm_bindgen(readonly)]
+    pub centroids: Vec<f32>,
+    #[wasm_bindgen(readonly)]
+    pub bounding_circles: Vec<f32>,
+    #[wasm_bindgen(readonly)]
+    pub neighbor_counts: Vec<u32>,
+    #[wasm_bindgen(readonly)]
+    pub neighbors: Vec<u32>,
+    #[wasm_bindgen(readonly)]
+    pub neighbor_offsets: Vec<u32>,
+}
+
+impl LabelledCell {
+    /// Sites across edges of positive length, counter-clockwise.
+    fn neighbors(&self) -> Vec<usize> {
+        let n = self.polygon.len();
+        let mut out: Vec<usize> = Vec::new();
+        for k in 0..n {
+            let j = self.across[k];
+            if j != NONE && self.polygon[k] != self.polygon[(k + 1) % n] && !out.contains(&j) {
+                out.push(j);
+            }
+        }
+        out
+    }
+
+    fn perimeter(&self) -> f64 {
+        let n = self.polygon.len();
+        (0..n)
+            .map(|k| self.polygon[(k + 1) % n].sub(self.polygon[k]).len2().sqrt())
+            .sum()
+    }
+}
+
+/// Smallest circle enclosing `points` (Welzl's algorithm in its
+/// incremental form), as center and radius.
+pub(crate) fn enclosing_circle(points: &[Pt]) -> Option<(Pt, f64)> {
+    let inside = |c: Pt, r2: f64, p: Pt| p.sub(c).len2() <= r2 * (1.0 + 1e-12);
+    let diameter = |a: Pt, b: Pt| (a.add(b).mul(0.5), b.sub(a).len2() * 0.25);
+    let (mut c, mut r2) = (*points.first()?, 0.0);
+    for i in 1..points.len() {
+        if inside(c, r2, points[i]) {
+            continue;
+        }
+        (c, r2) = (points[i], 0.0);
+        for j in 0..i {
+            if inside(c, r2, points[j]) {
+                continue;
+            }
+            (c, r2) = diameter(points[i], points[j]);
+            for k in 0..j {
+                if inside(c, r2, points[k]) {
+                    continue;
+                }
+                (c, r2) = match circumcircle(points[i], points[j], points[k]) {
+                    Some(cc) => (cc.c, cc.r2),
+                    // Collinear: the outermost two span the circle.
+                    None => [
+                        diameter(points[i], points[j]),
+                        diameter(points[j], points[k]),
+                        diameter(points[k], points[i]),
+                    ]
+                    .into_iter()
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+                    .expect("three candidates"),
+                };
+            }
+        }
+    }
+    Some((c, r2.sqrt()))
+}
+
+fn cell_stats(cells: &[LabelledCell]) -> CellStats {
+    let mut stats = CellStats {
+        areas: Vec::with_capacity(cells.len()),
+        perimeters: Vec::with_capacity(cells.len()),
+        centroids: Vec::with_capacity(cells.len() * 2),
+        bounding_circles: Vec::with_capacity(cells.len() * 3),
+        neighbor_counts: Vec::with_capacity(cells.len()),
+        neighbors: Vec::new(),
+        neighbor_offsets: vec![0],
+    };
+    for cell in cells {
+        // `+ 0.0` turns the -0 of an empty sum into 0.
+        stats.areas.push((signed_area(&cell.polygon) + 0.0) as f32);
+        stats.perimeters.push(cell.perimeter() as f32);
+        let centroid = polygon_centroid(&cell.polygon).unwrap_or(Pt {
+            x: f64::NAN,
+            y: f64::NAN,
+        });
+        stats
+            .centroids
+            .extend([centroid.x as f32, centroid.y as f32]);
+        let (c, r) = enclosing_circle(&cell.polygon).unwrap_or((centroid, f64::NAN));
+        stats
+            .bounding_circles
+            .extend([c.x as f32, c.y as f32, r as f32]);
+        let neighbors = cell.neighbors();
+        stats.neighbor_counts.push(neighbors.len() as u32);
+        stats
+            .neighbors
+            .extend(neighbors.into_iter().map(|j| j as u32));
+        stats.neighbor_offsets.push(stats.neighbors.len() as u32);
+    }
+    stats
+}
+
+fn stats_for(points_flat: &[f32], clip: &[Pt]) -> CellStats {
+    let tri = Triangulation::new(&read_points(points_flat));
+    cell_stats(&labelled_cells(&tri, clip))
+}
+
+#[wasm_bindgen]
+impl VoronoiTests {
+    /// Area, perimeter, centroid, bounding circle and neighbours of every
+    /// site's Voronoi cell, clipped to the rectangle
+    /// `[min_x, max_x] x [min_y, max_y]`. Input layout: [x,y,vx,vy,...]
+    pub fn voronoi_cell_stats(
+        points_flat: &[f32],
+        min_x: f32,
+        min_y: f32,
+        max_x: f32,
+        max_y: f32,
+    ) -> CellStats {
+        let clip = rect_polygon(min_x as f64, min_y as f64, max_x as f64, max_y as f64);
+        stats_for(points_flat, &clip)
+    }
+
+    /// [`VoronoiTests::voronoi_cell_stats`] with the cells clipped to a
+    /// convex polygon given as [x0,y0,x1,y1,...] in either winding order.
+    /// Fails if the boundary is not convex.
+    pub fn voronoi_cell_stats_clipped(
+        points_flat: &[f32],
+        boundary: &[f32],
+    ) -> Result<CellStats, TriangulationError> {
+        let clip = read_convex_polygon(boundary)?;
+        Ok(stats_for(points_flat, &clip))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voronoi::test_util::{pt, random_points};
+
+    /// Areas add up to the clip polygon, every neighbour lists the other
+    /// one back, and every cell lies in its bounding circle, which is no
+    /// larger than the circle around the centroid through the farthest
+    /// corner.
+    fn assert_stats(points: &[Pt], clip: &[Pt]) -> CellStats {
+        let cells = labelled_cells(&Triangulation::new(points), clip);
+        let stats = cell_stats(&cells);
+        let total: f64 = stats.areas.iter().map(|&a| a as f64).sum();
+        let expected = signed_area(clip);
+        assert!(
+            (total - expected).abs() < 1e-4 * expected,
+            "{total} vs {expected}"
+        );
+        let around = |i: usize| {
+            let (a, b) = (stats.neighbor_offsets[i], stats.neighbor_offsets[i + 1]);
+            &stats.neighbors[a as usize..b as usize]
+        };
+        for i in 0..points.len() {
+            assert_eq!(around(i).len(), stats.neighbor_counts[i] as usize);
+            for &j in around(i) {
+                assert!(around(j as usize).contains(&(i as u32)), "{i} -> {j}");
+            }
+        }
+        for (i, cell) in cells
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| !c.polygon.is_empty())
+        {
+            let b = &stats.bounding_circles[3 * i..3 * i + 3];
+            let (c, r) = (pt(b[0] as f64, b[1] as f64), b[2] as f64);
+            let centroid = pt(
+                stats.centroids[2 * i] as f64,
+                stats.centroids[2 * i + 1] as f64,
+            );
+            let mut farthest: f64 = 0.0;
+            for &p in &cell.polygon {
+                assert!(p.sub(c).len2().sqrt() <= r + 1e-4, "{i}: {p:?} outside");
+                farthest = farthest.max(p.sub(centroid).len2().sqrt());
+            }
+            assert!(r <= farthest + 1e-4, "{i}: radius {r} > {farthest}");
+        }
+        stats
+    }
+
+    #[test]
+    fn areas_fill_the_clip_and_neighbors_are_symmetric() {
+        // Some sites lie outside the clip polygon.
+        let mut points: Vec<Pt> = random_points(200, 11, 140.0)
+            .into_iter()
+            .map(|p| pt(p.x - 20.0, p.y * (100.0 / 140.0) - 20.0))
+            .collect();
+        points.push(points[7]);
+        let stats = assert_stats(&points, &rect_polygon(0.0, 0.0, 100.0, 60.0));
+        assert_eq!(stats.areas[200], 0.0);
+        assert_eq!(stats.neighbor_counts[200], 0);
+        assert!(stats.centroids[400].is_nan());
+
+        let hexagon: Vec<Pt> = (0..6)
+            .map(|k| {
+                let a = k as f64 * std::f64::consts::FRAC_PI_3;
+                pt(50.0 + 30.0 * a.cos(), 30.0 + 30.0 * a.sin())
+            })
+            .collect();
+        let stats = assert_stats(&points, &hexagon);
+        // Sites outside the hexagon can still own a sliver of it, but most
+        // of them have no cell and so no neighbours.
+        let empty = (0..200).filter(|&i| stats.areas[i] == 0.0).count();
+        assert!(empty > 50, "{empty}");
+        assert!(
+            (0..200)
+                .filter(|&i| stats.areas[i] == 0.0)
+                .all(|i| stats.neighbor_counts[i] == 0)
+        );
+    }
+
+    #[test]
+    fn collinear_neighbors_are_symmetric() {
+        let points = [
+            pt(70.0, 30.0),
+            pt(10.0, 30.0),
+            pt(40.0, 30.0),
+            pt(90.0, 30.0),
+        ];
+        let stats = assert_stats(&points, &rect_polygon(0.0, 0.0, 100.0, 60.0));
+        assert_eq!(stats.neighbor_counts, [2, 1, 2, 1]);
+        assert_eq!(stats.perimeters[1], 2.0 * (25.0 + 60.0));
+        // Site 1 owns the rectangle [0, 25] x [0, 60].
+        assert_eq!(stats.areas[1], 25.0 * 60.0);
+        assert_eq!(stats.centroids[2..4], [12.5, 30.0]);
+        assert_eq!(stats.bounding_circles[3..6], [12.5, 30.0, 32.5]);
+    }
+}
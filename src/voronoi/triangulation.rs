@@ -0,0 +1,1166 @@
+//! Incremental Delaunay triangulation on a half-edge structure.
+//!
+//! Triangles are stored Delaunator-style: triangle `t` owns the half-edges
+//! `3t`, `3t + 1` and `3t + 2` in counter-clockwise order, `verts[e]` is the
+//! origin of half-edge `e` and `twins[e]` the oppositely oriented half-edge of
+//! the neighbouring triangle. The outside of the convex hull is covered by
+//! "ghost" triangles that share the virtual vertex [`GHOST`], so every
+//! half-edge has a twin and inserting outside the hull works exactly like
+//! inserting inside it.
+//!
+//! Points are inserted in Hilbert order. Each insertion walks from the
+//! previously inserted vertex to the containing triangle and re-triangulates
+//! the (small) Bowyer–Watson cavity, which gives `O(n log n)` overall. All
+//! geometric decisions go through the exact predicates in
+//! [`super::predicates`], so degenerate input cannot corrupt the topology.
+
+use super::predicates::{incircle, inpower, orient2d};
+use super::{Constraint, Edge, Pt, Tri};
+use std::collections::HashMap;
+
+/// Marker for "no half-edge / no vertex".
+pub(crate) const NONE: usize = usize::MAX;
+/// The virtual vertex at infinity shared by all ghost triangles.
+pub(crate) const GHOST: usize = usize::MAX - 1;
+
+#[inline]
+pub(crate) const fn next(e: usize) -> usize {
+    if e % 3 == 2 { e - 2 } else { e + 1 }
+}
+
+#[inline]
+pub(crate) const fn prev(e: usize) -> usize {
+    if e.is_multiple_of(3) { e + 2 } else { e - 1 }
+}
+
+/// `true` if `p` lies strictly between `a` and `b`, assuming the three are collinear.
+fn strictly_between(a: Pt, b: Pt, p: Pt) -> bool {
+    if a.x != b.x {
+        (a.x < p.x && p.x < b.x) || (b.x < p.x && p.x < a.x)
+    } else {
+        (a.y < p.y && p.y < b.y) || (b.y < p.y && p.y < a.y)
+    }
+}
+
+/// Result of inserting a single point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Insertion {
+    /// The point became a new vertex.
+    Inserted,
+    /// The point coincides with the given, already inserted vertex.
+    Duplicate(usize),
+    /// There is no triangle yet to insert into (fewer than three non-collinear points).
+    Deferred,
+    /// The weighted point is redundant: its power cell is empty, so it is
+    /// not a vertex of the regular triangulation.
+    Hidden,
+}
+
+pub(crate) struct Triangulation {
+    pub(crate) points: Vec<Pt>,
+    /// Origin vertex of every half-edge; `NONE` for freed triangle slots.
+    pub(crate) verts: Vec<usize>,
+    /// Opposite half-edge of every half-edge.
+    pub(crate) twins: Vec<usize>,
+    /// One outgoing half-edge per vertex, `NONE` if the point is not a vertex.
+    pub(crate) vert_edge: Vec<usize>,
+    free: Vec<usize>,
+    hint: usize,
+    rng: u32,
+    marks: Vec<u32>,
+    stamp: u32,
+    cavity: Vec<usize>,
+    boundary: Vec<usize>,
+    /// Forced edges of a constrained triangulation; never flipped and never
+    /// crossed by an insertion cavity.
+    pub(crate) constraints: HashMap<Edge, Constraint>,
+    /// Point weights of a regular triangulation; empty for plain Delaunay.
+    pub(crate) weights: Vec<f64>,
+}
+
+impl Triangulation {
+    /// Triangulate `points`. Points that coincide with an earlier point are
+    /// skipped, as are non-finite ones; if all points are collinear the
+    /// result has no triangles.
+    pub(crate) fn new(points: &[Pt]) -> Self {
+        Self::with_filter(points.to_vec(), |_| true)
+    }
+
+    /// Triangulate the finite points for which `keep` returns `true`; the
+    /// others stay in [`Self::points`] (so indices are preserved) but are not
+    /// vertices.
+    pub(crate) fn with_filter(points: Vec<Pt>, keep: impl Fn(usize) -> bool) -> Self {
+        let mut order = hilbert_order(&points);
+        // A NaN passes every orientation test and would corrupt the mesh.
+        order.retain(|&i| keep(i) && points[i].x.is_finite() && points[i].y.is_finite());
+        let mut tri = Self::empty(points);
+        tri.insert_all(&order);
+        tri
+    }
+
+    /// Regular (weighted Delaunay) triangulation, the dual of the power
+    /// diagram. `weights[i]` is the squared radius of point `i`; missing
+    /// weights count as zero. Points whose power cell is empty are not
+    /// vertices, nor are points with a non-finite position or weight, and of
+    /// coinciding points only the heaviest is (the first of equally heavy
+    /// ones).
+    pub(crate) fn regular(points: Vec<Pt>, weights: &[f64]) -> Self {
+        let mut order = hilbert_order(&points);
+        let mut tri = Self::empty(points);
+        tri.weights = (0..tri.points.len())
+            .map(|i| weights.get(i).copied().unwrap_or(0.0))
+            .collect();
+        order.retain(|&i| {
+            let p = tri.points[i];
+            p.x.is_finite() && p.y.is_finite() && tri.weights[i].is_finite()
+        });
+        tri.heaviest_first(&mut order);
+        tri.insert_all(&order);
+        tri
+    }
+
+    /// Reorder `order` so that of coinciding points the heaviest comes
+    /// first. Its power cell contains those of the others, which are then
+    /// rejected as duplicates of it on insertion.
+    fn heaviest_first(&self, order: &mut [usize]) {
+        let key = |p: Pt| ((p.x + 0.0).to_bits(), (p.y + 0.0).to_bits());
+        let mut first: HashMap<(u64, u64), usize> = HashMap::new();
+        for k in 0..order.len() {
+            let i = order[k];
+            let f = *first.entry(key(self.points[i])).or_insert(k);
+            let (w, wf) = (self.weights[i], self.weights[order[f]]);
+            if w > wf || (w == wf && i < order[f]) {
+                order.swap(f, k);
+            }
+        }
+    }
+
+    fn insert_all(&mut self, order: &[usize]) {
+        if let Some(seed) = self.seed_triangle(order) {
+            for &i in order {
+                if !seed.contains(&i) {
+                    self.insert(i);
+                }
+            }
+        }
+    }
+
+    fn empty(points: Vec<Pt>) -> Self {
+        let n = points.len();
+        Self {
+            points,
+            verts: Vec::with_capacity(n * 6 + 6),
+            twins: Vec::with_capacity(n * 6 + 6),
+            vert_edge: vec![NONE; n],
+            free: Vec::new(),
+            hint: NONE,
+            rng: 0x2545_F491,
+            marks: Vec::new(),
+            stamp: 0,
+            cavity: Vec::new(),
+            boundary: Vec::new(),
+            constraints: HashMap::new(),
+            weights: Vec::new(),
+        }
+    }
+
+    /// Pick the first three non-collinear points of `order` and build the
+    /// initial triangle with its three ghosts.
+    fn seed_triangle(&mut self, order: &[usize]) -> Option<[usize; 3]> {
+        let &a = order.first()?;
+        let pa = self.points[a];
+        let &b = order.iter().find(|&&i| self.points[i] != pa)?;
+        let pb = self.points[b];
+        let &c = order
+            .iter()
+            .find(|&&i| orient2d(pa, pb, self.points[i]) != 0.0)?;
+        let (b, c) = if orient2d(pa, pb, self.points[c]) > 0.0 {
+            (b, c)
+        } else {
+            (c, b)
+        };
+
+        let t0 = self.alloc_tri();
+        let g0 = self.alloc_tri();
+        let g1 = self.alloc_tri();
+        let g2 = self.alloc_tri();
+        self.set_tri(t0, a, b, c);
+        // Ghost triangle for hull edge x -> y is (y, x, GHOST).
+        self.set_tri(g0, b, a, GHOST);
+        self.set_tri(g1, c, b, GHOST);
+        self.set_tri(g2, a, c, GHOST);
+        self.link(3 * t0, 3 * g0);
+        self.link(3 * t0 + 1, 3 * g1);
+        self.link(3 * t0 + 2, 3 * g2);
+        self.link(3 * g0 + 1, 3 * g2 + 2);
+        self.link(3 * g1 + 1, 3 * g0 + 2);
+        self.link(3 * g2 + 1, 3 * g1 + 2);
+
+        self.vert_edge[a] = 3 * t0;
+        self.vert_edge[b] = 3 * t0 + 1;
+        self.vert_edge[c] = 3 * t0 + 2;
+        self.hint = 3 * t0;
+        Some([a, b, c])
+    }
+
+    /// Append a point without inserting it; returns its index.
+    pub(crate) fn push_point(&mut self, p: Pt) -> usize {
+        self.points.push(p);
+        self.vert_edge.push(NONE);
+        self.points.len() - 1
+    }
+
+    /// Drop the last point again; it must not be a vertex.
+    pub(crate) fn pop_point(&mut self) {
+        debug_assert_eq!(self.vert_edge.last(), Some(&NONE));
+        self.points.pop();
+        self.vert_edge.pop();
+    }
+
+    /// `true` once the points span a triangle (are not all collinear).
+    #[inline]
+    pub(crate) fn has_triangles(&self) -> bool {
+        !self.verts.is_empty()
+    }
+
+    #[inline]
+    pub(crate) fn tri_count(&self) -> usize {
+        self.verts.len() / 3
+    }
+
+    #[inline]
+    pub(crate) fn is_live(&self, t: usize) -> bool {
+        self.verts[3 * t] != NONE
+    }
+
+    #[inline]
+    pub(crate) fn is_ghost(&self, t: usize) -> bool {
+        self.verts[3 * t] == GHOST
+            || self.verts[3 * t + 1] == GHOST
+            || self.verts[3 * t + 2] == GHOST
+    }
+
+    /// `true` for live triangles that are not ghosts.
+    #[inline]
+    pub(crate) fn is_solid(&self, t: usize) -> bool {
+        self.is_live(t) && !self.is_ghost(t)
+    }
+
+    #[inline]
+    pub(crate) fn tri_verts(&self, t: usize) -> [usize; 3] {
+        [
+            self.verts[3 * t],
+            self.verts[3 * t + 1],
+            self.verts[3 * t + 2],
+        ]
+    }
+
+    /// All live, non-ghost triangles in counter-clockwise vertex order.
+    pub(crate) fn triangles(&self) -> Vec<Tri> {
+        (0..self.tri_count())
+            .filter(|&t| self.is_solid(t))
+            .map(|t| {
+                let [a, b, c] = self.tri_verts(t);
+                Tri { a, b, c }
+            })
+            .collect()
+    }
+
+    /// Outgoing half-edges of vertex `v` in counter-clockwise order. Empty if
+    /// `v` is not a vertex of the triangulation.
+    pub(crate) fn star(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        let start = self.vert_edge.get(v).copied().unwrap_or(NONE);
+        let mut e = start;
+        std::iter::from_fn(move || {
+            if e == NONE {
+                return None;
+            }
+            let out = e;
+            e = self.twins[prev(e)];
+            if e == start {
+                e = NONE;
+            }
+            Some(out)
+        })
+    }
+
+    /// Delaunay neighbours of vertex `v` in counter-clockwise order, including
+    /// [`GHOST`] if `v` lies on the convex hull.
+    pub(crate) fn neighbors(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        self.star(v).map(|e| self.verts[next(e)])
+    }
+
+    /// `true` if point `i` is a vertex of the triangulation.
+    #[inline]
+    pub(crate) fn is_vertex(&self, i: usize) -> bool {
+        self.vert_edge[i] != NONE
+    }
+
+    fn alloc_tri(&mut self) -> usize {
+        if let Some(t) = self.free.pop() {
+            return t;
+        }
+        let t = self.tri_count();
+        self.verts.extend_from_slice(&[NONE; 3]);
+        self.twins.extend_from_slice(&[NONE; 3]);
+        t
+    }
+
+    fn free_tri(&mut self, t: usize) {
+        self.verts[3 * t..3 * t + 3].fill(NONE);
+        self.twins[3 * t..3 * t + 3].fill(NONE);
+        self.free.push(t);
+    }
+
+    #[inline]
+    fn set_tri(&mut self, t: usize, a: usize, b: usize, c: usize) {
+        self.verts[3 * t] = a;
+        self.verts[3 * t + 1] = b;
+        self.verts[3 * t + 2] = c;
+    }
+
+    #[inline]
+    fn link(&mut self, e: usize, f: usize) {
+        self.twins[e] = f;
+        self.twins[f] = e;
+    }
+
+    #[inline]
+    fn next_rand(&mut self) -> u32 {
+        self.rng = crate::rand::hash_u32(self.rng);
+        self.rng
+    }
+
+    /// Walk from the last touched triangle towards `p`. Returns a solid
+    /// triangle containing `p` (possibly on its boundary), or the ghost
+    /// triangle of a hull edge that sees `p` from outside.
+    pub(crate) fn locate(&mut self, p: Pt) -> usize {
+        let mut t = if self.hint != NONE && self.verts[self.hint] != NONE {
+            self.hint / 3
+        } else {
+            (0..self.tri_count())
+                .find(|&t| self.is_live(t))
+                .expect("locate on an empty triangulation")
+        };
+        if self.is_ghost(t) {
+            // Step over the hull edge into the solid neighbour.
+            let e = (0..3)
+                .map(|k| 3 * t + k)
+                .find(|&e| self.verts[e] != GHOST && self.verts[next(e)] != GHOST)
+                .expect("ghost triangle without hull edge");
+            t = self.twins[e] / 3;
+        }
+
+        'walk: loop {
+            // Remember where we ended up; consecutive queries are usually close.
+            self.hint = 3 * t;
+            if self.is_ghost(t) {
+                return t;
+            }
+            // Stochastic start edge, so the walk cannot cycle.
+            let r = (self.next_rand() % 3) as usize;
+            for k in 0..3 {
+                let e = 3 * t + (r + k) % 3;
+                let a = self.points[self.verts[e]];
+                let b = self.points[self.verts[next(e)]];
+                if orient2d(a, b, p) < 0.0 {
+                    t = self.twins[e] / 3;
+                    continue 'walk;
+                }
+            }
+            return t;
+        }
+    }
+
+    /// `true` if the circumcircle of triangle `t` (or the outer half-plane of
+    /// a ghost triangle) strictly contains `p`.
+    pub(crate) fn in_conflict(&self, t: usize, p: Pt) -> bool {
+        self.in_power_conflict(t, p, 0.0)
+    }
+
+    /// [`Self::in_conflict`] for a point of weight `w`; in a regular
+    /// triangulation solid triangles use the power test instead of the
+    /// circumcircle.
+    fn in_power_conflict(&self, t: usize, p: Pt, w: f64) -> bool {
+        let [a, b, c] = self.tri_verts(t);
+        let (x, y) = if c == GHOST {
+            (a, b)
+        } else if a == GHOST {
+            (b, c)
+        } else if b == GHOST {
+            (c, a)
+        } else if self.weights.is_empty() {
+            return incircle(self.points[a], self.points[b], self.points[c], p) > 0.0;
+        } else {
+            let [a, b, c] = [a, b, c].map(|v| (self.points[v], self.weights[v]));
+            return inpower(a, b, c, (p, w)) > 0.0;
+        };
+        let (px, py) = (self.points[x], self.points[y]);
+        let o = orient2d(px, py, p);
+        o > 0.0 || (o == 0.0 && strictly_between(px, py, p))
+    }
+
+    /// Insert point `i` of [`Self::points`] as a new vertex.
+    pub(crate) fn insert(&mut self, i: usize) -> Insertion {
+        if self.verts.is_empty() {
+            return Insertion::Deferred;
+        }
+        let p = self.points[i];
+        let t = self.locate(p);
+        if !self.is_ghost(t)
+            && let Some(&v) = self.tri_verts(t).iter().find(|&&v| self.points[v] == p)
+        {
+            return Insertion::Duplicate(v);
+        }
+        let w = self.weights.get(i).copied().unwrap_or(0.0);
+        if !self.weights.is_empty() && self.is_hidden(t, p, w) {
+            return Insertion::Hidden;
+        }
+
+        // A point on a forced edge splits it; the cavity may then cross it.
+        let split = (3 * t..3 * t + 3)
+            .filter(|&e| self.is_constrained(e))
+            .map(|e| (self.verts[e], self.verts[next(e)]))
+            .find(|&(a, b)| {
+                let (pa, pb) = (self.points[a], self.points[b]);
+                orient2d(pa, pb, p) == 0.0 && strictly_between(pa, pb, p)
+            });
+        let kind = split.and_then(|(a, b)| self.constraints.remove(&Edge::new(a, b)));
+
+        self.collect_cavity(t, p, w);
+        // With weights the cavity can swallow vertices, which become hidden.
+        let inner: Vec<usize> = if self.weights.is_empty() {
+            Vec::new()
+        } else {
+            self.cavity
+                .iter()
+                .flat_map(|&ct| self.tri_verts(ct))
+                .filter(|&v| v != GHOST)
+                .collect()
+        };
+        self.fill_cavity(i);
+        for v in inner {
+            let e = self.vert_edge[v];
+            if e != NONE && self.verts[e] != v {
+                self.vert_edge[v] = NONE;
+            }
+        }
+        if let (Some((a, b)), Some(kind)) = (split, kind) {
+            self.constraints.insert(Edge::new(a, i), kind);
+            self.constraints.insert(Edge::new(i, b), kind);
+        }
+        Insertion::Inserted
+    }
+
+    /// `true` if the weighted point `p` in triangle `t` is redundant. Outside
+    /// the hull it never is; on a hull edge the solid side decides.
+    fn is_hidden(&self, t: usize, p: Pt, w: f64) -> bool {
+        if !self.is_ghost(t) {
+            return !self.in_power_conflict(t, p, w);
+        }
+        let e = (3 * t..3 * t + 3)
+            .find(|&e| self.verts[e] != GHOST && self.verts[next(e)] != GHOST)
+            .expect("ghost triangle without hull edge");
+        let (a, b) = (self.points[self.verts[e]], self.points[self.verts[next(e)]]);
+        orient2d(a, b, p) == 0.0 && !self.in_power_conflict(self.twins[e] / 3, p, w)
+    }
+
+    /// `true` if half-edge `e` is a forced edge.
+    #[inline]
+    pub(crate) fn is_constrained(&self, e: usize) -> bool {
+        !self.constraints.is_empty()
+            && self
+                .constraints
+                .contains_key(&Edge::new(self.verts[e], self.verts[next(e)]))
+    }
+
+    /// Flood-fill the Bowyer–Watson cavity of `p` starting at triangle `t`
+    /// and collect its boundary as a closed, ordered chain of half-edges.
+    fn collect_cavity(&mut self, t: usize, p: Pt, w: f64) {
+        self.stamp = self.stamp.wrapping_add(1);
+        if self.stamp == 0 {
+            self.marks.fill(0);
+            self.stamp = 1;
+        }
+        if self.marks.len() < self.tri_count() {
+            self.marks.resize(self.tri_count(), 0);
+        }
+
+        self.cavity.clear();
+        self.cavity.push(t);
+        self.marks[t] = self.stamp;
+        let mut k = 0;
+        while k < self.cavity.len() {
+            let ct = self.cavity[k];
+            k += 1;
+            for e in 3 * ct..3 * ct + 3 {
+                let n = self.twins[e] / 3;
+                if self.marks[n] != self.stamp
+                    && !self.is_constrained(e)
+                    && self.in_power_conflict(n, p, w)
+                {
+                    self.marks[n] = self.stamp;
+                    self.cavity.push(n);
+                }
+            }
+        }
+
+        // Any boundary edge will do as the start of the chain.
+        let start = self
+            .cavity
+            .iter()
+            .flat_map(|&ct| 3 * ct..3 * ct + 3)
+            .find(|&e| self.marks[self.twins[e] / 3] != self.stamp)
+            .expect("cavity without boundary");
+
+        self.boundary.clear();
+        let mut e = start;
+        loop {
+            self.boundary.push(e);
+            // Rotate around the destination of `e` until we leave the cavity.
+            let mut f = next(e);
+            while self.marks[self.twins[f] / 3] == self.stamp {
+                f = next(self.twins[f]);
+            }
+            e = f;
+            if e == start {
+                break;
+            }
+        }
+    }
+
+    /// Vertices that `p` would connect to if it were inserted, in
+    /// counter-clockwise order, without changing the triangulation. `None`
+    /// unless `p` lies strictly inside the convex hull.
+    pub(crate) fn natural_neighbors(&mut self, p: Pt) -> Option<Vec<usize>> {
+        if !self.has_triangles() {
+            return None;
+        }
+        let t = self.locate(p);
+        if self.is_ghost(t) {
+            return None;
+        }
+        self.collect_cavity(t, p, 0.0);
+        if self.cavity.iter().any(|&ct| self.is_ghost(ct)) {
+            return None;
+        }
+        Some(self.boundary.iter().map(|&e| self.verts[e]).collect())
+    }
+
+    /// Replace the collected cavity by a fan of triangles around vertex `i`.
+    fn fill_cavity(&mut self, i: usize) {
+        let k = self.boundary.len();
+        let mut slots = std::mem::take(&mut self.cavity);
+        let boundary = std::mem::take(&mut self.boundary);
+
+        // Remember the outer twins and origins before the slots are reused.
+        let outer: Vec<(usize, usize, usize)> = boundary
+            .iter()
+            .map(|&e| (self.verts[e], self.verts[next(e)], self.twins[e]))
+            .collect();
+
+        while slots.len() < k {
+            let t = self.alloc_tri();
+            slots.push(t);
+        }
+        for &t in &slots[k..] {
+            self.free_tri(t);
+        }
+
+        for (j, &(a, b, out)) in outer.iter().enumerate() {
+            let s = slots[j];
+            self.set_tri(s, a, b, i);
+            self.link(3 * s, out);
+            if a != GHOST {
+                self.vert_edge[a] = 3 * s;
+            }
+        }
+        for j in 0..k {
+            let s = slots[j];
+            let s_next = slots[(j + 1) % k];
+            self.link(3 * s + 1, 3 * s_next + 2);
+        }
+
+        self.vert_edge[i] = 3 * slots[0] + 2;
+        self.hint = 3 * slots[0];
+
+        slots.clear();
+        self.cavity = slots;
+        self.boundary = boundary;
+    }
+
+    /// Remove vertex `v` and re-triangulate its star locally. Returns `false`
+    /// if `v` is not a vertex. If the remaining points no longer span a
+    /// triangle, the triangulation is left without triangles.
+    ///
+    /// Only valid for plain Delaunay triangulations: the star is filled
+    /// without regard to weights or forced edges, and the fallback rebuild
+    /// drops both.
+    pub(crate) fn remove(&mut self, v: usize) -> bool {
+        debug_assert!(
+            self.weights.is_empty() && self.constraints.is_empty(),
+            "remove on a regular or constrained triangulation"
+        );
+        if !self.is_vertex(v) {
+            return false;
+        }
+        let star: Vec<usize> = self.star(v).collect();
+        let ghost_at = star.iter().position(|&e| self.verts[next(e)] == GHOST);
+        let repaired = match ghost_at {
+            None => self.remove_interior(v, &star),
+            Some(g) => {
+                // Start the chain right after the ghost edge.
+                let mut star = star;
+                star.rotate_left(g + 1);
+                star.pop();
+                self.remove_hull(v, &star)
+            }
+        };
+        if !repaired {
+            // Only reached for degenerate remainders; start over without `v`.
+            let points = std::mem::take(&mut self.points);
+            let keep: Vec<bool> = (0..points.len())
+                .map(|i| i != v && self.vert_edge[i] != NONE)
+                .collect();
+            *self = Self::with_filter(points, |i| keep[i]);
+        }
+        self.vert_edge[v] = NONE;
+        true
+    }
+
+    /// Remove an interior vertex: its link is a closed, star-shaped polygon.
+    fn remove_interior(&mut self, v: usize, star: &[usize]) -> bool {
+        let poly: Vec<usize> = star.iter().map(|&e| self.verts[next(e)]).collect();
+        let outer: Vec<usize> = star.iter().map(|&e| self.twins[next(e)]).collect();
+        let Some(tris) = triangulate_polygon(&self.points, &poly) else {
+            return false;
+        };
+        for &e in star {
+            self.free_tri(e / 3);
+        }
+        self.vert_edge[v] = NONE;
+        let mut diagonals = self.stitch(&poly, &outer, &tris);
+        self.legalize(&mut diagonals);
+        true
+    }
+
+    /// Remove a hull vertex. `star` holds its outgoing edges to the link
+    /// chain `x_1 .. x_k` in counter-clockwise order: `v -> x_1` follows the
+    /// ghost edge, and the triangle of `v -> x_k` is the ghost of hull edge
+    /// `x_k -> v`. The new hull from `x_k` to `x_1` is the convex chain of the
+    /// link facing `v`; each pocket between it and the link is triangulated
+    /// on its own.
+    fn remove_hull(&mut self, v: usize, star: &[usize]) -> bool {
+        let k = star.len();
+        let last_ghost = star[k - 1] / 3;
+        let first_ghost = self.twins[star[0]] / 3;
+        let chain: Vec<usize> = star.iter().map(|&e| self.verts[next(e)]).collect();
+        // Outer twins of the chain edges x_i -> x_{i+1}.
+        let outer: Vec<usize> = star[..k - 1].iter().map(|&e| self.twins[next(e)]).collect();
+        if outer.iter().all(|&o| self.is_ghost(o / 3)) {
+            // Nothing but the star was solid: the rest is a line at best.
+            return false;
+        }
+
+        // Convex chain facing v, built from x_1 towards x_k (right turns only).
+        let mut hull: Vec<usize> = Vec::with_capacity(k);
+        for i in 0..k {
+            while hull.len() >= 2 {
+                let a = self.points[chain[hull[hull.len() - 2]]];
+                let b = self.points[chain[hull[hull.len() - 1]]];
+                if orient2d(a, b, self.points[chain[i]]) > 0.0 {
+                    hull.pop();
+                } else {
+                    break;
+                }
+            }
+            hull.push(i);
+        }
+
+        // Triangulate all pockets before touching the topology.
+        let mut pockets = Vec::new();
+        for w in hull.windows(2) {
+            let (a, b) = (w[0], w[1]);
+            if b > a + 1 {
+                let poly = chain[a..=b].to_vec();
+                let Some(tris) = triangulate_polygon(&self.points, &poly) else {
+                    return false;
+                };
+                pockets.push((a, b, poly, tris));
+            }
+        }
+
+        // Ghost-side twins at both ends of the removed hull section: the edge
+        // G -> x_k before it and x_1 -> G after it.
+        let before = self.twins[prev(3 * last_ghost + self.ghost_slot(last_ghost))];
+        let after = self.twins[3 * first_ghost + self.ghost_slot(first_ghost)];
+        for &e in &star[..k - 1] {
+            self.free_tri(e / 3);
+        }
+        self.free_tri(first_ghost);
+        self.free_tri(last_ghost);
+        self.vert_edge[v] = NONE;
+
+        // The solid edge x_b -> x_a under each new hull edge, keyed by `a`.
+        let mut hull_edges: Vec<(usize, usize)> = Vec::with_capacity(hull.len());
+        let mut diagonals = Vec::new();
+        for (a, b, poly, tris) in pockets {
+            let mut pocket_outer = outer[a..b].to_vec();
+            pocket_outer.push(NONE);
+            for e in self.stitch(&poly, &pocket_outer, &tris) {
+                if self.twins[e] == NONE {
+                    hull_edges.push((a, e));
+                } else {
+                    diagonals.push(e);
+                }
+            }
+        }
+        for w in hull.windows(2) {
+            if w[1] == w[0] + 1 {
+                hull_edges.push((w[0], outer[w[0]]));
+            }
+        }
+        hull_edges.sort_unstable();
+
+        // New ghosts, walking the new hull from x_k back to x_1. The ghost of
+        // hull edge x -> y is (y, x, G).
+        let mut open_edge = before;
+        for &(_, inner) in hull_edges.iter().rev() {
+            let x = self.verts[inner];
+            let y = self.verts[next(inner)];
+            let g = self.alloc_tri();
+            self.set_tri(g, y, x, GHOST);
+            self.link(3 * g, inner);
+            self.link(3 * g + 1, open_edge);
+            open_edge = 3 * g + 2;
+            self.vert_edge[x] = inner;
+            self.vert_edge[y] = 3 * g;
+        }
+        self.link(open_edge, after);
+
+        self.legalize(&mut diagonals);
+        true
+    }
+
+    /// Position (0..3) of the ghost vertex within ghost triangle `t`.
+    fn ghost_slot(&self, t: usize) -> usize {
+        (0..3)
+            .find(|&k| self.verts[3 * t + k] == GHOST)
+            .expect("not a ghost triangle")
+    }
+
+    /// Create the triangles `tris` over polygon `poly` (vertex ids), linking
+    /// polygon edge `poly[i] -> poly[i + 1]` to `outer[i]` (`NONE` leaves it
+    /// open) and the diagonals to each other. Returns the new inner edges.
+    fn stitch(&mut self, poly: &[usize], outer: &[usize], tris: &[[usize; 3]]) -> Vec<usize> {
+        let n = poly.len();
+        let mut open: Vec<(usize, usize, usize)> = Vec::new();
+        let mut inner = Vec::new();
+        for &[a, b, c] in tris {
+            let t = self.alloc_tri();
+            self.set_tri(t, a, b, c);
+            for e in 3 * t..3 * t + 3 {
+                let (u, w) = (self.verts[e], self.verts[next(e)]);
+                self.vert_edge[u] = e;
+                let boundary = (0..n).find(|&i| poly[i] == u && poly[(i + 1) % n] == w);
+                if let Some(i) = boundary {
+                    if outer[i] != NONE {
+                        self.link(e, outer[i]);
+                    } else {
+                        self.twins[e] = NONE;
+                        inner.push(e);
+                    }
+                } else if let Some(k) = open.iter().position(|&(ou, ow, _)| ou == w && ow == u) {
+                    let (_, _, f) = open.swap_remove(k);
+                    self.link(e, f);
+                    inner.push(e);
+                } else {
+                    open.push((u, w, e));
+                }
+            }
+        }
+        debug_assert!(open.is_empty(), "unmatched diagonal");
+        inner
+    }
+
+    /// Flip edges from `stack` (and the edges around each flip) until all of
+    /// them are locally Delaunay.
+    pub(crate) fn legalize(&mut self, stack: &mut Vec<usize>) {
+        while let Some(e) = stack.pop() {
+            let f = self.twins[e];
+            if f == NONE
+                || self.verts[e] == NONE
+                || !self.is_solid(e / 3)
+                || !self.is_solid(f / 3)
+                || self.is_constrained(e)
+            {
+                continue;
+            }
+            let a = self.points[self.verts[e]];
+            let b = self.points[self.verts[next(e)]];
+            let c = self.points[self.verts[prev(e)]];
+            let d = self.points[self.verts[prev(f)]];
+            if incircle(a, b, c, d) > 0.0 {
+                self.flip(e);
+                stack.extend([e, f, prev(e), prev(f)]);
+            }
+        }
+    }
+
+    /// Flip the edge `e` (a -> b) shared by triangles (a, b, c) and (b, a, d)
+    /// into the edge c - d. The quadrilateral must be strictly convex.
+    pub(crate) fn flip(&mut self, e0: usize) {
+        let f0 = self.twins[e0];
+        let (e1, e2) = (next(e0), prev(e0));
+        let (f1, f2) = (next(f0), prev(f0));
+        let a = self.verts[e0];
+        let b = self.verts[f0];
+        let c = self.verts[e2];
+        let d = self.verts[f2];
+        let out_e1 = self.twins[e1];
+        let out_f1 = self.twins[f1];
+
+        // (a, d, c) and (b, c, d); e2 = c -> a and f2 = d -> b keep their twins.
+        self.verts[e0] = a;
+        self.verts[e1] = d;
+        self.verts[f0] = b;
+        self.verts[f1] = c;
+        self.link(e0, out_f1);
+        self.link(f0, out_e1);
+        self.link(e1, f1);
+
+        self.vert_edge[a] = e0;
+        self.vert_edge[b] = f0;
+        self.vert_edge[c] = e2;
+        self.vert_edge[d] = f2;
+        self.hint = e0;
+    }
+
+    /// Move point `v` to `p`. If the triangles around `v` stay valid at the
+    /// new position, the Delaunay property is restored with edge flips;
+    /// otherwise `v` is removed and inserted again. Returns `true` if the
+    /// vertex could be moved in place. Points that are not vertices (e.g.
+    /// duplicates) are re-inserted at their new position. A non-finite `p`
+    /// takes `v` out of the triangulation, which counts as not moved in
+    /// place if it was a vertex, until it moves somewhere finite.
+    pub(crate) fn move_vertex(&mut self, v: usize, p: Pt) -> bool {
+        if self.points[v] == p {
+            return true;
+        }
+        if !p.x.is_finite() || !p.y.is_finite() {
+            let removed = self.remove(v);
+            self.points[v] = p;
+            return !removed;
+        }
+        if !self.is_vertex(v) {
+            self.points[v] = p;
+            self.insert(v);
+            return false;
+        }
+        // Reuse the cavity buffers; moving many points is a hot loop.
+        let mut star = std::mem::take(&mut self.cavity);
+        let mut stack = std::mem::take(&mut self.boundary);
+        star.clear();
+        star.extend(self.star(v));
+        let movable = self.can_move(&star, p);
+        if movable {
+            self.points[v] = p;
+            // Only edges with `v` in one of their triangles can have become
+            // illegal: the spokes of the star and the edges of its link.
+            stack.clear();
+            stack.extend(star.iter().flat_map(|&e| [e, next(e)]));
+            self.legalize(&mut stack);
+        }
+        self.cavity = star;
+        self.boundary = stack;
+        if movable {
+            return true;
+        }
+        self.remove(v);
+        self.points[v] = p;
+        self.insert(v);
+        false
+    }
+
+    /// `true` if the vertex with outgoing half-edges `star` can be moved to
+    /// `p` without changing the topology: all of its triangles keep their
+    /// orientation and, on the hull, the hull stays strictly convex.
+    fn can_move(&self, star: &[usize], p: Pt) -> bool {
+        for &e in star {
+            let (a, b) = (self.verts[next(e)], self.verts[prev(e)]);
+            if a == GHOST {
+                // Ghost (y, v, GHOST) of the hull edge v -> y; yy follows y.
+                let yy = self.verts[prev(self.twins[next(e)])];
+                let (py, pyy) = (self.points[b], self.points[yy]);
+                if orient2d(p, py, pyy) <= 0.0 {
+                    return false;
+                }
+            } else if b == GHOST {
+                // Ghost (v, x, GHOST) of the hull edge x -> v; xx precedes x,
+                // and y is the other hull neighbour of v.
+                let xx = self.verts[prev(self.twins[next(e)])];
+                let y = self.verts[prev(self.twins[prev(e)])];
+                let (px, pxx, py) = (self.points[a], self.points[xx], self.points[y]);
+                if orient2d(pxx, px, p) <= 0.0 || orient2d(px, p, py) <= 0.0 {
+                    return false;
+                }
+            } else if orient2d(p, self.points[a], self.points[b]) <= 0.0 {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Ear-clip the simple, counter-clockwise polygon `poly` (vertex ids into
+/// `points`). Returns `None` if no ear can be found, which only happens for
+/// polygons that are not simple.
+fn triangulate_polygon(points: &[Pt], poly: &[usize]) -> Option<Vec<[usize; 3]>> {
+    let mut ring = poly.to_vec();
+    let mut tris = Vec::with_capacity(poly.len().saturating_sub(2));
+    while ring.len() > 3 {
+        let n = ring.len();
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = (ring[(i + n - 1) % n], ring[i], ring[(i + 1) % n]);
+            let (pa, pb, pc) = (points[a], points[b], points[c]);
+            orient2d(pa, pb, pc) > 0.0
+                && ring.iter().all(|&o| {
+                    o == a
+                        || o == b
+                        || o == c
+                        || orient2d(pa, pb, points[o]) < 0.0
+                        || orient2d(pb, pc, points[o]) < 0.0
+                        || orient2d(pc, pa, points[o]) < 0.0
+                })
+        })?;
+        let (a, b, c) = (ring[(ear + n - 1) % n], ring[ear], ring[(ear + 1) % n]);
+        tris.push([a, b, c]);
+        ring.remove(ear);
+    }
+    if ring.len() == 3 {
+        if orient2d(points[ring[0]], points[ring[1]], points[ring[2]]) <= 0.0 {
+            return None;
+        }
+        tris.push([ring[0], ring[1], ring[2]]);
+    }
+    Some(tris)
+}
+
+/// Indices of `points` sorted along a Hilbert curve over their bounding box.
+pub(crate) fn hilbert_order(points: &[Pt]) -> Vec<usize> {
+    const N: u32 = 1 << 16;
+
+    let mut minx = f64::INFINITY;
+    let mut miny = f64::INFINITY;
+    let mut maxx = f64::NEG_INFINITY;
+    let mut maxy = f64::NEG_INFINITY;
+    for p in points {
+        minx = minx.min(p.x);
+        miny = miny.min(p.y);
+        maxx = maxx.max(p.x);
+        maxy = maxy.max(p.y);
+    }
+    let sx = (N - 1) as f64 / (maxx - minx).max(f64::MIN_POSITIVE);
+    let sy = (N - 1) as f64 / (maxy - miny).max(f64::MIN_POSITIVE);
+
+    let mut keyed: Vec<(u64, usize)> = points
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let x = ((p.x - minx) * sx) as u32;
+            let y = ((p.y - miny) * sy) as u32;
+            (hilbert_index(N, x, y), i)
+        })
+        .collect();
+    keyed.sort_unstable();
+    keyed.into_iter().map(|(_, i)| i).collect()
+}
+
+fn hilbert_index(n: u32, mut x: u32, mut y: u32) -> u64 {
+    let mut d = 0u64;
+    let mut s = n / 2;
+    while s > 0 {
+        let rx = u32::from(x & s > 0);
+        let ry = u32::from(y & s > 0);
+        d += (s as u64) * (s as u64) * ((3 * rx) ^ ry) as u64;
+        if ry == 0 {
+            if rx == 1 {
+                x = n - 1 - x;
+                y = n - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    d
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voronoi::test_util::{pt, random_points};
+
+    /// Check topology, orientation, the local Delaunay property of every
+    /// interior edge and the Euler relation; returns the number of vertices.
+    fn assert_delaunay(tri: &Triangulation) -> usize {
+        for e in 0..tri.verts.len() {
+            if tri.verts[e] == NONE {
+                continue;
+            }
+            let f = tri.twins[e];
+            assert_eq!(tri.twins[f], e, "twin of twin");
+            assert_eq!(tri.verts[f], tri.verts[next(e)], "twin endpoints");
+        }
+        for t in tri.triangles() {
+            let (a, b, c) = (tri.points[t.a], tri.points[t.b], tri.points[t.c]);
+            assert!(orient2d(a, b, c) > 0.0, "inverted triangle {t:?}");
+        }
+        for e in 0..tri.verts.len() {
+            let f = tri.twins[e];
+            if f == NONE || !tri.is_solid(e / 3) || !tri.is_solid(f / 3) {
+                continue;
+            }
+            let [a, b, c] = tri.tri_verts(e / 3).map(|v| tri.points[v]);
+            let d = tri.points[tri.verts[prev(f)]];
+            assert!(
+                incircle(a, b, c, d) <= 0.0,
+                "edge {e} is not locally Delaunay"
+            );
+        }
+        let vertices = tri.vert_edge.iter().filter(|&&e| e != NONE).count();
+        let hull = (0..tri.tri_count())
+            .filter(|&t| tri.is_live(t) && tri.is_ghost(t))
+            .count();
+        assert_eq!(
+            tri.triangles().len(),
+            2 * vertices - 2 - hull,
+            "Euler relation"
+        );
+        vertices
+    }
+
+    #[test]
+    fn grid_aligned_points() {
+        let points: Vec<Pt> = (0..30 * 30)
+            .map(|i| pt((i % 30) as f64, (i / 30) as f64))
+            .collect();
+        let tri = Triangulation::new(&points);
+        assert_eq!(assert_delaunay(&tri), points.len());
+        assert_eq!(tri.triangles().len(), 2 * 29 * 29);
+    }
+
+    #[test]
+    fn cocircular_points() {
+        // Integer points on the circle of radius 25, plus its center.
+        let mut points = vec![pt(0.0, 0.0)];
+        for &(x, y) in &[
+            (25.0, 0.0),
+            (24.0, 7.0),
+            (20.0, 15.0),
+            (15.0, 20.0),
+            (7.0, 24.0),
+        ] {
+            for (sx, sy) in [(1.0, 1.0), (-1.0, 1.0), (-1.0, -1.0), (1.0, -1.0)] {
+                points.push(pt(sx * x, sy * y));
+                points.push(pt(sy * y, sx * x));
+            }
+        }
+        points.sort_by(|a, b| {
+            a.x.partial_cmp(&b.x)
+                .unwrap()
+                .then(a.y.partial_cmp(&b.y).unwrap())
+        });
+        points.dedup();
+
+        let tri = Triangulation::new(&points);
+        assert_eq!(assert_delaunay(&tri), points.len());
+
+        // Without the center the hull is all there is.
+        let ring: Vec<Pt> = points
+            .iter()
+            .copied()
+            .filter(|p| p.x != 0.0 || p.y != 0.0)
+            .collect();
+        let tri = Triangulation::new(&ring);
+        assert_eq!(assert_delaunay(&tri), ring.len());
+        assert_eq!(tri.triangles().len(), ring.len() - 2);
+    }
+
+    #[test]
+    fn duplicate_points() {
+        let mut points = random_points(200, 7, 100.0);
+        let copies: Vec<Pt> = points.iter().step_by(3).copied().collect();
+        points.extend(copies);
+
+        let tri = Triangulation::new(&points);
+        assert_eq!(assert_delaunay(&tri), 200);
+        for (i, p) in points.iter().enumerate().skip(200) {
+            assert_eq!(tri.vert_edge[i], NONE, "duplicate {i} became a vertex");
+            let j = (i - 200) * 3;
+            assert_eq!(*p, points[j]);
+        }
+    }
+
+    #[test]
+    fn collinear_points() {
+        let points: Vec<Pt> = (0..10).map(|i| pt(i as f64, 2.0 * i as f64)).collect();
+        let tri = Triangulation::new(&points);
+        assert!(tri.triangles().is_empty());
+
+        let mut points = points;
+        points.push(pt(3.0, -1.0));
+        let tri = Triangulation::new(&points);
+        assert_eq!(assert_delaunay(&tri), points.len());
+        assert_eq!(tri.triangles().len(), 9);
+    }
+
+    #[test]
+    fn non_finite_points_are_skipped() {
+        let mut points = random_points(30, 5, 100.0);
+        let clean = Triangulation::new(&points).triangles().len();
+        for bad in [pt(f64::NAN, 5.0), pt(5.0, f64::INFINITY)] {
+            // First in the input, so it would be the seed vertex.
+            points.insert(0, bad);
+            let tri = Triangulation::new(&points);
+            assert_eq!(tri.vert_edge[0], NONE);
+            assert_eq!(assert_delaunay(&tri), 30);
+            assert_eq!(tri.triangles().len(), clean);
+            points.remove(0);
+        }
+    }
+
+    /// 100k points have to triangulate well under a second. Run with
+    /// `cargo test --release -- --ignored hundred_thousand`.
+    #[test]
+    #[ignore = "benchmark; run in release"]
+    fn hundred_thousand_points() {
+        const N: usize = 100_000;
+        let random = random_points(N, 11, 1000.0);
+        let grid: Vec<Pt> = (0..N)
+            .map(|i| pt((i % 316) as f64, (i / 316) as f64))
+            .collect();
+        let cocircular: Vec<Pt> = (0..N)
+            .map(|i| {
+                let a = i as f64 / N as f64 * std::f64::consts::TAU;
+                pt(500.0 * a.cos(), 500.0 * a.sin())
+            })
+            .collect();
+        let duplicates: Vec<Pt> = (0..N).map(|i| random[i % (N / 10)]).collect();
+
+        for (name, points) in [
+            ("random", random),
+            ("grid", grid),
+            ("cocircular", cocircular),
+            ("duplicates", duplicates),
+        ] {
+            let start = std::time::Instant::now();
+            let tri = Triangulation::new(&points);
+            let elapsed = start.elapsed();
+            assert!(!tri.triangles().is_empty());
+            assert!(
+                elapsed < std::time::Duration::from_secs(1),
+                "{name} took {elapsed:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,335 @@
+//! Checks for triangulation output.
+//!
+//! The triangles are checked as plain index triplets, independent of the
+//! structure that produced them, so the `triangles` of any `DelaunayResult`
+//! can be verified:
+//!
+//! - every triangle is counter-clockwise and not degenerate,
+//! - every directed edge belongs to at most one triangle (a manifold mesh),
+//! - every interior edge is locally Delaunay, i.e. the vertex across it lies
+//!   outside the circumcircle; by the Delaunay lemma this is enough for the
+//!   empty-circle property of the whole triangulation,
+//! - the Euler characteristic `V - E + F` is 1, as for any triangulated disk,
+//! - no two triangles overlap,
+//! - every vertex is finite,
+//! - every finite input point is a vertex or coincides with one.
+//!
+//! All geometric tests use the exact predicates, and are written so that a
+//! NaN result fails them.
+
+use super::delaunay::DelaunayTriangulation;
+use super::predicates::{incircle, orient2d};
+use super::{Pt, VoronoiTests, read_points};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use wasm_bindgen::prelude::*;
+
+/// Outcome of [`VoronoiTests::validate_triangulation`]. Edges and point
+/// pairs are flat `[a0,b0,a1,b1,...]` buffers, triangles are numbered in
+/// the order of the index buffer.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, Default)]
+pub struct ValidationReport {
+    /// `true` if none of the checks found a problem.
+    pub valid: bool,
+    pub vertices: u32,
+    pub edges: u32,
+    pub triangles: u32,
+    /// `V - E + F`; 1 for a valid triangulation.
+    pub euler_characteristic: i32,
+    /// Triangles that reference a point that does not exist.
+    #[wasm_bindgen(readonly)]
+    pub out_of_range_triangles: Vec<u32>,
+    /// Vertices with a NaN or infinite coordinate.
+    #[wasm_bindgen(readonly)]
+    pub non_finite_vertices: Vec<u32>,
+    /// Triangles that are clockwise or have no area.
+    #[wasm_bindgen(readonly)]
+    pub inverted_triangles: Vec<u32>,
+    /// Directed edges shared by more than one triangle.
+    #[wasm_bindgen(readonly)]
+    pub non_manifold_edges: Vec<u32>,
+    /// Interior edges whose opposite vertex lies inside the circumcircle.
+    #[wasm_bindgen(readonly)]
+    pub non_delaunay_edges: Vec<u32>,
+    /// Pairs of triangles whose interiors intersect.
+    #[wasm_bindgen(readonly)]
+    pub overlapping_triangles: Vec<u32>,
+    /// Input points that are not vertices but coincide with one, paired
+    /// with that vertex.
+    #[wasm_bindgen(readonly)]
+    pub duplicate_points: Vec<u32>,
+    /// Finite input points that are neither vertices nor duplicates of one.
+    #[wasm_bindgen(readonly)]
+    pub unused_points: Vec<u32>,
+}
+
+/// Check the triangles `indices` over `points`. Only finite points for
+/// which `expected` returns `true` have to be used.
+pub(crate) fn validate(
+    points: &[Pt],
+    indices: &[u32],
+    expected: impl Fn(usize) -> bool,
+) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let mut tris: Vec<(usize, [usize; 3])> = Vec::new();
+    for (t, ix) in indices.chunks_exact(3).enumerate() {
+        let ix = [ix[0] as usize, ix[1] as usize, ix[2] as usize];
+        if ix.iter().any(|&i| i >= points.len()) {
+            report.out_of_range_triangles.push(t as u32);
+        } else {
+            tris.push((t, ix));
+        }
+    }
+    report.triangles = (indices.len() / 3) as u32;
+
+    // Directed edges, each with the vertex opposite to it.
+    let mut opposite: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut used = vec![false; points.len()];
+    for &(t, [a, b, c]) in &tris {
+        if orient2d(points[a], points[b], points[c]).partial_cmp(&0.0) != Some(Ordering::Greater) {
+            report.inverted_triangles.push(t as u32);
+        }
+        for (u, v, w) in [(a, b, c), (b, c, a), (c, a, b)] {
+            used[u] = true;
+            if opposite.insert((u, v), w).is_some() {
+                report.non_manifold_edges.extend([u as u32, v as u32]);
+            }
+        }
+    }
+
+    let mut edges = 0usize;
+    for (&(u, v), &w) in &opposite {
+        match opposite.get(&(v, u)) {
+            // Count and test interior edges once, from the smaller index.
+            Some(&x) if u < v => {
+                edges += 1;
+                let (pu, pv, pw, px) = (points[u], points[v], points[w], points[x]);
+                if incircle(pu, pv, pw, px)
+                    .partial_cmp(&0.0)
+                    .is_none_or(Ordering::is_gt)
+                {
+                    report.non_delaunay_edges.extend([u as u32, v as u32]);
+                }
+            }
+            Some(_) => {}
+            None => edges += 1,
+        }
+    }
+    report.non_delaunay_edges.sort_unstable();
+    report.non_manifold_edges.sort_unstable();
+
+    let finite = |i: usize| points[i].x.is_finite() && points[i].y.is_finite();
+    report.non_finite_vertices = (0..points.len())
+        .filter(|&i| used[i] && !finite(i))
+        .map(|i| i as u32)
+        .collect();
+    let vertices = used.iter().filter(|&&u| u).count();
+    report.vertices = vertices as u32;
+    report.edges = edges as u32;
+    report.euler_characteristic = vertices as i32 - edges as i32 + tris.len() as i32;
+
+    report.overlapping_triangles = overlapping_pairs(points, &tris);
+
+    // Points are the same if their coordinates are bitwise equal (with
+    // -0 and 0 folded together, as the predicates see them).
+    let key = |p: Pt| ((p.x + 0.0).to_bits(), (p.y + 0.0).to_bits());
+    let vertex_at: HashMap<_, usize> = (0..points.len())
+        .filter(|&i| used[i])
+        .map(|i| (key(points[i]), i))
+        .collect();
+    for i in (0..points.len()).filter(|&i| !used[i] && finite(i) && expected(i)) {
+        match vertex_at.get(&key(points[i])) {
+            Some(&v) => report.duplicate_points.extend([i as u32, v as u32]),
+            None => report.unused_points.push(i as u32),
+        }
+    }
+
+    report.valid = report.out_of_range_triangles.is_empty()
+        && report.non_finite_vertices.is_empty()
+        && report.inverted_triangles.is_empty()
+        && report.non_manifold_edges.is_empty()
+        && report.non_delaunay_edges.is_empty()
+        && report.overlapping_triangles.is_empty()
+        && report.unused_points.is_empty()
+        && (tris.is_empty() || report.euler_characteristic == 1);
+    report
+}
+
+/// `true` if the interiors of the counter-clockwise triangles intersect:
+/// two convex polygons are disjoint exactly if an edge of one of them has
+/// the other one entirely on its outer side.
+fn interiors_overlap(s: [Pt; 3], t: [Pt; 3]) -> bool {
+    let separates = |a: [Pt; 3], b: [Pt; 3]| {
+        (0..3).any(|k| {
+            let (p, q) = (a[k], a[(k + 1) % 3]);
+            // A NaN orientation does not separate.
+            b.iter().all(|&r| orient2d(p, q, r) <= 0.0)
+        })
+    };
+    !separates(s, t) && !separates(t, s)
+}
+
+/// Pairs of triangles with intersecting interiors, found by bucketing the
+/// bounding boxes in a uniform grid. Degenerate triangles have no interior
+/// and are skipped; clockwise ones are turned around first.
+fn overlapping_pairs(points: &[Pt], tris: &[(usize, [usize; 3])]) -> Vec<u32> {
+    let corners: Vec<(usize, [Pt; 3])> = tris
+        .iter()
+        .filter_map(|&(t, [a, b, c])| {
+            let (pa, pb, pc) = (points[a], points[b], points[c]);
+            match orient2d(pa, pb, pc) {
+                o if o > 0.0 => Some((t, [pa, pb, pc])),
+                o if o < 0.0 => Some((t, [pa, pc, pb])),
+                _ => None,
+            }
+        })
+        .collect();
+    if corners.len() < 2 {
+        return Vec::new();
+    }
+
+    let bbox = |c: &[Pt; 3]| {
+        let min = Pt {
+            x: c[0].x.min(c[1].x).min(c[2].x),
+            y: c[0].y.min(c[1].y).min(c[2].y),
+        };
+        let max = Pt {
+            x: c[0].x.max(c[1].x).max(c[2].x),
+            y: c[0].y.max(c[1].y).max(c[2].y),
+        };
+        (min, max)
+    };
+    let (mut lo, mut hi) = bbox(&corners[0].1);
+    for (_, c) in &corners {
+        let (a, b) = bbox(c);
+        lo = Pt {
+            x: lo.x.min(a.x),
+            y: lo.y.min(a.y),
+        };
+        hi = Pt {
+            x: hi.x.max(b.x),
+            y: hi.y.max(b.y),
+        };
+    }
+    let n = (corners.len() as f64).sqrt().ceil() as usize;
+    let cell = |v: f64, lo: f64, hi: f64| {
+        let f = if hi > lo { (v - lo) / (hi - lo) } else { 0.0 };
+        ((f * n as f64) as usize).min(n - 1)
+    };
+    let mut grid: Vec<Vec<usize>> = vec![Vec::new(); n * n];
+    for (k, (_, c)) in corners.iter().enumerate() {
+        let (a, b) = bbox(c);
+        for j in cell(a.y, lo.y, hi.y)..=cell(b.y, lo.y, hi.y) {
+            for i in cell(a.x, lo.x, hi.x)..=cell(b.x, lo.x, hi.x) {
+                grid[j * n + i].push(k);
+            }
+        }
+    }
+
+    let mut pairs = HashSet::new();
+    for bucket in &grid {
+        for (x, &k) in bucket.iter().enumerate() {
+            for &l in &bucket[x + 1..] {
+                let (s, t) = (corners[k], corners[l]);
+                if interiors_overlap(s.1, t.1) {
+                    pairs.insert((s.0.min(t.0) as u32, s.0.max(t.0) as u32));
+                }
+            }
+        }
+    }
+    let mut pairs: Vec<_> = pairs.into_iter().collect();
+    pairs.sort_unstable();
+    pairs.into_iter().flat_map(|(s, t)| [s, t]).collect()
+}
+
+#[wasm_bindgen]
+impl VoronoiTests {
+    /// Check a Delaunay triangulation of the points, e.g. the `triangles` of
+    /// [`VoronoiTests::delaunay_indices`], which index the input points as
+    /// long as none were merged. Input layout: [x,y,vx,vy,...]
+    pub fn validate_triangulation(points_flat: &[f32], indices: &[u32]) -> ValidationReport {
+        validate(&read_points(points_flat), indices, |_| true)
+    }
+}
+
+#[wasm_bindgen]
+impl DelaunayTriangulation {
+    /// Check the current triangles against all live points.
+    pub fn validate(&self) -> ValidationReport {
+        let indices = self.delaunay_indices();
+        validate(&self.tri.points, &indices, |i| !self.removed[i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voronoi::test_util::pt;
+    use crate::voronoi::triangle_indices;
+    use crate::voronoi::triangulation::Triangulation;
+
+    #[test]
+    fn delaunay_output_is_valid() {
+        let mut points: Vec<Pt> = (0..100)
+            .map(|k| pt((k % 10) as f64, (k / 10) as f64))
+            .collect();
+        points.push(pt(3.0, 4.0));
+        let indices = triangle_indices(&Triangulation::new(&points));
+        let report = validate(&points, &indices, |_| true);
+        assert!(report.valid, "{report:?}");
+        assert_eq!(report.vertices, 100);
+        assert_eq!(report.euler_characteristic, 1);
+        assert_eq!(report.duplicate_points, vec![100, 43]);
+    }
+
+    #[test]
+    fn reports_broken_triangles() {
+        let points = [
+            pt(0.0, 0.0),
+            pt(4.0, 0.0),
+            pt(4.0, 4.0),
+            pt(0.0, 4.0),
+            pt(2.0, 1.0),
+            pt(9.0, 9.0),
+        ];
+        // A fan that skips the inner point, plus a clockwise triangle
+        // overlapping it.
+        let indices = [0, 1, 2, 0, 2, 3, 0, 4, 1];
+        let report = validate(&points, &indices, |_| true);
+        assert!(!report.valid);
+        assert_eq!(report.inverted_triangles, vec![2]);
+        assert_eq!(report.overlapping_triangles, vec![0, 2]);
+        assert_eq!(report.unused_points, vec![5]);
+
+        // A flat rhombus is Delaunay across its short diagonal only.
+        let points = [pt(0.0, 0.0), pt(4.0, -1.0), pt(8.0, 0.0), pt(4.0, 1.0)];
+        let report = validate(&points, &[0, 1, 3, 1, 2, 3], |_| true);
+        assert!(report.valid, "{report:?}");
+        let report = validate(&points, &[0, 1, 2, 0, 2, 3], |_| true);
+        assert_eq!(report.non_delaunay_edges, vec![0, 2]);
+    }
+
+    #[test]
+    fn reports_non_finite_vertices() {
+        let mut points = vec![pt(0.0, 0.0), pt(4.0, 0.0), pt(4.0, 4.0), pt(0.0, 4.0)];
+        // A non-finite point that is not used is fine.
+        points.push(pt(f64::NAN, 1.0));
+        let report = validate(&points, &[0, 1, 2, 0, 2, 3], |_| true);
+        assert!(report.valid, "{report:?}");
+
+        // As a vertex it fails the orientation and in-circle tests.
+        points[3] = pt(f64::NAN, 4.0);
+        let report = validate(&points, &[0, 1, 2, 0, 2, 3], |_| true);
+        assert!(!report.valid);
+        assert_eq!(report.non_finite_vertices, vec![3]);
+        assert_eq!(report.inverted_triangles, vec![1]);
+        assert_eq!(report.non_delaunay_edges, vec![0, 2]);
+        assert!(report.unused_points.is_empty());
+
+        points[3] = pt(0.0, f64::INFINITY);
+        let report = validate(&points, &[0, 1, 2, 0, 2, 3], |_| true);
+        assert_eq!(report.non_finite_vertices, vec![3]);
+        assert!(!report.valid);
+    }
+}
@@ -0,0 +1,440 @@
+//! Weighted Voronoi diagrams.
+//!
+//! In the power diagram site `i` with weight `w_i` (the squared radius of a
+//! circle around it) claims the points `x` with the smallest power distance
+//! `|x - p_i|² - w_i`. Cells are convex and bounded by straight radical
+//! lines, and the neighbouring cells are given by the regular (weighted
+//! Delaunay) triangulation, so they are clipped exactly like the unweighted
+//! cells. A site can be outweighed completely and get an empty cell.
+//!
+//! The additively weighted diagram uses `|x - p_i| - w_i` instead. Its cell
+//! boundaries are hyperbola branches and it has no triangulation to build
+//! on, but every cell is star-shaped around its site, so it is traced by
+//! casting rays from the site and stopping each at the nearest boundary.
+
+use super::cells::{VoronoiCells, clip_half_plane, read_convex_polygon, rect_polygon};
+use super::degenerate::TriangulationError;
+use super::triangulation::{GHOST, Triangulation};
+use super::{Pt, VoronoiTests, read_points, triangle_indices};
+use wasm_bindgen::prelude::*;
+
+/// Upper bound on the rays per additively weighted cell.
+const MAX_RAYS: usize = 4096;
+
+/// Whether a site takes part in a weighted diagram at all.
+fn finite_site(p: Pt, w: f64) -> bool {
+    p.x.is_finite() && p.y.is_finite() && w.is_finite()
+}
+
+/// Cut `poly` down to the points with a smaller power distance to `site`
+/// than to `other`; the weights are the sites' squared radii.
+fn clip_radical(poly: &[Pt], site: (Pt, f64), other: (Pt, f64)) -> Vec<Pt> {
+    let ((p, wp), (q, wq)) = (site, other);
+    let d = q.sub(p);
+    // The radical line crosses p -> q at this fraction of the way.
+    let t = 0.5 + (wp - wq) / (2.0 * d.len2());
+    clip_half_plane(poly, p.add(d.mul(t)), d)
+}
+
+/// Power cell of every point of the regular triangulation `tri`, clipped to
+/// the convex, counter-clockwise polygon `clip`. Hidden, duplicate and
+/// non-finite points get an empty cell.
+pub(crate) fn power_cells(tri: &Triangulation, clip: &[Pt]) -> Vec<Vec<Pt>> {
+    let points = &tri.points;
+    let site = |i: usize| (points[i], tri.weights[i]);
+    let finite = |i: usize| finite_site(points[i], tri.weights[i]);
+    let cell_of = |i: usize, others: &mut dyn Iterator<Item = usize>| {
+        let mut cell = clip.to_vec();
+        for j in others {
+            cell = clip_radical(&cell, site(i), site(j));
+            if cell.is_empty() {
+                break;
+            }
+        }
+        cell
+    };
+    if !tri.has_triangles() {
+        // All sites on one line: without a triangulation to tell the
+        // neighbours, cut every cell by all other sites.
+        let weight = |i: usize| tri.weights[i];
+        return (0..points.len())
+            .map(|i| {
+                if !finite(i) {
+                    return Vec::new();
+                }
+                // Only the heaviest of coinciding sites, the first on ties,
+                // gets a cell.
+                let outweighed = (0..points.len()).filter(|&j| finite(j)).any(|j| {
+                    points[j] == points[i]
+                        && (weight(j) > weight(i) || (weight(j) == weight(i) && j < i))
+                });
+                if outweighed {
+                    return Vec::new();
+                }
+                cell_of(
+                    i,
+                    &mut (0..points.len()).filter(|&j| finite(j) && points[j] != points[i]),
+                )
+            })
+            .collect();
+    }
+    (0..points.len())
+        .map(|i| {
+            if !tri.is_vertex(i) {
+                return Vec::new();
+            }
+            cell_of(i, &mut tri.neighbors(i).filter(|&j| j != GHOST))
+        })
+        .collect()
+}
+
+/// Additively weighted Voronoi cell of every site as a polygon of `samples`
+/// rays (3 to [`MAX_RAYS`]), clipped to the convex, counter-clockwise
+/// polygon `clip`. Sites whose cell is empty, because another site's weight
+/// exceeds theirs by at least the distance between them, get an empty
+/// polygon, as do all but the first of coinciding sites with equal weight
+/// and sites with a non-finite position or weight.
+pub(crate) fn additively_weighted_cells(
+    points: &[Pt],
+    weights: &[f64],
+    clip: &[Pt],
+    samples: usize,
+) -> Vec<Vec<Pt>> {
+    let samples = samples.clamp(3, MAX_RAYS);
+    (0..points.len())
+        .map(|i| {
+            let (p, w) = (points[i], weights[i]);
+            if !finite_site(p, w) {
+                return Vec::new();
+            }
+            let mut rivals = Vec::new();
+            for j in (0..points.len()).filter(|&j| j != i && finite_site(points[j], weights[j])) {
+                let d = points[j].sub(p);
+                let delta = weights[j] - w;
+                let dist = d.len2().sqrt();
+                if dist < delta || (dist == delta && (dist > 0.0 || j < i)) {
+                    return Vec::new();
+                }
+                if dist > -delta {
+                    rivals.push((d, delta));
+                }
+            }
+
+            // Rays longer than twice the farthest clip corner are cut off
+            // outside the clip polygon, so capping them changes nothing.
+            let reach = clip
+                .iter()
+                .map(|c| c.sub(p).len2())
+                .fold(0.0, f64::max)
+                .sqrt()
+                * 2.0;
+            let mut star = Vec::with_capacity(samples);
+            for k in 0..samples {
+                let angle = k as f64 * std::f64::consts::TAU / samples as f64;
+                let u = Pt {
+                    x: angle.cos(),
+                    y: angle.sin(),
+                };
+                // On the ray p + t u the boundary with site j satisfies
+                // |t u - d| = t + delta, i.e. t = (|d|² - delta²) / 2(u·d + delta).
+                let t = rivals
+                    .iter()
+                    .filter_map(|&(d, delta)| {
+                        let denom = 2.0 * (u.dot(d) + delta);
+                        (denom > 0.0).then(|| (d.len2() - delta * delta) / denom)
+                    })
+                    .fold(reach, f64::min);
+                star.push(p.add(u.mul(t)));
+            }
+            let mut cell = star;
+            for (k, &a) in clip.iter().enumerate() {
+                let b = clip[(k + 1) % clip.len()];
+                // Keep the left side of every counter-clockwise clip edge.
+                let outward = Pt {
+                    x: b.y - a.y,
+                    y: a.x - b.x,
+                };
+                cell = clip_half_plane(&cell, a, outward);
+            }
+            cell
+        })
+        .collect()
+}
+
+/// Weights for the points of a flat point buffer; missing ones are zero.
+fn read_weights(weights: &[f32], n: usize) -> Vec<f64> {
+    (0..n)
+        .map(|i| weights.get(i).map_or(0.0, |&w| w as f64))
+        .collect()
+}
+
+fn power_cells_for(points_flat: &[f32], weights: &[f32], clip: &[Pt]) -> VoronoiCells {
+    let points = read_points(points_flat);
+    let weights = read_weights(weights, points.len());
+    let tri = Triangulation::regular(points, &weights);
+    VoronoiCells::from_polygons(&power_cells(&tri, clip))
+}
+
+#[wasm_bindgen]
+impl VoronoiTests {
+    /// Regular (weighted Delaunay) triangulation as index triplets.
+    /// `weights[i]` is the squared radius of site `i`; sites whose power
+    /// cell is empty are left out. Input layout: [x,y,vx,vy,...]
+    pub fn regular_indices(points_flat: &[f32], weights: &[f32]) -> Vec<u32> {
+        let points = read_points(points_flat);
+        let weights = read_weights(weights, points.len());
+        triangle_indices(&Triangulation::regular(points, &weights))
+    }
+
+    /// Power diagram cell of every site, clipped to the rectangle
+    /// `[min_x, max_x] x [min_y, max_y]`. `weights[i]` is the squared radius
+    /// of site `i`; with equal weights this is [`VoronoiTests::voronoi_cells`].
+    pub fn power_cells(
+        points_flat: &[f32],
+        weights: &[f32],
+        min_x: f32,
+        min_y: f32,
+        max_x: f32,
+        max_y: f32,
+    ) -> VoronoiCells {
+        let clip = rect_polygon(min_x as f64, min_y as f64, max_x as f64, max_y as f64);
+        power_cells_for(points_flat, weights, &clip)
+    }
+
+    /// Power diagram cell of every site, clipped to a convex polygon given
+    /// as [x0,y0,x1,y1,...] in either winding order. Fails if the boundary
+    /// is not convex.
+    pub fn power_cells_clipped(
+        points_flat: &[f32],
+        weights: &[f32],
+        boundary: &[f32],
+    ) -> Result<VoronoiCells, TriangulationError> {
+        let clip = read_convex_polygon(boundary)?;
+        Ok(power_cells_for(points_flat, weights, &clip))
+    }
+
+    /// Additively weighted Voronoi cell of every site, clipped to the
+    /// rectangle `[min_x, max_x] x [min_y, max_y]`. Site `i` claims the
+    /// points where `|x - p_i| - weights[i]` is smallest; the curved cell
+    /// boundaries are sampled with `samples` rays per site, at most 4096.
+    pub fn additively_weighted_cells(
+        points_flat: &[f32],
+        weights: &[f32],
+        min_x: f32,
+        min_y: f32,
+        max_x: f32,
+        max_y: f32,
+        samples: u32,
+    ) -> VoronoiCells {
+        let points = read_points(points_flat);
+        let weights = read_weights(weights, points.len());
+        let clip = rect_polygon(min_x as f64, min_y as f64, max_x as f64, max_y as f64);
+        let cells = additively_weighted_cells(&points, &weights, &clip, samples as usize);
+        VoronoiCells::from_polygons(&cells)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voronoi::cells::{clipped_cells, signed_area};
+    use crate::voronoi::predicates::orient2d;
+    use crate::voronoi::test_util::{inside, pt, random_points};
+
+    fn contains(poly: &[Pt], p: Pt) -> bool {
+        poly.len() >= 3
+            && (0..poly.len()).all(|k| orient2d(poly[k], poly[(k + 1) % poly.len()], p) >= 0.0)
+    }
+
+    fn area(cells: &[Vec<Pt>]) -> f64 {
+        cells
+            .iter()
+            .filter(|c| c.len() >= 3)
+            .map(|c| signed_area(c))
+            .sum()
+    }
+
+    #[test]
+    fn power_cells_match_brute_force() {
+        let clip = rect_polygon(-10.0, -10.0, 110.0, 110.0);
+        let mut hidden = 0;
+        for seed in 1..20u32 {
+            let points = random_points(150, seed, 100.0);
+            let mut s = seed * 7 + 1;
+            let weights: Vec<f64> = (0..points.len())
+                .map(|_| crate::rand::frand01(&mut s) as f64 * 60.0)
+                .collect();
+            let tri = Triangulation::regular(points.clone(), &weights);
+            let cells = power_cells(&tri, &clip);
+            assert!((area(&cells) - 120.0 * 120.0).abs() < 1e-6, "seed {seed}");
+            // A vertex's cell can still lie outside the clip polygon.
+            for (i, cell) in cells.iter().enumerate() {
+                assert!(tri.is_vertex(i) || cell.is_empty(), "seed {seed} site {i}");
+            }
+            hidden += cells.iter().filter(|c| c.is_empty()).count();
+
+            // A point lies in the cell of the site with the smallest power
+            // distance, up to ties.
+            for _ in 0..200 {
+                let q = pt(
+                    crate::rand::frand01(&mut s) as f64 * 120.0 - 10.0,
+                    crate::rand::frand01(&mut s) as f64 * 120.0 - 10.0,
+                );
+                let power = |i: usize| q.sub(points[i]).len2() - weights[i];
+                let best = (0..points.len())
+                    .min_by(|&a, &b| power(a).total_cmp(&power(b)))
+                    .unwrap();
+                let found = (0..points.len())
+                    .any(|i| (power(i) - power(best)).abs() < 1e-6 && contains(&cells[i], q));
+                assert!(found, "seed {seed}: {q:?} is not in the cell of {best}");
+            }
+        }
+        assert!(hidden > 0, "no site was outweighed");
+
+        // A light site between heavy ones is hidden.
+        let points = vec![pt(0.0, 0.0), pt(10.0, 0.0), pt(5.0, 9.0), pt(5.0, 3.0)];
+        let tri = Triangulation::regular(points, &[100.0, 100.0, 100.0, 0.0]);
+        assert!(!tri.is_vertex(3));
+        assert!(power_cells(&tri, &clip)[3].is_empty());
+    }
+
+    #[test]
+    fn equal_weights_give_voronoi_cells() {
+        let clip = rect_polygon(0.0, 0.0, 50.0, 50.0);
+        let points = random_points(100, 3, 50.0);
+        let weighted = power_cells(&Triangulation::regular(points.clone(), &[5.0; 100]), &clip);
+        let plain = clipped_cells(&Triangulation::new(&points), &clip);
+        for (a, b) in weighted.iter().zip(&plain) {
+            assert_eq!(a.len(), b.len());
+            assert!((signed_area(a) - signed_area(b)).abs() < 1e-9);
+            let centroid = |c: &[Pt]| c.iter().fold(pt(0.0, 0.0), |s, &p| s.add(p));
+            assert!(centroid(a).sub(centroid(b)).len2() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn heaviest_coinciding_site_wins() {
+        let clip = rect_polygon(0.0, 0.0, 100.0, 100.0);
+        let mut points = random_points(30, 9, 100.0);
+        let mut weights = vec![10.0; 30];
+        // Site 30 coincides with site 5 and is heavier, site 31 with 12 and
+        // lighter, site 32 with 20 and as heavy.
+        points.extend([points[5], points[12], points[20]]);
+        weights.extend([50.0, 1.0, 10.0]);
+        let cells = power_cells(&Triangulation::regular(points.clone(), &weights), &clip);
+        for (i, empty) in [
+            (5, true),
+            (30, false),
+            (12, false),
+            (31, true),
+            (20, false),
+            (32, true),
+        ] {
+            assert_eq!(cells[i].is_empty(), empty, "site {i}");
+        }
+
+        // The heavier site claims what it would claim alone.
+        let mut alone = weights[..30].to_vec();
+        alone[5] = 50.0;
+        let single = power_cells(
+            &Triangulation::regular(points[..30].to_vec(), &alone),
+            &clip,
+        );
+        assert!((signed_area(&cells[30]) - signed_area(&single[5])).abs() < 1e-9);
+
+        // The same without triangles.
+        let line = vec![
+            pt(10.0, 50.0),
+            pt(50.0, 50.0),
+            pt(50.0, 50.0),
+            pt(90.0, 50.0),
+        ];
+        let tri = Triangulation::regular(line, &[0.0, 1.0, 400.0, 0.0]);
+        let cells = power_cells(&tri, &clip);
+        assert!(cells[1].is_empty() && !cells[2].is_empty());
+        assert!((area(&cells) - 10_000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn additively_weighted_cells_match_brute_force() {
+        let clip = rect_polygon(0.0, 0.0, 100.0, 100.0);
+        for seed in 1..6u32 {
+            let points = random_points(12, seed, 100.0);
+            let mut s = seed * 5 + 3;
+            let weights: Vec<f64> = (0..points.len())
+                .map(|_| crate::rand::frand01(&mut s) as f64 * 15.0)
+                .collect();
+            let cells = additively_weighted_cells(&points, &weights, &clip, 720);
+            let total = area(&cells);
+            assert!((total - 100.0 * 100.0).abs() < 50.0, "seed {seed}: {total}");
+            for k in 0..40 * 40 {
+                let q = pt((k % 40) as f64 * 2.5 + 1.25, (k / 40) as f64 * 2.5 + 1.25);
+                let dist = |i: usize| q.sub(points[i]).len2().sqrt() - weights[i];
+                let mut order: Vec<usize> = (0..points.len()).collect();
+                order.sort_by(|&a, &b| dist(a).total_cmp(&dist(b)));
+                // The sampled boundary may cut corners near the true one.
+                if dist(order[1]) - dist(order[0]) < 0.5 {
+                    continue;
+                }
+                assert!(
+                    inside(std::slice::from_ref(&cells[order[0]]), q),
+                    "seed {seed}: {q:?} is not in the cell of {}",
+                    order[0]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn dominated_sites_get_empty_cells() {
+        let clip = rect_polygon(0.0, 0.0, 100.0, 100.0);
+        let points = [
+            pt(50.0, 50.0),
+            // Outweighed by more than the distance,
+            pt(60.0, 50.0),
+            // by exactly the distance,
+            pt(80.0, 50.0),
+            // and a copy of the next site with the same weight.
+            pt(20.0, 80.0),
+            pt(20.0, 80.0),
+            pt(20.0, 20.0),
+        ];
+        let weights = [30.0, 5.0, 0.0, 2.0, 2.0, 0.0];
+        let cells = additively_weighted_cells(&points, &weights, &clip, 64);
+        let empty: Vec<bool> = cells.iter().map(Vec::is_empty).collect();
+        assert_eq!(empty, [false, true, true, false, true, false]);
+
+        // The number of rays is capped.
+        let cells = additively_weighted_cells(&points[..1], &[0.0], &clip, usize::MAX);
+        assert_eq!(cells[0].len(), 4);
+        let one = pt(50.0, 50.0);
+        let star = additively_weighted_cells(&[one, pt(70.0, 50.0)], &[0.0; 2], &clip, usize::MAX);
+        assert!(star[0].len() <= MAX_RAYS + 4);
+    }
+
+    #[test]
+    fn non_finite_sites_get_empty_cells() {
+        let clip = rect_polygon(0.0, 0.0, 100.0, 100.0);
+        let mut points = random_points(10, 4, 100.0);
+        points.push(pt(f64::NAN, 50.0));
+        let mut weights = vec![3.0; 11];
+        weights[2] = f64::NAN;
+        let cells = additively_weighted_cells(&points, &weights, &clip, 720);
+        assert!(cells[2].is_empty() && cells[10].is_empty());
+        let total = area(&cells);
+        assert!((total - 100.0 * 100.0).abs() < 50.0, "{total}");
+
+        // The power cells of sites on one line.
+        let line = vec![
+            pt(10.0, 50.0),
+            pt(30.0, 50.0),
+            pt(50.0, 50.0),
+            pt(f64::INFINITY, 50.0),
+            pt(90.0, 50.0),
+        ];
+        let tri = Triangulation::regular(line, &[0.0, f64::NAN, 0.0, 0.0, 0.0]);
+        let cells = power_cells(&tri, &clip);
+        assert!(cells[1].is_empty() && cells[3].is_empty());
+        assert!((area(&cells) - 10_000.0).abs() < 1e-9);
+    }
+}